@@ -179,15 +179,19 @@ mod tests {
         let album_storage: AlbumStorage = Arc::new(Mutex::new(HashMap::new()));
 
         bot.dependencies(deps![album_storage]);
-        // ATTENTION!!! This is NOT how it would work in real life. Because we are simulating
-        // an update, there is no distribution function, so in reality you would see that behaviour
-        // only if you set the distribution function to always return None.
-        // I don't consider it a big deal, the default distribution function is more for real
-        // users, who may send multiple messages in a row, for testing no distribution function is
-        // fine
-        bot.dispatch_and_check_last_text("Detected 3 messages without media group!")
-            .await;
-        // In reality it would be 3 messages with the text "Detected 1 messages without media group!"
+        // The updates go through a real dispatcher with our distribution function, so the
+        // messages without a media group are handled one by one, just like in real life
+        bot.dispatcher(|builder| builder.distribution_function(default_distribution_function));
+        bot.dispatch().await;
+
+        let responses = bot.get_responses();
+        assert_eq!(responses.sent_messages.len(), 3);
+        for message in responses.sent_messages {
+            assert_eq!(
+                message.text(),
+                Some("Detected 1 messages without media group!")
+            );
+        }
     }
 
     #[tokio::test]
@@ -1,9 +1,13 @@
+// Every test here uses the same redis storage and the same chat, so they are run serially.
+// Bots themselves don't need that, every bot has its own fake server.
 use crate::{get_bot_storage, handler_tree::handler_tree, text, State};
 
+use serial_test::serial;
 use teloxide::dptree::deps;
 use teloxide_tests::{MockBot, MockCallbackQuery, MockMessagePhoto, MockMessageText};
 
 #[tokio::test]
+#[serial]
 async fn test_start() {
     let bot = MockBot::new(MockMessageText::new().text("/start"), handler_tree());
 
@@ -26,6 +30,7 @@ async fn test_start() {
 }
 
 #[tokio::test]
+#[serial]
 async fn test_what_is_the_first_number() {
     let bot = MockBot::new(MockCallbackQuery::new().data("add"), handler_tree());
 
@@ -42,6 +47,7 @@ async fn test_what_is_the_first_number() {
 }
 
 #[tokio::test]
+#[serial]
 async fn test_message_errors() {
     let bot = MockBot::new(MockMessageText::new().text("not a number"), handler_tree());
 
@@ -62,6 +68,7 @@ async fn test_message_errors() {
 }
 
 #[tokio::test]
+#[serial]
 async fn test_what_is_the_second_number() {
     let bot = MockBot::new(MockMessageText::new().text("5"), handler_tree());
 
@@ -82,6 +89,7 @@ async fn test_what_is_the_second_number() {
 }
 
 #[tokio::test]
+#[serial]
 async fn test_add_result() {
     let bot = MockBot::new(MockMessageText::new().text("4"), handler_tree());
 
@@ -100,6 +108,7 @@ async fn test_add_result() {
 }
 
 #[tokio::test]
+#[serial]
 async fn test_subtract_result() {
     let bot = MockBot::new(MockMessageText::new().text("4"), handler_tree());
 
@@ -1,5 +1,5 @@
 use crate::{add_deep_link, handler_tree::handler_tree, text, State};
-use teloxide::{dispatching::dialogue::InMemStorage, dptree::deps};
+use teloxide::{dispatching::dialogue::InMemStorage, dptree::deps, types::MessageEntityKind};
 use teloxide_tests::{MockBot, MockMessagePhoto, MockMessageText};
 
 #[tokio::test]
@@ -56,8 +56,13 @@ async fn test_send_message() {
 
     assert_eq!(
         sent_message.text().unwrap(),
-        text::YOU_HAVE_A_NEW_MESSAGE.replace("{message}", "I love you!")
+        "You have a new message!\n\nI love you!"
     );  // Just checking that the text and sender are correct
+    // The html markup is turned into entities, like in telegram
+    assert_eq!(
+        sent_message.entities().unwrap()[0].kind,
+        MessageEntityKind::Italic
+    );
     assert_eq!(sent_message.chat.id.0, 987654321);
 
     assert_eq!(
@@ -10,18 +10,16 @@ async fn download_document(bot: Bot, message: Message) -> HandlerResult {
     if let Some(document) = message.document() {
         let file = bot.get_file(document.file.id.clone()).await?; // Get the file
 
-        // Make the destination file
-        let mut dest = tokio::fs::File::create("test.txt").await?;
+        // Download the file right into memory
+        let mut content = Vec::new();
+        bot.download_file(&file.path, &mut content).await?;
 
-        // Download the file (its always a dummy, but it works as a check)
-        bot.download_file(&file.path, &mut dest).await?;
-
-        // Just a check that the file was downloaded
-        assert!(tokio::fs::read_to_string("test.txt").await.is_ok());
-
-        bot.send_message(message.chat.id, "Downloaded!").await?;
-
-        tokio::fs::remove_file("test.txt").await?; // Just a cleanup
+        let lines = String::from_utf8_lossy(&content).lines().count();
+        bot.send_message(
+            message.chat.id,
+            format!("Downloaded! The file has {lines} lines"),
+        )
+        .await?;
     } else {
         bot.send_message(message.chat.id, "Not a document").await?;
     }
@@ -59,7 +57,16 @@ mod tests {
 
     #[tokio::test]
     async fn test_download_document_and_check() {
+        let document = MockMessageDocument::new().file_content("first\nsecond\nthird");
+        let bot = MockBot::new(document, handler_tree());
+        bot.dispatch_and_check_last_text("Downloaded! The file has 3 lines")
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_download_empty_document() {
         let bot = MockBot::new(MockMessageDocument::new(), handler_tree());
-        bot.dispatch_and_check_last_text("Downloaded!").await;
+        bot.dispatch_and_check_last_text("Downloaded! The file has 0 lines")
+            .await;
     }
 }
@@ -232,10 +232,12 @@ mod tests {
 
     use super::*;
     use dptree::deps;
+    use serial_test::serial;
     use teloxide::types::ReplyMarkup;
     use teloxide_tests::{MockBot, MockMessageDocument, MockMessageText, MockUser};
 
     #[tokio::test]
+    #[serial]
     async fn test_start() {
         let bot = MockBot::new(MockMessageText::new().text("/start"), handler_tree());
         // This fully deletes the user to test its creation
@@ -260,6 +262,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[serial]
     async fn test_cancel() {
         // Cancel is universal, so only one test is needed
         let bot = MockBot::new(MockMessageText::new().text("/cancel"), handler_tree());
@@ -287,6 +290,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[serial]
     async fn test_profile() {
         let bot = MockBot::new(
             MockMessageText::new().text(keyboards::PROFILE_BUTTON),
@@ -312,6 +316,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[serial]
     async fn test_change_nickname() {
         let bot = MockBot::new(
             MockMessageText::new().text(keyboards::CHANGE_NICKNAME_BUTTON),
@@ -326,6 +331,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[serial]
     async fn test_changed_nickname() {
         let bot = MockBot::new(MockMessageText::new().text("nickname"), handler_tree());
 
@@ -356,11 +362,11 @@ mod tests {
     }
 
     #[tokio::test]
+    #[serial]
     async fn test_delete_phrase() {
         // !!!!!!!! VERY IMPORTANT !!!!!!!!! Because the tests are run async, the database queries
-        // might race condition themselves. Because of that, write all db queries __after__
-        // creating the bot. Bot creation makes a lock that prevents other tests from starting,
-        // before this one finishes
+        // might race condition themselves. Every bot has its own fake server, but the database
+        // is shared, so all of the tests that use it are marked with #[serial]
         let bot = MockBot::new(
             MockMessageText::new().text(keyboards::REMOVE_PHRASE_BUTTON),
             handler_tree(),
@@ -391,6 +397,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[serial]
     async fn test_deleted_phrase() {
         let bot = MockBot::new(MockMessageText::new().text("not a number"), handler_tree());
 
@@ -439,6 +446,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[serial]
     async fn test_add_phrase() {
         let bot = MockBot::new(
             MockMessageText::new().text(keyboards::ADD_PHRASE_BUTTON),
@@ -456,6 +464,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[serial]
     async fn test_what_is_new_phrase_text() {
         let bot = MockBot::new(MockMessageText::new().text("🤗🤗🤗🤗"), handler_tree());
 
@@ -476,6 +485,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[serial]
     async fn test_what_is_new_phrase_bot_text() {
         let bot = MockBot::new(MockMessageText::new().text("hug"), handler_tree());
 
@@ -496,6 +506,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[serial]
     async fn test_added_phrase() {
         let bot = MockBot::new(
             MockMessageText::new().text("(me) hugged (reply)"),
@@ -518,8 +529,6 @@ mod tests {
             responses.sent_messages.first().unwrap().text(),
             Some(text::added_phrase("🤗", "hug", "(me) hugged (reply)")).as_deref()
         );
-        // It is better to make the tests regarding the database __before__ the bot goes out of
-        // scope, otherwise nasty race conditions can happen
         assert_eq!(
             db::get_user_phrases(MockUser::ID as i64)
                 .unwrap()
@@ -528,7 +537,5 @@ mod tests {
                 .text,
             "hug".to_string()
         );
-        drop(bot); // This is here not to ensure the bot is dropped, but to ensure that it is
-                   // __not__ dropped before.
     }
 }
@@ -49,10 +49,12 @@ pub async fn bot_phrase(bot: Bot, msg: Message) -> HandlerResult {
 
 #[cfg(test)]
 mod tests {
-    use crate::{db, handler_tree::handler_tree, text};
+    use crate::{db, handler_tree::handler_tree};
+    use serial_test::serial;
     use teloxide_tests::{MockBot, MockGroupChat, MockMessageText, MockUser};
 
     #[tokio::test]
+    #[serial]
     async fn test_phrase() {
         let chat = MockGroupChat::new().build();
 
@@ -68,8 +70,8 @@ mod tests {
             .reply_to_message(reply_message.build());
 
         let bot = MockBot::new(me_message, handler_tree());
-        // !!! IMPORTANT !!! same as in test_delete_phrase in private handlers, do all db stuff
-        // after creating the bot
+        // !!! IMPORTANT !!! same as in test_delete_phrase in private handlers, the database is
+        // shared between the tests, so this test is #[serial]
         db::full_user_redeletion(1234, Some("nick1".to_string()));
         db::full_user_redeletion(5678, Some("nick2".to_string()));
         db::create_phrase(
@@ -80,19 +82,13 @@ mod tests {
         )
         .unwrap();
 
-        // Parse mode doesn't yet work, so it still has link text. But that isn't a problem!
-        bot.dispatch_and_check_last_text(
-            &format!(
-                "🤗 | {} hugged {}",
-                text::make_link("nick1".to_string(), 1234),
-                text::make_link("nick2".to_string(), 5678)
-            )
-            .to_string(),
-        )
-        .await;
+        // The links are parsed into text mentions, so only their text is left
+        bot.dispatch_and_check_last_text("🤗 | nick1 hugged nick2")
+            .await;
     }
 
     #[tokio::test]
+    #[serial]
     async fn test_no_phrase() {
         let chat = MockGroupChat::new().build();
 
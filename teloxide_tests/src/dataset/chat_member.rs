@@ -0,0 +1,325 @@
+use crate::proc_macros::Changeable;
+use chrono::{DateTime, Utc};
+use core::sync::atomic::{AtomicI32, Ordering};
+use teloxide::types::*;
+
+use super::{MockGroupChat, MockMe, MockUser};
+
+fn administrator_with_all_rights() -> Administrator {
+    Administrator {
+        custom_title: None,
+        is_anonymous: false,
+        can_be_edited: false,
+        can_manage_chat: true,
+        can_change_info: true,
+        can_post_messages: true,
+        can_edit_messages: true,
+        can_delete_messages: true,
+        can_post_stories: true,
+        can_edit_stories: true,
+        can_delete_stories: true,
+        can_manage_video_chats: true,
+        can_invite_users: true,
+        can_restrict_members: true,
+        can_pin_messages: true,
+        can_manage_topics: true,
+        can_promote_members: true,
+    }
+}
+
+macro_rules! ChatMemberUpdated {
+    (
+        #[derive($($derive:meta),*)]
+        $pub:vis struct $name:ident;
+        update_kind: $update_kind:ident,
+    ) => {
+        #[derive($($derive),*)]
+        $pub struct $name {  // Both ChatMember and MyChatMember updates are the same struct
+            pub chat: Chat,
+            pub from: User,
+            pub date: DateTime<Utc>,
+            /// The user, whose status was changed
+            pub user: User,
+            pub old_chat_member: ChatMemberKind,
+            pub new_chat_member: ChatMemberKind,
+            pub invite_link: Option<ChatInviteLink>,
+            pub via_chat_folder_invite_link: bool,
+        }
+
+        impl $name {
+            pub const VIA_CHAT_FOLDER_INVITE_LINK: bool = false;
+
+            pub(crate) fn new_chat_member_updated(user: User) -> Self {
+                Self {
+                    chat: MockGroupChat::new().build(),
+                    from: MockUser::new().build(),
+                    date: Utc::now(),
+                    user,
+                    old_chat_member: ChatMemberKind::Left,
+                    new_chat_member: ChatMemberKind::Member,
+                    invite_link: None,
+                    via_chat_folder_invite_link: Self::VIA_CHAT_FOLDER_INVITE_LINK,
+                }
+            }
+
+            /// Makes the update a transition from `Left` to `Member`
+            pub fn joined(mut self) -> Self {
+                self.old_chat_member = ChatMemberKind::Left;
+                self.new_chat_member = ChatMemberKind::Member;
+                self
+            }
+
+            /// Makes the update a transition from `Member` to `Left`
+            pub fn left(mut self) -> Self {
+                self.old_chat_member = ChatMemberKind::Member;
+                self.new_chat_member = ChatMemberKind::Left;
+                self
+            }
+
+            /// Makes the update a transition from `Member` to `Administrator` with all of the
+            /// administrator rights
+            pub fn promoted(mut self) -> Self {
+                self.old_chat_member = ChatMemberKind::Member;
+                self.new_chat_member = ChatMemberKind::Administrator(administrator_with_all_rights());
+                self
+            }
+
+            /// Makes the update a transition from `Member` to `Banned` forever
+            pub fn banned(mut self) -> Self {
+                self.old_chat_member = ChatMemberKind::Member;
+                self.new_chat_member = ChatMemberKind::Banned(Banned {
+                    until_date: UntilDate::Forever,
+                });
+                self
+            }
+
+            pub(crate) fn build_chat_member_updated(self) -> ChatMemberUpdated {
+                ChatMemberUpdated {
+                    chat: self.chat,
+                    from: self.from,
+                    date: self.date,
+                    old_chat_member: ChatMember {
+                        user: self.user.clone(),
+                        kind: self.old_chat_member,
+                    },
+                    new_chat_member: ChatMember {
+                        user: self.user,
+                        kind: self.new_chat_member,
+                    },
+                    invite_link: self.invite_link,
+                    via_chat_folder_invite_link: self.via_chat_folder_invite_link,
+                }
+            }
+        }
+
+        impl crate::dataset::IntoUpdate for $name {
+            fn into_update(self, id: AtomicI32) -> Vec<Update> {
+                vec![Update {
+                    id: UpdateId(id.fetch_add(1, Ordering::Relaxed) as u32),
+                    kind: UpdateKind::$update_kind(self.build()),
+                }]
+            }
+        }
+    }
+}
+
+ChatMemberUpdated! {
+    #[derive(Changeable, Clone)]
+    pub struct MockChatMemberUpdated;
+    update_kind: ChatMember,
+}
+
+impl MockChatMemberUpdated {
+    /// Creates a new easily changable chat member updated builder. By default it is a
+    /// `MockUser` joining a `MockGroupChat`
+    ///
+    /// # Example
+    /// ```
+    /// let chat_member_updated = teloxide_tests::MockChatMemberUpdated::new()
+    ///     .left()
+    ///     .build();
+    /// assert!(chat_member_updated.old_chat_member.is_present());
+    /// assert!(!chat_member_updated.new_chat_member.is_present());
+    /// ```
+    ///
+    pub fn new() -> Self {
+        Self::new_chat_member_updated(MockUser::new().build())
+    }
+
+    /// Builds the chat member updated
+    ///
+    /// # Example
+    /// ```
+    /// let mock_chat_member_updated = teloxide_tests::MockChatMemberUpdated::new();
+    /// let chat_member_updated = mock_chat_member_updated.build();
+    /// assert_eq!(chat_member_updated.new_chat_member.user.id.0, teloxide_tests::MockUser::ID);  // The user is a MockUser by default
+    /// ```
+    ///
+    pub fn build(self) -> ChatMemberUpdated {
+        self.build_chat_member_updated()
+    }
+}
+
+ChatMemberUpdated! {
+    #[derive(Changeable, Clone)]
+    pub struct MockMyChatMemberUpdated;
+    update_kind: MyChatMember,
+}
+
+impl MockMyChatMemberUpdated {
+    /// Creates a new easily changable my chat member updated builder. By default it is the
+    /// bot (`MockMe`) being added to a `MockGroupChat` by a `MockUser`
+    ///
+    /// # Example
+    /// ```
+    /// let my_chat_member_updated = teloxide_tests::MockMyChatMemberUpdated::new()
+    ///     .promoted()
+    ///     .build();
+    /// assert!(my_chat_member_updated.new_chat_member.is_administrator());
+    /// ```
+    ///
+    pub fn new() -> Self {
+        Self::new_chat_member_updated(MockMe::new().build().user)
+    }
+
+    /// Builds the my chat member updated
+    ///
+    /// # Example
+    /// ```
+    /// let mock_my_chat_member_updated = teloxide_tests::MockMyChatMemberUpdated::new();
+    /// let my_chat_member_updated = mock_my_chat_member_updated.build();
+    /// assert_eq!(my_chat_member_updated.new_chat_member.user.id.0, teloxide_tests::MockMe::ID);  // The user is the bot by default
+    /// ```
+    ///
+    pub fn build(self) -> ChatMemberUpdated {
+        self.build_chat_member_updated()
+    }
+}
+
+#[derive(Changeable, Clone)]
+pub struct MockChatMember {
+    pub user: User,
+    pub kind: ChatMemberKind,
+}
+
+impl MockChatMember {
+    /// Creates a new easily changable chat member builder. By default it is a `MockUser`, that
+    /// is a regular member of the chat. The members are given to the bot with
+    /// `MockBot::chat_members`
+    ///
+    /// # Example
+    /// ```
+    /// let chat_member = teloxide_tests::MockChatMember::new()
+    ///     .user(teloxide_tests::MockUser::new().id(2).build())
+    ///     .build();
+    /// assert_eq!(chat_member.user.id.0, 2);
+    /// assert!(chat_member.kind.is_member());
+    /// ```
+    ///
+    pub fn new() -> Self {
+        Self {
+            user: MockUser::new().build(),
+            kind: ChatMemberKind::Member,
+        }
+    }
+
+    /// Creates a chat member builder of the owner of the chat
+    ///
+    /// # Example
+    /// ```
+    /// let chat_member = teloxide_tests::MockChatMember::owner().build();
+    /// assert!(chat_member.kind.is_owner());
+    /// ```
+    ///
+    pub fn owner() -> Self {
+        Self::new().kind(ChatMemberKind::Owner(Owner {
+            custom_title: None,
+            is_anonymous: false,
+        }))
+    }
+
+    /// Creates a chat member builder of an administrator with all of the administrator rights
+    ///
+    /// # Example
+    /// ```
+    /// let chat_member = teloxide_tests::MockChatMember::administrator().build();
+    /// assert!(chat_member.kind.can_restrict_members());
+    /// ```
+    ///
+    pub fn administrator() -> Self {
+        Self::new().kind(ChatMemberKind::Administrator(
+            administrator_with_all_rights(),
+        ))
+    }
+
+    /// Creates a chat member builder of a member that can't send anything, forever
+    ///
+    /// # Example
+    /// ```
+    /// let chat_member = teloxide_tests::MockChatMember::restricted().build();
+    /// assert!(chat_member.kind.is_restricted());
+    /// ```
+    ///
+    pub fn restricted() -> Self {
+        Self::new().kind(ChatMemberKind::Restricted(Restricted {
+            until_date: UntilDate::Forever,
+            is_member: true,
+            can_send_messages: false,
+            can_send_audios: false,
+            can_send_documents: false,
+            can_send_photos: false,
+            can_send_videos: false,
+            can_send_video_notes: false,
+            can_send_voice_notes: false,
+            can_send_other_messages: false,
+            can_add_web_page_previews: false,
+            can_change_info: false,
+            can_invite_users: false,
+            can_pin_messages: false,
+            can_manage_topics: false,
+            can_send_polls: false,
+        }))
+    }
+
+    /// Creates a chat member builder of a user that is banned forever
+    ///
+    /// # Example
+    /// ```
+    /// let chat_member = teloxide_tests::MockChatMember::banned().build();
+    /// assert!(chat_member.kind.is_banned());
+    /// ```
+    ///
+    pub fn banned() -> Self {
+        Self::new().kind(ChatMemberKind::Banned(Banned {
+            until_date: UntilDate::Forever,
+        }))
+    }
+
+    /// Creates a chat member builder of a user that left the chat
+    ///
+    /// # Example
+    /// ```
+    /// let chat_member = teloxide_tests::MockChatMember::left().build();
+    /// assert!(!chat_member.kind.is_present());
+    /// ```
+    ///
+    pub fn left() -> Self {
+        Self::new().kind(ChatMemberKind::Left)
+    }
+
+    /// Builds the chat member
+    ///
+    /// # Example
+    /// ```
+    /// let mock_chat_member = teloxide_tests::MockChatMember::new();
+    /// let chat_member = mock_chat_member.build();
+    /// assert_eq!(chat_member.user.id.0, teloxide_tests::MockUser::ID);  // The user is a MockUser by default
+    /// ```
+    ///
+    pub fn build(self) -> ChatMember {
+        ChatMember {
+            user: self.user,
+            kind: self.kind,
+        }
+    }
+}
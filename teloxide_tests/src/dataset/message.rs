@@ -1,4 +1,4 @@
-use super::chat::MockPrivateChat;
+use super::chat::{MockPrivateChat, MockSupergroupChat};
 use crate::proc_macros::Changeable;
 use chrono::{DateTime, Utc};
 use core::sync::atomic::{AtomicI32, Ordering};
@@ -22,6 +22,9 @@ macro_rules! Message {
             pub chat: Chat,
             pub is_topic_message: bool,
             pub via_bot: Option<User>,
+            /// Content of the file in the message, that the bot will get when downloading it
+            pub file_content: Option<Vec<u8>>,
+            update_kind: MessageUpdateKind,
             $($fpub $field : $type,)*
         }
         impl $name {
@@ -36,6 +39,8 @@ macro_rules! Message {
                     chat: MockPrivateChat::new().build(),
                     is_topic_message: false,
                     via_bot: None,
+                    file_content: None,
+                    update_kind: MessageUpdateKind::Message,
                     $($field,)*
                 }
             }
@@ -53,6 +58,63 @@ macro_rules! Message {
                     kind: message_kind,
                 }
             }
+
+            /// Makes the update an `UpdateKind::EditedMessage` instead of `UpdateKind::Message`.
+            /// If the message has no `edit_date`, it is set to the current time.
+            ///
+            /// # Example
+            /// ```
+            /// use teloxide_tests::IntoUpdate;
+            /// let update = teloxide_tests::MockMessageText::new()
+            ///     .as_edited()
+            ///     .into_update(1.into())[0]
+            ///     .clone();
+            /// match update.kind {
+            ///     teloxide::types::UpdateKind::EditedMessage(message) => assert!(message.edit_date().is_some()),
+            ///     _ => panic!("The update should be an edited message"),
+            /// }
+            /// ```
+            pub fn as_edited(mut self) -> Self {
+                self.update_kind = MessageUpdateKind::EditedMessage;
+                self
+            }
+
+            /// Makes the update an `UpdateKind::ChannelPost` instead of `UpdateKind::Message`.
+            /// The chat isn't changed, so set it to a `MockChannelChat` yourself.
+            ///
+            /// # Example
+            /// ```
+            /// use teloxide_tests::{IntoUpdate, MockChannelChat, MockMessageText};
+            /// let update = MockMessageText::new()
+            ///     .chat(MockChannelChat::new().build())
+            ///     .as_channel_post()
+            ///     .into_update(1.into())[0]
+            ///     .clone();
+            /// assert!(matches!(update.kind, teloxide::types::UpdateKind::ChannelPost(_)));
+            /// ```
+            pub fn as_channel_post(mut self) -> Self {
+                self.update_kind = MessageUpdateKind::ChannelPost;
+                self
+            }
+
+            /// Makes the update an `UpdateKind::EditedChannelPost` instead of
+            /// `UpdateKind::Message`. If the message has no `edit_date`, it is set to the current
+            /// time.
+            ///
+            /// # Example
+            /// ```
+            /// use teloxide_tests::{IntoUpdate, MockChannelChat, MockMessageText};
+            /// let update = MockMessageText::new()
+            ///     .chat(MockChannelChat::new().build())
+            ///     .as_edited_channel_post()
+            ///     .into_update(1.into())[0]
+            ///     .clone();
+            /// assert!(matches!(update.kind, teloxide::types::UpdateKind::EditedChannelPost(_)));
+            /// ```
+            pub fn as_edited_channel_post(mut self) -> Self {
+                self.update_kind = MessageUpdateKind::EditedChannelPost;
+                self
+            }
         }
 
         impl crate::dataset::IntoUpdate for $name {
@@ -70,17 +132,52 @@ macro_rules! Message {
             /// ```
             ///
             fn into_update(self, id: AtomicI32) -> Vec<Update> {
+                let update_kind = self.update_kind;
+                let mut message = self.build();
+                if let (
+                    MessageUpdateKind::EditedMessage | MessageUpdateKind::EditedChannelPost,
+                    MessageKind::Common(ref mut message_common),
+                ) = (update_kind, &mut message.kind)
+                {
+                    message_common.edit_date.get_or_insert(Utc::now());
+                }
                 vec![Update {
                     id: UpdateId(id.fetch_add(1, Ordering::Relaxed) as u32),
-                    kind: UpdateKind::Message(self.build()),
+                    kind: match update_kind {
+                        MessageUpdateKind::Message => UpdateKind::Message(message),
+                        MessageUpdateKind::EditedMessage => UpdateKind::EditedMessage(message),
+                        MessageUpdateKind::ChannelPost => UpdateKind::ChannelPost(message),
+                        MessageUpdateKind::EditedChannelPost => {
+                            UpdateKind::EditedChannelPost(message)
+                        }
+                    },
                 }]
             }
+
+            fn file_contents(&self) -> std::collections::HashMap<String, Vec<u8>> {
+                let Some(content) = self.file_content.clone() else {
+                    return std::collections::HashMap::new();
+                };
+                let message = serde_json::to_value(self.clone().build()).unwrap();
+                crate::mock_bot::find_file(message)
+                    .map(|file| std::collections::HashMap::from([(file.id, content)]))
+                    .unwrap_or_default()
+            }
         }
     }
 }
 
 pub(crate) use Message;
 
+/// What kind of update the mocked message becomes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum MessageUpdateKind {
+    Message,
+    EditedMessage,
+    ChannelPost,
+    EditedChannelPost,
+}
+
 // More messages like Webapp data is needed
 
 Message! {
@@ -127,3 +224,319 @@ impl MockMessageDice {
         }))
     }
 }
+
+Message! {
+    #[derive(Changeable, Clone)]
+    pub struct MockMessageInvoice {
+        pub title: String,
+        pub description: String,
+        pub start_parameter: String,
+        pub currency: String,
+        pub total_amount: u32,
+    }
+}
+
+impl MockMessageInvoice {
+    pub const TITLE: &'static str = "Title";
+    pub const DESCRIPTION: &'static str = "Description";
+    pub const START_PARAMETER: &'static str = "";
+    pub const CURRENCY: &'static str = "XTR";
+    pub const TOTAL_AMOUNT: u32 = 100;
+
+    /// Creates a new easily changable message invoice builder
+    ///
+    /// # Example
+    /// ```
+    /// let message = teloxide_tests::MockMessageInvoice::new()
+    ///     .title("Some item")
+    ///     .build();
+    /// assert_eq!(message.invoice().unwrap().title, "Some item");
+    /// ```
+    ///
+    pub fn new() -> Self {
+        Self::new_message(
+            Self::TITLE.to_string(),
+            Self::DESCRIPTION.to_string(),
+            Self::START_PARAMETER.to_string(),
+            Self::CURRENCY.to_string(),
+            Self::TOTAL_AMOUNT,
+        )
+    }
+
+    /// Builds the message invoice
+    ///
+    /// # Example
+    /// ```
+    /// let mock_message = teloxide_tests::MockMessageInvoice::new();
+    /// let message = mock_message.build();
+    /// assert_eq!(message.invoice().unwrap().currency, teloxide_tests::MockMessageInvoice::CURRENCY);  // CURRENCY is a default value
+    /// ```
+    ///
+    pub fn build(self) -> Message {
+        self.clone().build_message(MessageKind::Invoice(MessageInvoice {
+            invoice: Invoice {
+                title: self.title,
+                description: self.description,
+                start_parameter: self.start_parameter,
+                currency: self.currency,
+                total_amount: self.total_amount,
+            },
+        }))
+    }
+}
+
+Message! {
+    #[derive(Changeable, Clone)]
+    pub struct MockMessageSuccessfulPayment {
+        pub currency: Currency,
+        pub total_amount: u32,
+        pub invoice_payload: String,
+        pub shipping_option_id: Option<String>,
+        pub order_info: OrderInfo,
+        pub telegram_payment_charge_id: String,
+        pub provider_payment_charge_id: String,
+    }
+}
+
+impl MockMessageSuccessfulPayment {
+    pub const CURRENCY: Currency = Currency::USD;
+    pub const TOTAL_AMOUNT: u32 = 100;
+    pub const INVOICE_PAYLOAD: &'static str = "payload";
+    pub const TELEGRAM_PAYMENT_CHARGE_ID: &'static str = "telegram_payment_charge_id";
+    pub const PROVIDER_PAYMENT_CHARGE_ID: &'static str = "provider_payment_charge_id";
+
+    /// Creates a new easily changable message successful payment builder. After it is
+    /// dispatched, the bot can refund the payment by its `telegram_payment_charge_id`.
+    ///
+    /// teloxide's `Currency` doesn't have Telegram Stars (`XTR`), so some other currency has to
+    /// stand in for them.
+    ///
+    /// # Example
+    /// ```
+    /// let message = teloxide_tests::MockMessageSuccessfulPayment::new()
+    ///     .invoice_payload("item_1")
+    ///     .build();
+    /// assert_eq!(message.successful_payment().unwrap().invoice_payload, "item_1");
+    /// ```
+    ///
+    pub fn new() -> Self {
+        Self::new_message(
+            Self::CURRENCY,
+            Self::TOTAL_AMOUNT,
+            Self::INVOICE_PAYLOAD.to_string(),
+            None,
+            OrderInfo::default(),
+            Self::TELEGRAM_PAYMENT_CHARGE_ID.to_string(),
+            Self::PROVIDER_PAYMENT_CHARGE_ID.to_string(),
+        )
+    }
+
+    /// Builds the message successful payment
+    ///
+    /// # Example
+    /// ```
+    /// let mock_message = teloxide_tests::MockMessageSuccessfulPayment::new();
+    /// let message = mock_message.build();
+    /// assert_eq!(message.successful_payment().unwrap().total_amount, teloxide_tests::MockMessageSuccessfulPayment::TOTAL_AMOUNT);  // TOTAL_AMOUNT is a default value
+    /// ```
+    ///
+    pub fn build(self) -> Message {
+        self.clone()
+            .build_message(MessageKind::SuccessfulPayment(MessageSuccessfulPayment {
+                successful_payment: SuccessfulPayment {
+                    currency: self.currency,
+                    total_amount: self.total_amount,
+                    invoice_payload: self.invoice_payload,
+                    shipping_option_id: self.shipping_option_id,
+                    order_info: self.order_info,
+                    telegram_payment_charge_id: self.telegram_payment_charge_id,
+                    provider_payment_charge_id: self.provider_payment_charge_id,
+                },
+            }))
+    }
+}
+
+Message! {
+    #[derive(Changeable, Clone)]
+    pub struct MockMessageForumTopicCreated {
+        pub name: String,
+        pub icon_color: u32,
+        pub icon_custom_emoji_id: Option<String>,
+    }
+}
+
+impl MockMessageForumTopicCreated {
+    pub const NAME: &'static str = "Topic";
+    pub const ICON_COLOR: u32 = 0x6FB9F0;
+
+    /// Creates a new easily changable forum topic created message builder. It is sent to a
+    /// forum `MockSupergroupChat`, and the topic's thread id is the `thread_id` of the message,
+    /// or its id if there is no `thread_id`. The `icon_color` is in RGB format, like `0x6FB9F0`
+    ///
+    /// # Example
+    /// ```
+    /// let message = teloxide_tests::MockMessageForumTopicCreated::new()
+    ///     .name("Ticket #1")
+    ///     .build();
+    /// match message.kind {
+    ///     teloxide::types::MessageKind::ForumTopicCreated(created) => {
+    ///         assert_eq!(created.forum_topic_created.name, "Ticket #1")
+    ///     }
+    ///     _ => panic!("The message should be a forum topic created message"),
+    /// }
+    /// ```
+    ///
+    pub fn new() -> Self {
+        Self::new_message(Self::NAME.to_string(), Self::ICON_COLOR, None)
+            .chat(MockSupergroupChat::new().is_forum(true).build())
+            .is_topic_message(true)
+    }
+
+    /// Builds the forum topic created message
+    ///
+    /// # Example
+    /// ```
+    /// let mock_message = teloxide_tests::MockMessageForumTopicCreated::new();
+    /// let message = mock_message.build();
+    /// assert!(message.is_topic_message);
+    /// assert_eq!(message.forum_topic_created().unwrap().name, teloxide_tests::MockMessageForumTopicCreated::NAME);
+    /// ```
+    ///
+    pub fn build(self) -> Message {
+        let [_, red, green, blue] = self.icon_color.to_be_bytes();
+        self.clone()
+            .build_message(MessageKind::ForumTopicCreated(MessageForumTopicCreated {
+                forum_topic_created: ForumTopicCreated {
+                    name: self.name,
+                    icon_color: [red, green, blue],
+                    icon_custom_emoji_id: self.icon_custom_emoji_id,
+                },
+            }))
+    }
+}
+
+Message! {
+    #[derive(Changeable, Clone)]
+    pub struct MockMessageForumTopicEdited {
+        pub name: Option<String>,
+        pub icon_custom_emoji_id: Option<String>,
+    }
+}
+
+impl MockMessageForumTopicEdited {
+    /// Creates a new easily changable forum topic edited message builder. Set its `thread_id`
+    /// to the topic that was edited
+    ///
+    /// # Example
+    /// ```
+    /// let message = teloxide_tests::MockMessageForumTopicEdited::new()
+    ///     .name("New name")
+    ///     .build();
+    /// assert!(message.is_topic_message);
+    /// ```
+    ///
+    pub fn new() -> Self {
+        Self::new_message(None, None)
+            .chat(MockSupergroupChat::new().is_forum(true).build())
+            .is_topic_message(true)
+    }
+
+    /// Builds the forum topic edited message
+    ///
+    /// # Example
+    /// ```
+    /// let mock_message = teloxide_tests::MockMessageForumTopicEdited::new();
+    /// let message = mock_message.build();
+    /// assert!(message.forum_topic_edited().is_some());
+    /// ```
+    ///
+    pub fn build(self) -> Message {
+        self.clone()
+            .build_message(MessageKind::ForumTopicEdited(MessageForumTopicEdited {
+                forum_topic_edited: ForumTopicEdited {
+                    name: self.name,
+                    icon_custom_emoji_id: self.icon_custom_emoji_id,
+                },
+            }))
+    }
+}
+
+Message! {
+    #[derive(Changeable, Clone)]
+    pub struct MockMessageForumTopicClosed {}
+}
+
+impl MockMessageForumTopicClosed {
+    /// Creates a new easily changable forum topic closed message builder. Set its `thread_id`
+    /// to the topic that was closed
+    ///
+    /// # Example
+    /// ```
+    /// let message = teloxide_tests::MockMessageForumTopicClosed::new()
+    ///     .thread_id(teloxide::types::ThreadId(teloxide::types::MessageId(2)))
+    ///     .build();
+    /// assert_eq!(message.thread_id.unwrap().0.0, 2);
+    /// ```
+    ///
+    pub fn new() -> Self {
+        Self::new_message()
+            .chat(MockSupergroupChat::new().is_forum(true).build())
+            .is_topic_message(true)
+    }
+
+    /// Builds the forum topic closed message
+    ///
+    /// # Example
+    /// ```
+    /// let mock_message = teloxide_tests::MockMessageForumTopicClosed::new();
+    /// let message = mock_message.build();
+    /// assert!(message.forum_topic_closed().is_some());
+    /// ```
+    ///
+    pub fn build(self) -> Message {
+        self.clone()
+            .build_message(MessageKind::ForumTopicClosed(MessageForumTopicClosed {
+                forum_topic_closed: ForumTopicClosed {},
+            }))
+    }
+}
+
+Message! {
+    #[derive(Changeable, Clone)]
+    pub struct MockMessageForumTopicReopened {}
+}
+
+impl MockMessageForumTopicReopened {
+    /// Creates a new easily changable forum topic reopened message builder. Set its
+    /// `thread_id` to the topic that was reopened
+    ///
+    /// # Example
+    /// ```
+    /// let message = teloxide_tests::MockMessageForumTopicReopened::new()
+    ///     .thread_id(teloxide::types::ThreadId(teloxide::types::MessageId(2)))
+    ///     .build();
+    /// assert_eq!(message.thread_id.unwrap().0.0, 2);
+    /// ```
+    ///
+    pub fn new() -> Self {
+        Self::new_message()
+            .chat(MockSupergroupChat::new().is_forum(true).build())
+            .is_topic_message(true)
+    }
+
+    /// Builds the forum topic reopened message
+    ///
+    /// # Example
+    /// ```
+    /// let mock_message = teloxide_tests::MockMessageForumTopicReopened::new();
+    /// let message = mock_message.build();
+    /// assert!(message.forum_topic_reopened().is_some());
+    /// ```
+    ///
+    pub fn build(self) -> Message {
+        self.clone()
+            .build_message(MessageKind::ForumTopicReopened(MessageForumTopicReopened {
+                forum_topic_reopened: ForumTopicReopened {},
+            }))
+    }
+}
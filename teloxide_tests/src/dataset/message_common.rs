@@ -1,4 +1,4 @@
-use super::message::Message;
+use super::message::{Message, MessageUpdateKind};
 use super::{chat::MockPrivateChat, MockUser};
 use super::{MockLocation, MockPhotoSize, MockVideo};
 use crate::proc_macros::Changeable;
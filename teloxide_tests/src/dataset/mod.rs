@@ -1,20 +1,25 @@
 //! A set of mocked structs for testing purposes. Read more in teloxide_tests crate.
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicI32, Ordering};
 
 use chrono::{DateTime, Utc};
 use mime::Mime;
 use proc_macros::Changeable;
 use teloxide::types::{
-    ChatFullInfo, ChatPhoto, FileMeta, LinkPreviewOptions, Location, Me, PhotoSize, Seconds, Update, User, UserId, Video
+    ChatAdministratorRights, ChatFullInfo, ChatPhoto, FileMeta, LinkPreviewOptions, Location, Me, PhotoSize, Seconds, Update, User, UserId, Video
 };
 pub mod chat;
+pub mod chat_member;
 
 pub mod message;
 pub mod message_common;
+pub mod poll;
 pub mod queries;
 pub use chat::*;
+pub use chat_member::*;
 pub use message::*;
 pub use message_common::*;
+pub use poll::*;
 pub use queries::*;
 use teloxide_tests_macros as proc_macros;
 #[cfg(test)]
@@ -23,6 +28,11 @@ mod tests;
 pub trait IntoUpdate {
     /// Converts the mocked struct into an update vector, incrementing the id by 1
     fn into_update(self, id: AtomicI32) -> Vec<Update>;
+
+    /// Contents of the files in the mocked struct by their file_id, so the bot can download them
+    fn file_contents(&self) -> HashMap<String, Vec<u8>> {
+        HashMap::new()
+    }
 }
 
 impl<T> IntoUpdate for Vec<T>
@@ -35,6 +45,10 @@ where
             .flatten()
             .collect()
     }
+
+    fn file_contents(&self) -> HashMap<String, Vec<u8>> {
+        self.iter().flat_map(|u| u.file_contents()).collect()
+    }
 }
 
 //
@@ -117,6 +131,7 @@ pub struct MockMe {
     pub can_join_groups: bool,
     pub can_read_all_group_messages: bool,
     pub supports_inline_queries: bool,
+    pub administrator_rights: Option<ChatAdministratorRights>,
 }
 
 impl MockMe {
@@ -130,7 +145,9 @@ impl MockMe {
     pub const CAN_READ_ALL_GROUP_MESSAGES: bool = false;
     pub const SUPPORTS_INLINE_QUERIES: bool = false;
 
-    /// Creates a new easily changable me builder
+    /// Creates a new easily changable me builder. The `administrator_rights` are the rights of
+    /// the bot in the group chats, if it isn't in the `MockBot::chat_members` of the chat. By
+    /// default the bot has all of the rights
     ///
     /// # Examples
     /// ```
@@ -151,6 +168,7 @@ impl MockMe {
             can_join_groups: Self::CAN_JOIN_GROUPS,
             can_read_all_group_messages: Self::CAN_READ_ALL_GROUP_MESSAGES,
             supports_inline_queries: Self::SUPPORTS_INLINE_QUERIES,
+            administrator_rights: None,
         }
     }
 
@@ -0,0 +1,230 @@
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use crate::proc_macros::Changeable;
+use chrono::{DateTime, Utc};
+use teloxide::types::*;
+
+use super::MockUser;
+
+#[derive(Changeable, Clone)]
+pub struct MockPoll {
+    pub id: String,
+    pub question: String,
+    pub options: Vec<PollOption>,
+    pub is_closed: bool,
+    pub total_voter_count: u32,
+    pub is_anonymous: bool,
+    pub poll_type: PollType,
+    pub allows_multiple_answers: bool,
+    pub correct_option_id: Option<u8>,
+    pub explanation: Option<String>,
+    pub explanation_entities: Option<Vec<MessageEntity>>,
+    pub open_period: Option<Seconds>,
+    pub close_date: Option<DateTime<Utc>>,
+}
+
+impl MockPoll {
+    pub const ID: &'static str = "12345";
+    pub const QUESTION: &'static str = "Question";
+    pub const IS_CLOSED: bool = false;
+    pub const TOTAL_VOTER_COUNT: u32 = 0;
+    pub const IS_ANONYMOUS: bool = true;
+    pub const POLL_TYPE: PollType = PollType::Regular;
+    pub const ALLOWS_MULTIPLE_ANSWERS: bool = false;
+
+    /// Creates a new easily changable poll builder
+    ///
+    /// # Examples
+    /// ```
+    /// let poll = teloxide_tests::MockPoll::new()
+    ///     .question("What is the answer?")
+    ///     .build();
+    /// assert_eq!(poll.question, "What is the answer?");
+    /// ```
+    ///
+    pub fn new() -> Self {
+        Self {
+            id: Self::ID.to_string(),
+            question: Self::QUESTION.to_string(),
+            options: vec![],
+            is_closed: Self::IS_CLOSED,
+            total_voter_count: Self::TOTAL_VOTER_COUNT,
+            is_anonymous: Self::IS_ANONYMOUS,
+            poll_type: Self::POLL_TYPE,
+            allows_multiple_answers: Self::ALLOWS_MULTIPLE_ANSWERS,
+            correct_option_id: None,
+            explanation: None,
+            explanation_entities: None,
+            open_period: None,
+            close_date: None,
+        }
+    }
+
+    /// Creates a poll builder with the state of the poll, for example from a poll that the
+    /// bot sent, so it can be changed and sent back as an update
+    ///
+    /// # Examples
+    /// ```
+    /// let poll = teloxide_tests::MockPoll::new().id("1").build();
+    /// let closed_poll = teloxide_tests::MockPoll::from_poll(&poll)
+    ///     .is_closed(true)
+    ///     .build();
+    /// assert_eq!(closed_poll.id, "1");
+    /// assert!(closed_poll.is_closed);
+    /// ```
+    ///
+    pub fn from_poll(poll: &Poll) -> Self {
+        let poll = poll.clone();
+        Self {
+            id: poll.id,
+            question: poll.question,
+            options: poll.options,
+            is_closed: poll.is_closed,
+            total_voter_count: poll.total_voter_count,
+            is_anonymous: poll.is_anonymous,
+            poll_type: poll.poll_type,
+            allows_multiple_answers: poll.allows_multiple_answers,
+            correct_option_id: poll.correct_option_id,
+            explanation: poll.explanation,
+            explanation_entities: poll.explanation_entities,
+            open_period: poll.open_period,
+            close_date: poll.close_date,
+        }
+    }
+
+    /// Builds the poll
+    ///
+    /// # Example
+    /// ```
+    /// let mock_poll = teloxide_tests::MockPoll::new();
+    /// let poll = mock_poll.build();
+    /// assert_eq!(poll.id, teloxide_tests::MockPoll::ID);  // ID is a default value
+    /// ```
+    ///
+    pub fn build(self) -> Poll {
+        Poll {
+            id: self.id,
+            question: self.question,
+            options: self.options,
+            is_closed: self.is_closed,
+            total_voter_count: self.total_voter_count,
+            is_anonymous: self.is_anonymous,
+            poll_type: self.poll_type,
+            allows_multiple_answers: self.allows_multiple_answers,
+            correct_option_id: self.correct_option_id,
+            explanation: self.explanation,
+            explanation_entities: self.explanation_entities,
+            open_period: self.open_period,
+            close_date: self.close_date,
+        }
+    }
+}
+
+impl crate::dataset::IntoUpdate for MockPoll {
+    /// Converts the MockPoll into an updates vector. If the bot sent a poll with the same id,
+    /// the stored poll is replaced by this one.
+    ///
+    /// # Example
+    /// ```
+    /// use teloxide_tests::IntoUpdate;
+    /// let mock_poll = teloxide_tests::MockPoll::new();
+    /// let update = mock_poll.clone().into_update(1.into())[0].clone();
+    /// assert_eq!(update.id, teloxide::types::UpdateId(1));
+    /// assert_eq!(update.kind, teloxide::types::UpdateKind::Poll(
+    ///     mock_poll.build())
+    /// );
+    /// ```
+    ///
+    fn into_update(self, id: AtomicI32) -> Vec<Update> {
+        vec![Update {
+            id: UpdateId(id.fetch_add(1, Ordering::Relaxed) as u32),
+            kind: UpdateKind::Poll(self.build()),
+        }]
+    }
+}
+
+#[derive(Changeable, Clone)]
+pub struct MockPollAnswer {
+    pub poll_id: String,
+    pub voter: Voter,
+    pub option_ids: Vec<u8>,
+}
+
+impl MockPollAnswer {
+    pub const POLL_ID: &'static str = MockPoll::ID;
+
+    /// Creates a new easily changable poll answer builder. By default it is a `MockUser`,
+    /// voting for the first option
+    ///
+    /// # Examples
+    /// ```
+    /// let poll_answer = teloxide_tests::MockPollAnswer::new()
+    ///     .option_ids(vec![1])
+    ///     .build();
+    /// assert_eq!(poll_answer.option_ids, vec![1]);
+    /// ```
+    ///
+    pub fn new() -> Self {
+        Self {
+            poll_id: Self::POLL_ID.to_string(),
+            voter: Voter::User(MockUser::new().build()),
+            option_ids: vec![0],
+        }
+    }
+
+    /// Creates a poll answer builder for the poll, for example the one that the bot sent.
+    /// Dispatching the answer updates the votes of the poll that the bot knows about.
+    ///
+    /// # Examples
+    /// ```
+    /// let poll = teloxide_tests::MockPoll::new().id("1").build();
+    /// let poll_answer = teloxide_tests::MockPollAnswer::to(&poll)
+    ///     .option_ids(vec![1])
+    ///     .build();
+    /// assert_eq!(poll_answer.poll_id, "1");
+    /// ```
+    ///
+    pub fn to(poll: &Poll) -> Self {
+        Self::new().poll_id(poll.id.clone())
+    }
+
+    /// Builds the poll answer
+    ///
+    /// # Example
+    /// ```
+    /// let mock_poll_answer = teloxide_tests::MockPollAnswer::new();
+    /// let poll_answer = mock_poll_answer.build();
+    /// assert_eq!(poll_answer.poll_id, teloxide_tests::MockPollAnswer::POLL_ID);  // POLL_ID is a default value
+    /// ```
+    ///
+    pub fn build(self) -> PollAnswer {
+        PollAnswer {
+            poll_id: self.poll_id,
+            voter: self.voter,
+            option_ids: self.option_ids,
+        }
+    }
+}
+
+impl crate::dataset::IntoUpdate for MockPollAnswer {
+    /// Converts the MockPollAnswer into an updates vector. If the bot sent the poll, its
+    /// votes are updated.
+    ///
+    /// # Example
+    /// ```
+    /// use teloxide_tests::IntoUpdate;
+    /// let mock_poll_answer = teloxide_tests::MockPollAnswer::new();
+    /// let update = mock_poll_answer.clone().into_update(1.into())[0].clone();
+    /// assert_eq!(update.id, teloxide::types::UpdateId(1));
+    /// assert_eq!(update.kind, teloxide::types::UpdateKind::PollAnswer(
+    ///     mock_poll_answer.build())
+    /// );
+    /// ```
+    ///
+    fn into_update(self, id: AtomicI32) -> Vec<Update> {
+        vec![Update {
+            id: UpdateId(id.fetch_add(1, Ordering::Relaxed) as u32),
+            kind: UpdateKind::PollAnswer(self.build()),
+        }]
+    }
+}
@@ -125,4 +125,321 @@ impl crate::dataset::IntoUpdate for MockCallbackQuery {
     }
 }
 
-// Add more queries here like ShippingQuery, PreCheckoutQuery etc.
+#[derive(Changeable, Clone)]
+pub struct MockInlineQuery {
+    pub id: String,
+    pub from: User,
+    pub location: Option<Location>,
+    pub query: String,
+    pub offset: String,
+    pub chat_type: Option<ChatType>,
+}
+
+impl MockInlineQuery {
+    pub const ID: &'static str = "id";
+    pub const QUERY: &'static str = "query";
+    pub const OFFSET: &'static str = "";
+
+    /// Creates a new easily changable inline query builder
+    ///
+    /// # Examples
+    /// ```
+    /// let inline_query = teloxide_tests::MockInlineQuery::new()
+    ///     .query("some query")
+    ///     .build();
+    /// assert_eq!(inline_query.query, "some query");
+    /// ```
+    ///
+    pub fn new() -> Self {
+        Self {
+            id: Self::ID.to_string(),
+            from: MockUser::new().build(),
+            location: None,
+            query: Self::QUERY.to_string(),
+            offset: Self::OFFSET.to_string(),
+            chat_type: Some(ChatType::Private),
+        }
+    }
+
+    /// Builds the inline query
+    ///
+    /// # Example
+    /// ```
+    /// let mock_inline_query = teloxide_tests::MockInlineQuery::new();
+    /// let inline_query = mock_inline_query.build();
+    /// assert_eq!(inline_query.id, teloxide_tests::MockInlineQuery::ID);  // ID is a default value
+    /// ```
+    ///
+    pub fn build(self) -> InlineQuery {
+        InlineQuery {
+            id: self.id,
+            from: self.from,
+            location: self.location,
+            query: self.query,
+            offset: self.offset,
+            chat_type: self.chat_type,
+        }
+    }
+}
+
+impl crate::dataset::IntoUpdate for MockInlineQuery {
+    /// Converts the MockInlineQuery into an updates vector
+    ///
+    /// # Example
+    /// ```
+    /// use teloxide_tests::IntoUpdate;
+    /// let mock_inline_query = teloxide_tests::MockInlineQuery::new();
+    /// let update = mock_inline_query.clone().into_update(1.into())[0].clone();
+    /// assert_eq!(update.id, teloxide::types::UpdateId(1));
+    /// assert_eq!(update.kind, teloxide::types::UpdateKind::InlineQuery(
+    ///     mock_inline_query.build())
+    /// );
+    /// ```
+    ///
+    fn into_update(self, id: AtomicI32) -> Vec<Update> {
+        vec![Update {
+            id: UpdateId(id.fetch_add(1, Ordering::Relaxed) as u32),
+            kind: UpdateKind::InlineQuery(self.build()),
+        }]
+    }
+}
+
+#[derive(Changeable, Clone)]
+pub struct MockChosenInlineResult {
+    pub result_id: String,
+    pub from: User,
+    pub location: Option<Location>,
+    pub inline_message_id: Option<String>,
+    pub query: String,
+}
+
+impl MockChosenInlineResult {
+    pub const RESULT_ID: &'static str = "result_id";
+    pub const QUERY: &'static str = "query";
+
+    /// Creates a new easily changable chosen inline result builder
+    ///
+    /// # Examples
+    /// ```
+    /// let chosen_inline_result = teloxide_tests::MockChosenInlineResult::new()
+    ///     .result_id("some_result")
+    ///     .build();
+    /// assert_eq!(chosen_inline_result.result_id, "some_result");
+    /// ```
+    ///
+    pub fn new() -> Self {
+        Self {
+            result_id: Self::RESULT_ID.to_string(),
+            from: MockUser::new().build(),
+            location: None,
+            inline_message_id: None,
+            query: Self::QUERY.to_string(),
+        }
+    }
+
+    /// Builds the chosen inline result
+    ///
+    /// # Example
+    /// ```
+    /// let mock_chosen_inline_result = teloxide_tests::MockChosenInlineResult::new();
+    /// let chosen_inline_result = mock_chosen_inline_result.build();
+    /// assert_eq!(chosen_inline_result.result_id, teloxide_tests::MockChosenInlineResult::RESULT_ID);  // RESULT_ID is a default value
+    /// ```
+    ///
+    pub fn build(self) -> ChosenInlineResult {
+        ChosenInlineResult {
+            result_id: self.result_id,
+            from: self.from,
+            location: self.location,
+            inline_message_id: self.inline_message_id,
+            query: self.query,
+        }
+    }
+}
+
+impl crate::dataset::IntoUpdate for MockChosenInlineResult {
+    /// Converts the MockChosenInlineResult into an updates vector
+    ///
+    /// # Example
+    /// ```
+    /// use teloxide_tests::IntoUpdate;
+    /// let mock_chosen_inline_result = teloxide_tests::MockChosenInlineResult::new();
+    /// let update = mock_chosen_inline_result.clone().into_update(1.into())[0].clone();
+    /// assert_eq!(update.id, teloxide::types::UpdateId(1));
+    /// assert_eq!(update.kind, teloxide::types::UpdateKind::ChosenInlineResult(
+    ///     mock_chosen_inline_result.build())
+    /// );
+    /// ```
+    ///
+    fn into_update(self, id: AtomicI32) -> Vec<Update> {
+        vec![Update {
+            id: UpdateId(id.fetch_add(1, Ordering::Relaxed) as u32),
+            kind: UpdateKind::ChosenInlineResult(self.build()),
+        }]
+    }
+}
+
+#[derive(Changeable, Clone)]
+pub struct MockShippingQuery {
+    pub id: String,
+    pub from: User,
+    pub invoice_payload: String,
+    pub shipping_address: ShippingAddress,
+}
+
+impl MockShippingQuery {
+    pub const ID: &'static str = "id";
+    pub const INVOICE_PAYLOAD: &'static str = "payload";
+
+    /// Creates a new easily changable shipping query builder
+    ///
+    /// # Examples
+    /// ```
+    /// let shipping_query = teloxide_tests::MockShippingQuery::new()
+    ///     .invoice_payload("item_1")
+    ///     .build();
+    /// assert_eq!(shipping_query.invoice_payload, "item_1");
+    /// ```
+    ///
+    pub fn new() -> Self {
+        Self {
+            id: Self::ID.to_string(),
+            from: MockUser::new().build(),
+            invoice_payload: Self::INVOICE_PAYLOAD.to_string(),
+            shipping_address: ShippingAddress {
+                country_code: CountryCode::US,
+                state: "State".to_string(),
+                city: "City".to_string(),
+                street_line1: "Street 1".to_string(),
+                street_line2: "".to_string(),
+                post_code: "12345".to_string(),
+            },
+        }
+    }
+
+    /// Builds the shipping query
+    ///
+    /// # Example
+    /// ```
+    /// let mock_shipping_query = teloxide_tests::MockShippingQuery::new();
+    /// let shipping_query = mock_shipping_query.build();
+    /// assert_eq!(shipping_query.id, teloxide_tests::MockShippingQuery::ID);  // ID is a default value
+    /// ```
+    ///
+    pub fn build(self) -> ShippingQuery {
+        ShippingQuery {
+            id: self.id,
+            from: self.from,
+            invoice_payload: self.invoice_payload,
+            shipping_address: self.shipping_address,
+        }
+    }
+}
+
+impl crate::dataset::IntoUpdate for MockShippingQuery {
+    /// Converts the MockShippingQuery into an updates vector
+    ///
+    /// # Example
+    /// ```
+    /// use teloxide_tests::IntoUpdate;
+    /// let mock_shipping_query = teloxide_tests::MockShippingQuery::new();
+    /// let update = mock_shipping_query.clone().into_update(1.into())[0].clone();
+    /// assert_eq!(update.id, teloxide::types::UpdateId(1));
+    /// assert_eq!(update.kind, teloxide::types::UpdateKind::ShippingQuery(
+    ///     mock_shipping_query.build())
+    /// );
+    /// ```
+    ///
+    fn into_update(self, id: AtomicI32) -> Vec<Update> {
+        vec![Update {
+            id: UpdateId(id.fetch_add(1, Ordering::Relaxed) as u32),
+            kind: UpdateKind::ShippingQuery(self.build()),
+        }]
+    }
+}
+
+#[derive(Changeable, Clone)]
+pub struct MockPreCheckoutQuery {
+    pub id: String,
+    pub from: User,
+    pub currency: Currency,
+    pub total_amount: u32,
+    pub invoice_payload: String,
+    pub shipping_option_id: Option<String>,
+    pub order_info: OrderInfo,
+}
+
+impl MockPreCheckoutQuery {
+    pub const ID: &'static str = "id";
+    pub const CURRENCY: Currency = Currency::USD;
+    pub const TOTAL_AMOUNT: u32 = 100;
+    pub const INVOICE_PAYLOAD: &'static str = "payload";
+
+    /// Creates a new easily changable pre-checkout query builder.
+    ///
+    /// teloxide's `Currency` doesn't have Telegram Stars (`XTR`), so some other currency has to
+    /// stand in for them.
+    ///
+    /// # Examples
+    /// ```
+    /// let pre_checkout_query = teloxide_tests::MockPreCheckoutQuery::new()
+    ///     .total_amount(50)
+    ///     .build();
+    /// assert_eq!(pre_checkout_query.total_amount, 50);
+    /// ```
+    ///
+    pub fn new() -> Self {
+        Self {
+            id: Self::ID.to_string(),
+            from: MockUser::new().build(),
+            currency: Self::CURRENCY,
+            total_amount: Self::TOTAL_AMOUNT,
+            invoice_payload: Self::INVOICE_PAYLOAD.to_string(),
+            shipping_option_id: None,
+            order_info: OrderInfo::default(),
+        }
+    }
+
+    /// Builds the pre-checkout query
+    ///
+    /// # Example
+    /// ```
+    /// let mock_pre_checkout_query = teloxide_tests::MockPreCheckoutQuery::new();
+    /// let pre_checkout_query = mock_pre_checkout_query.build();
+    /// assert_eq!(pre_checkout_query.id, teloxide_tests::MockPreCheckoutQuery::ID);  // ID is a default value
+    /// ```
+    ///
+    pub fn build(self) -> PreCheckoutQuery {
+        PreCheckoutQuery {
+            id: self.id,
+            from: self.from,
+            currency: self.currency,
+            total_amount: self.total_amount,
+            invoice_payload: self.invoice_payload,
+            shipping_option_id: self.shipping_option_id,
+            order_info: self.order_info,
+        }
+    }
+}
+
+impl crate::dataset::IntoUpdate for MockPreCheckoutQuery {
+    /// Converts the MockPreCheckoutQuery into an updates vector
+    ///
+    /// # Example
+    /// ```
+    /// use teloxide_tests::IntoUpdate;
+    /// let mock_pre_checkout_query = teloxide_tests::MockPreCheckoutQuery::new();
+    /// let update = mock_pre_checkout_query.clone().into_update(1.into())[0].clone();
+    /// assert_eq!(update.id, teloxide::types::UpdateId(1));
+    /// assert_eq!(update.kind, teloxide::types::UpdateKind::PreCheckoutQuery(
+    ///     mock_pre_checkout_query.build())
+    /// );
+    /// ```
+    ///
+    fn into_update(self, id: AtomicI32) -> Vec<Update> {
+        vec![Update {
+            id: UpdateId(id.fetch_add(1, Ordering::Relaxed) as u32),
+            kind: UpdateKind::PreCheckoutQuery(self.build()),
+        }]
+    }
+}
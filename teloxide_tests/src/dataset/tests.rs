@@ -2,7 +2,10 @@ use crate::dataset::*;
 use crate::proc_macros::Changeable;
 use teloxide::{
     dispatching::dialogue::GetChatId,
-    types::{ChatId, MessageEntity, MessageId, True, UpdateId, UserId},
+    types::{
+        ChatId, ChatKind, ChatPublic, MessageEntity, MessageId, PublicChatKind, True, UpdateId,
+        UpdateKind, UserId,
+    },
 };
 
 #[derive(Changeable)]
@@ -225,6 +228,16 @@ fn test_message_common_document() {
     );
 }
 
+#[test]
+fn test_message_file_contents() {
+    let message = MockMessageDocument::new().file_content("content");
+    assert_eq!(
+        message.file_contents().get(MockMessageDocument::FILE_ID),
+        Some(&b"content".to_vec())
+    );
+    assert!(MockMessageDocument::new().file_contents().is_empty());
+}
+
 #[test]
 fn test_message_common_game() {
     let message = MockMessageGame::new();
@@ -376,3 +389,142 @@ fn test_callback_query() {
     assert_eq!(query_object.id, MockCallbackQuery::ID);
     assert_eq!(query_object.from.first_name, MockUser::FIRST_NAME);
 }
+
+#[test]
+fn test_inline_query() {
+    let query = MockInlineQuery::new();
+    let query_object = query.build();
+    assert_eq!(query_object.id, MockInlineQuery::ID);
+    assert_eq!(query_object.query, MockInlineQuery::QUERY);
+    assert_eq!(query_object.from.first_name, MockUser::FIRST_NAME);
+}
+
+#[test]
+fn test_chosen_inline_result() {
+    let result = MockChosenInlineResult::new();
+    let result_object = result.build();
+    assert_eq!(result_object.result_id, MockChosenInlineResult::RESULT_ID);
+    assert_eq!(result_object.from.first_name, MockUser::FIRST_NAME);
+}
+
+#[test]
+fn test_chat_member_updated() {
+    let updated = MockChatMemberUpdated::new().banned();
+    let updated_object = updated.build();
+    assert_eq!(updated_object.chat.id.0, MockGroupChat::ID);
+    assert_eq!(updated_object.new_chat_member.user.id.0, MockUser::ID);
+    assert!(updated_object.old_chat_member.is_member());
+    assert!(updated_object.new_chat_member.is_banned());
+}
+
+#[test]
+fn test_my_chat_member_updated() {
+    let updated = MockMyChatMemberUpdated::new();
+    let updated_object = updated.build();
+    assert_eq!(updated_object.from.id.0, MockUser::ID);
+    assert_eq!(updated_object.new_chat_member.user.id.0, MockMe::ID);
+    assert!(updated_object.old_chat_member.is_left());
+    assert!(updated_object.new_chat_member.is_member());
+}
+
+#[test]
+fn test_message_as_edited() {
+    let update = MockMessageText::new().as_edited().into_update(1.into());
+    let UpdateKind::EditedMessage(message) = &update[0].kind else {
+        panic!("The update should be an edited message");
+    };
+    assert!(message.edit_date().is_some());
+
+    let update = MockMessageText::new()
+        .as_channel_post()
+        .into_update(1.into());
+    assert!(matches!(update[0].kind, UpdateKind::ChannelPost(_)));
+}
+
+#[test]
+fn test_poll() {
+    let poll = MockPoll::new().question("Test");
+    let poll_object = poll.build();
+    assert_eq!(poll_object.id, MockPoll::ID);
+    assert_eq!(poll_object.question, "Test");
+    assert!(!poll_object.is_closed);
+}
+
+#[test]
+fn test_poll_answer() {
+    let poll = MockPoll::new().id("1").build();
+    let answer_object = MockPollAnswer::to(&poll).option_ids(vec![1, 2]).build();
+    assert_eq!(answer_object.poll_id, "1");
+    assert_eq!(answer_object.option_ids, vec![1, 2]);
+    assert_eq!(answer_object.voter.user().unwrap().id.0, MockUser::ID);
+}
+
+#[test]
+fn test_pre_checkout_query() {
+    let query = MockPreCheckoutQuery::new().invoice_payload("item");
+    let query_object = query.build();
+    assert_eq!(query_object.id, MockPreCheckoutQuery::ID);
+    assert_eq!(query_object.invoice_payload, "item");
+    assert_eq!(query_object.from.first_name, MockUser::FIRST_NAME);
+}
+
+#[test]
+fn test_shipping_query() {
+    let query = MockShippingQuery::new();
+    let query_object = query.build();
+    assert_eq!(query_object.id, MockShippingQuery::ID);
+    assert_eq!(
+        query_object.invoice_payload,
+        MockShippingQuery::INVOICE_PAYLOAD
+    );
+}
+
+#[test]
+fn test_message_successful_payment() {
+    let message = MockMessageSuccessfulPayment::new().total_amount(50);
+    let message_object = message.build();
+    let payment = message_object.successful_payment().unwrap();
+    assert_eq!(payment.total_amount, 50);
+    assert_eq!(
+        payment.telegram_payment_charge_id,
+        MockMessageSuccessfulPayment::TELEGRAM_PAYMENT_CHARGE_ID
+    );
+}
+
+#[test]
+fn test_message_forum_topic_created() {
+    let message = MockMessageForumTopicCreated::new()
+        .name("Ticket")
+        .icon_color(0xFF93B2);
+    let message_object = message.build();
+    let created = message_object.forum_topic_created().unwrap();
+    assert_eq!(created.name, "Ticket");
+    assert_eq!(created.icon_color, [0xFF, 0x93, 0xB2]);
+    assert!(message_object.is_topic_message);
+    match message_object.chat.kind {
+        ChatKind::Public(ChatPublic {
+            kind: PublicChatKind::Supergroup(supergroup),
+            ..
+        }) => assert!(supergroup.is_forum),
+        _ => panic!("The chat should be a supergroup"),
+    }
+}
+
+#[test]
+fn test_message_forum_topic_edited() {
+    let message = MockMessageForumTopicEdited::new().name("New name");
+    let message_object = message.build();
+    let edited = message_object.forum_topic_edited().unwrap();
+    assert_eq!(edited.name.as_deref(), Some("New name"));
+    assert_eq!(edited.icon_custom_emoji_id, None);
+}
+
+#[test]
+fn test_chat_member() {
+    let chat_member = MockChatMember::administrator().user(MockUser::new().id(2).build());
+    let chat_member_object = chat_member.build();
+    assert_eq!(chat_member_object.user.id, UserId(2));
+    assert!(chat_member_object.kind.is_administrator());
+    assert!(chat_member_object.kind.can_promote_members());
+    assert!(MockChatMember::banned().build().kind.is_banned());
+}
@@ -57,11 +57,13 @@
 //! ## Supported Endpoints
 //!
 //! - /AnswerCallbackQuery
+//! - /AnswerInlineQuery
 //! - /DeleteMessage
 //! - /EditMessageText
 //! - /EditMessageReplyMarkup
 //! - /EditMessageCaption
 //! - /GetFile
+//! - /GetMe
 //! - /SendMessage
 //! - /SendDocument
 //! - /SendPhoto
@@ -86,13 +88,36 @@
 //! - /BanChatMember
 //! - /UnbanChatMember
 //! - /RestrictChatMember
+//! - /PromoteChatMember
+//! - /SetChatAdministratorCustomTitle
+//! - /GetChatMember
+//! - /GetChatAdministrators
+//! - /GetChatMemberCount
+//! - /GetChat
+//! - /SetChatTitle
+//! - /SetChatDescription
+//! - /SetChatPhoto
+//! - /DeleteChatPhoto
+//! - /SetChatPermissions
 //! - /SetMessageReaction
+//! - /StopPoll
+//! - /SendInvoice
+//! - /AnswerPreCheckoutQuery
+//! - /AnswerShippingQuery
+//! - /RefundStarPayment
+//! - /CreateForumTopic
+//! - /EditForumTopic
+//! - /CloseForumTopic
+//! - /ReopenForumTopic
+//! - /DeleteForumTopic
+//! - /GetForumTopicIconStickers
 //!
 //! More endpoints will be added as time goes on!
 //!
-//! (do not worry about /GetMe and /GetUpdates, they are not needed for this bot!)
+//! (do not worry about /GetUpdates, it is not needed for this bot!)
 //!
-//! And also fake file downloading!
+//! And also file downloading! The bot gets the bytes it uploaded, or the ones set with
+//! `file_content` on the mocked messages.
 //!
 //! ## Why even use unit tests?
 //!
@@ -124,29 +149,29 @@
 //! And try to not use the raw bot fields unless you know what you are doing! They are public only
 //! to give more options to those who seek it.
 //!
-//! ## **!!! IMPORTANT !!!**
+//! ## Parallel tests
 //!
-//! If you want to use the database or
-//! something that is shared across all tests, DO IT __AFTER__ THE `MockBot::new()`!!!!!
-//! The creation of the bot creates a safe lock that prevents other tests from starting, before
-//! this bot becomes out of scope.
-//! If you encounter issues regarding this, try to manually add `drop(bot);` at the end of your
-//! tests!
-//! Or use the [serial_test](https://crates.io/crates/serial_test) crate
+//! Every `MockBot` starts its own fake server on a port given by the OS, and keeps its own
+//! messages, files and responses, so the tests can be run in parallel, no locks or
+//! [serial_test](https://crates.io/crates/serial_test) needed!
 //!
-//! Please refer to the [phrase_bot example](https://github.com/LasterAlex/teloxide_tests/tree/master/examples/phrase_bot) for more information
+//! But if your tests share something outside of the bot, like a database, they still can race
+//! each other. Mark those tests with `#[serial]`, like in the [phrase_bot example](https://github.com/LasterAlex/teloxide_tests/tree/master/examples/phrase_bot)
 //!
 #![doc(
     html_logo_url = "https://github.com/user-attachments/assets/627beca8-5852-4c70-97e0-5f4fcb5e2040",
     html_favicon_url = "https://github.com/user-attachments/assets/627beca8-5852-4c70-97e0-5f4fcb5e2040"
 )]
 mod dataset;
+mod listener;
 pub mod mock_bot;
 mod server;
 #[cfg(test)]
 mod tests;
 
 pub use dataset::*;
-pub use mock_bot::MockBot;
-pub use server::Responses;
+pub use mock_bot::{
+    ApiLimits, FailurePolicy, MockBot, RateLimit, RateLimits, UnsupportedMethods,
+};
+pub use server::{ApiCall, BotRequest, RequestBody, Responses};
 use teloxide_tests_macros as proc_macros;
@@ -0,0 +1,46 @@
+//! Update listener that gives the mocked updates to a real teloxide `Dispatcher`
+use std::convert::Infallible;
+
+use futures_util::stream::{self, StreamExt, TakeUntil};
+use teloxide::{
+    stop::{mk_stop_token, StopFlag, StopToken},
+    types::Update,
+    update_listeners::{AsUpdateStream, UpdateListener},
+};
+
+/// Returns all of the updates it was given, one by one, and then ends, so the dispatcher stops
+/// after every update was handled
+pub(crate) struct MockUpdateListener {
+    updates: Vec<Update>,
+    stop_token: StopToken,
+    stop_flag: StopFlag,
+}
+
+impl MockUpdateListener {
+    pub fn new(updates: Vec<Update>) -> Self {
+        let (stop_token, stop_flag) = mk_stop_token();
+        Self {
+            updates,
+            stop_token,
+            stop_flag,
+        }
+    }
+}
+
+impl UpdateListener for MockUpdateListener {
+    type Err = Infallible;
+
+    fn stop_token(&mut self) -> StopToken {
+        self.stop_token.clone()
+    }
+}
+
+impl<'a> AsUpdateStream<'a> for MockUpdateListener {
+    type StreamErr = Infallible;
+    type Stream = TakeUntil<stream::Iter<std::vec::IntoIter<Result<Update, Infallible>>>, StopFlag>;
+
+    fn as_stream(&'a mut self) -> Self::Stream {
+        let updates: Vec<_> = self.updates.drain(..).map(Ok).collect();
+        stream::iter(updates).take_until(self.stop_flag.clone())
+    }
+}
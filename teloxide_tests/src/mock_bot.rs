@@ -1,35 +1,56 @@
 //! Mock bot that sends requests to the fake server
-use gag::Gag;
+use futures_util::future::BoxFuture;
 use serde_json::Value;
 use std::{
-    env,
+    hash::Hash,
     mem::discriminant,
+    net::TcpListener,
     panic,
-    sync::{atomic::AtomicI32, Arc, Mutex, MutexGuard, PoisonError},
+    sync::{atomic::AtomicI32, Arc, Mutex},
+    time::Duration,
 };
 use teloxide::{
     dispatching::dialogue::ErasedStorage,
     dptree::di::DependencySupplier,
-    types::{File, FileMeta, MaybeInaccessibleMessage, MessageId, MessageKind},
+    types::{
+        Chat, ChatKind, ChatMember, ChatMemberKind, ChatPublic, ChosenInlineResult, FileMeta,
+        ForumTopic, MaybeInaccessibleMessage, MessageId, MessageKind, Poll, PollAnswer,
+        PublicChatKind, PublicChatSupergroup, ThreadId, Voter,
+    },
 };
 use teloxide::{dptree::deps, types::UpdateKind};
 use tokio::task::JoinHandle;
 
-use crate::dataset::{IntoUpdate, MockMe};
-use crate::server::{self, Responses, FILES, MESSAGES};
+use crate::dataset::{
+    IntoUpdate, MockChatMember, MockMe, MockMessageForumTopicCreated, MockMessageText,
+};
+use crate::listener::MockUpdateListener;
+use crate::server::{
+    self, ApiCall, HandlerError, InjectedError, Payment, Responses, ServerManager, State,
+};
 use teloxide::{
     dispatching::{
         dialogue::{GetChatId, InMemStorage, Storage},
-        UpdateHandler,
+        DefaultKey, DispatcherBuilder, UpdateHandler,
     },
     prelude::*,
     types::Me,
+    ApiError,
 };
 
-static GET_POTENTIAL_STORAGE_LOCK: Mutex<()> = Mutex::new(());
-static BOT_LOCK: Mutex<()> = Mutex::new(());
+type BotError = Box<dyn std::error::Error + Send + Sync + 'static>;
+type RunDispatcher = Arc<
+    dyn Fn(
+            Bot,
+            UpdateHandler<BotError>,
+            DependencyMap,
+            MockUpdateListener,
+        ) -> BoxFuture<'static, ()>
+        + Send
+        + Sync,
+>;
 
-fn find_file(value: Value) -> Option<FileMeta> {
+pub(crate) fn find_file(value: Value) -> Option<FileMeta> {
     // Recursively searches for file meta
     let mut file_id = None;
     let mut file_unique_id = None;
@@ -71,35 +92,426 @@ fn find_chat_id(value: Value) -> Option<i64> {
     None
 }
 
-fn add_message(message: &mut Message) {
-    let max_id = MESSAGES.max_message_id();
-    if message.id.0 <= max_id || MESSAGES.get_message(message.id.0).is_some() {
+fn add_message(state: &mut State, message: &mut Message) {
+    let chat_id = message.chat.id.0;
+    let max_id = state.messages.max_message_id(chat_id);
+    if message.id.0 <= max_id || state.messages.get_message(chat_id, message.id.0).is_some() {
         message.id = MessageId(max_id + 1);
     }
     if let Some(file_meta) = find_file(serde_json::to_value(&message).unwrap()) {
-        let file = File {
-            meta: file_meta,
-            path: "some_path.txt".to_string(), // This doesn't really matter
-        };
-        FILES.lock().unwrap().push(file);
+        // The content, if there is any, was given by the mocked update
+        state.files.add_file(file_meta, "file", None);
     }
     if let MessageKind::Common(ref mut message_kind) = message.kind {
         if let Some(ref mut reply_message) = message_kind.reply_to_message {
-            add_message(reply_message);
+            add_message(state, reply_message);
+        }
+    }
+    state.messages.add_message(message.clone());
+}
+
+fn add_edited_message(state: &mut State, message: &Message) {
+    // The edited message replaces the old one, if the bot knows about it, keeping its id
+    let chat_id = message.chat.id.0;
+    state.messages.delete_message(chat_id, message.id.0);
+    if let Some(file_meta) = find_file(serde_json::to_value(message).unwrap()) {
+        state.files.add_file(file_meta, "file", None);
+    }
+    state.messages.add_message(message.clone());
+}
+
+fn add_payment(state: &mut State, message: &Message) {
+    let (Some(payment), Some(user)) = (message.successful_payment(), message.from.as_ref()) else {
+        return;
+    };
+    if state
+        .payments
+        .iter()
+        .any(|known| known.telegram_payment_charge_id == payment.telegram_payment_charge_id)
+    {
+        // The same message was dispatched again, the payment is already known
+        return;
+    }
+    state.payments.push(Payment {
+        user_id: user.id.0,
+        telegram_payment_charge_id: payment.telegram_payment_charge_id.clone(),
+        is_refunded: false,
+    });
+}
+
+fn add_chat_member(state: &mut State, message: &Message) {
+    let (Some(user), false) = (message.from.as_ref(), message.chat.is_private()) else {
+        return;
+    };
+    // Whoever sends messages to the chat is in it
+    if state
+        .chat_members
+        .get_member(message.chat.id.0, user.id.0)
+        .is_none()
+    {
+        state.chat_members.set_member(
+            message.chat.id.0,
+            ChatMember {
+                user: user.clone(),
+                kind: ChatMemberKind::Member,
+            },
+        );
+    }
+}
+
+fn add_forum_topic(state: &mut State, message: &Message) {
+    let chat_id = message.chat.id.0;
+    let ChatKind::Public(ChatPublic {
+        kind: PublicChatKind::Supergroup(PublicChatSupergroup { is_forum: true, .. }),
+        ..
+    }) = message.chat.kind
+    else {
+        return;
+    };
+    state.forum_topics.add_forum(chat_id);
+    // The topic is started by the forum topic created message
+    let thread_id = message.thread_id.unwrap_or(ThreadId(message.id));
+    match &message.kind {
+        MessageKind::ForumTopicCreated(created) => {
+            let created = &created.forum_topic_created;
+            state.forum_topics.add_topic(
+                chat_id,
+                ForumTopic {
+                    thread_id,
+                    name: created.name.clone(),
+                    icon_color: created.icon_color,
+                    icon_custom_emoji_id: created.icon_custom_emoji_id.clone(),
+                },
+            );
+        }
+        MessageKind::ForumTopicEdited(edited) => {
+            let edited = &edited.forum_topic_edited;
+            if let Some(topic) = state.forum_topics.get_topic_mut(chat_id, thread_id.0 .0) {
+                if let Some(name) = &edited.name {
+                    topic.topic.name = name.clone();
+                }
+                if let Some(icon_custom_emoji_id) = &edited.icon_custom_emoji_id {
+                    // An empty string means that the icon was removed
+                    topic.topic.icon_custom_emoji_id =
+                        Some(icon_custom_emoji_id.clone()).filter(|id| !id.is_empty());
+                }
+            }
+        }
+        MessageKind::ForumTopicClosed(_) | MessageKind::ForumTopicReopened(_) => {
+            let is_closed = matches!(message.kind, MessageKind::ForumTopicClosed(_));
+            if let Some(topic) = state.forum_topics.get_topic_mut(chat_id, thread_id.0 .0) {
+                topic.is_closed = is_closed;
+            }
+        }
+        // A message from a topic that the bot didn't see being created
+        _ if message.is_topic_message
+            && state
+                .forum_topics
+                .get_topic(chat_id, thread_id.0 .0)
+                .is_none() =>
+        {
+            let [_, red, green, blue] = MockMessageForumTopicCreated::ICON_COLOR.to_be_bytes();
+            state.forum_topics.add_topic(
+                chat_id,
+                ForumTopic {
+                    thread_id,
+                    name: MockMessageForumTopicCreated::NAME.to_string(),
+                    icon_color: [red, green, blue],
+                    icon_custom_emoji_id: None,
+                },
+            );
+        }
+        _ => {}
+    }
+}
+
+fn find_poll_message(state: &State, poll_id: &str) -> Option<Message> {
+    state
+        .messages
+        .all()
+        .iter()
+        .find(|message| message.poll().is_some_and(|poll| poll.id == poll_id))
+        .cloned()
+}
+
+fn update_poll(state: &mut State, poll: &Poll) {
+    // Only the polls that the bot knows about are stored
+    if let Some(message) = find_poll_message(state, &poll.id) {
+        state
+            .messages
+            .edit_message(message.chat.id.0, message.id.0, "poll", poll);
+    }
+}
+
+fn add_poll_answer(state: &mut State, answer: &PollAnswer) {
+    let Some(message) = find_poll_message(state, &answer.poll_id) else {
+        return;
+    };
+    let mut poll = message.poll().unwrap().clone();
+    if poll.is_closed {
+        // Nobody can vote in a closed poll
+        return;
+    }
+    let voter_id = match &answer.voter {
+        Voter::User(user) => user.id.0 as i64,
+        Voter::Chat(chat) => chat.id.0,
+    };
+    let votes = state.poll_votes.entry(poll.id.clone()).or_default();
+
+    // A new answer replaces the old one, and an empty answer just retracts it
+    if let Some(old_option_ids) = votes.remove(&voter_id) {
+        for option_id in old_option_ids {
+            if let Some(option) = poll.options.get_mut(option_id as usize) {
+                option.voter_count = option.voter_count.saturating_sub(1);
+            }
+        }
+        poll.total_voter_count = poll.total_voter_count.saturating_sub(1);
+    }
+    if !answer.option_ids.is_empty() {
+        for option_id in answer.option_ids.iter() {
+            if let Some(option) = poll.options.get_mut(*option_id as usize) {
+                option.voter_count += 1;
+            }
+        }
+        poll.total_voter_count += 1;
+        votes.insert(voter_id, answer.option_ids.clone());
+    }
+
+    update_poll(state, &poll);
+}
+
+fn add_inline_message(state: &mut State, inline_message_id: String, message: Option<Message>) {
+    match message {
+        Some(message) => {
+            state
+                .messages
+                .add_inline_message(inline_message_id, message);
+        }
+        None => {
+            // The bot should be able to edit the message, even if we don't know what's in it
+            if state
+                .messages
+                .get_inline_message(&inline_message_id)
+                .is_none()
+            {
+                let message = MockMessageText::new()
+                    .via_bot(state.me.user.clone())
+                    .build();
+                state
+                    .messages
+                    .add_inline_message(inline_message_id, message);
+            }
+        }
+    }
+}
+
+fn make_inline_message(state: &State, result: &ChosenInlineResult) -> Message {
+    let mut message = MockMessageText::new()
+        .from(result.from.clone())
+        .via_bot(state.me.user.clone());
+    // If the bot answered with the chosen result, the message has its text and keyboard
+    let chosen = state
+        .inline_query_results
+        .iter()
+        .rev()
+        .map(|result| serde_json::to_value(result).unwrap())
+        .find(|chosen| chosen["id"] == result.result_id.as_str());
+    if let Some(chosen) = chosen {
+        let content = &chosen["input_message_content"];
+        if let Some(text) = content["message_text"].as_str() {
+            message.text = text.to_string();
+            message.entities =
+                serde_json::from_value(content["entities"].clone()).unwrap_or_default();
         }
+        message.reply_markup = serde_json::from_value(chosen["reply_markup"].clone()).ok();
     }
-    MESSAGES.add_message(message.clone());
+    message.build()
+}
+
+/// What the bot does with the updates that weren't handled by the handler tree, or with the
+/// errors that the handlers returned
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FailurePolicy {
+    /// Fail the test
+    #[default]
+    Panic,
+    /// Act like nothing happened
+    Ignore,
+    /// Put them into the responses (`unhandled_updates` or `handler_errors`), so you can test
+    /// them
+    Collect,
+}
+
+/// What the fake server answers to the methods that teloxide_tests doesn't support yet. The
+/// calls are put into `responses.unsupported_methods` either way.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum UnsupportedMethods {
+    /// Answer with a telegram 404 error, that has the name of the method
+    #[default]
+    Error,
+    /// Answer with the 404 error and fail the test
+    Panic,
+    /// Answer with a success, with this json as the result
+    Stub(Value),
+}
+
+/// A limit of how many calls can be made in some time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    pub calls: usize,
+    pub per: Duration,
+}
+
+impl RateLimit {
+    pub fn new(calls: usize, per: Duration) -> Self {
+        Self { calls, per }
+    }
+}
+
+/// Telegram-like flood control of the fake server. Only the calls to a chat (the ones with a
+/// `chat_id`) are counted, the ones over the limit get a 429 error with `retry_after`, which is
+/// `RequestError::RetryAfter` for teloxide.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RateLimits {
+    /// The limit for every chat on its own
+    pub per_chat: Option<RateLimit>,
+    /// The limit for all of the chats together
+    pub global: Option<RateLimit>,
 }
 
-async fn stop_server() {
-    let client = reqwest::Client::new();
-    let _ = client
-        .post(format!(
-            "http://127.0.0.1:{}/stop/false",
-            MockBot::PORT.lock().unwrap().clone()
-        ))
-        .send()
-        .await;
+impl RateLimits {
+    /// The limits that telegram has: one message per second in a chat, and 30 messages per
+    /// second overall
+    pub fn telegram() -> Self {
+        Self {
+            per_chat: Some(RateLimit::new(1, Duration::from_secs(1))),
+            global: Some(RateLimit::new(30, Duration::from_secs(1))),
+        }
+    }
+}
+
+/// The documented Bot API limits that the fake server checks. The calls that go over them are
+/// answered with the same errors as in telegram, like `ApiError::MessageIsTooLong`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ApiLimits {
+    /// The max length of a message text, in UTF-16 code units
+    pub message_text: usize,
+    /// The max length of a media caption, in UTF-16 code units
+    pub caption: usize,
+    /// The max length of a poll question
+    pub poll_question: usize,
+    /// The max length of a poll option
+    pub poll_option: usize,
+    /// The max number of poll options
+    pub poll_options: usize,
+    /// The max number of items in a media group
+    pub media_group_items: usize,
+    /// The max size of the callback data of an inline button, in bytes
+    pub callback_data: usize,
+    /// The max number of results in an answer to an inline query
+    pub inline_query_results: usize,
+}
+
+impl ApiLimits {
+    /// The limits that telegram has
+    pub fn telegram() -> Self {
+        Self {
+            message_text: 4096,
+            caption: 1024,
+            poll_question: 300,
+            poll_option: 100,
+            poll_options: 10,
+            media_group_items: 10,
+            callback_data: 64,
+            inline_query_results: 50,
+        }
+    }
+
+    /// No upper limits at all. Empty texts and too small polls and media groups are still
+    /// rejected, because telegram can't send them either
+    pub fn unlimited() -> Self {
+        Self {
+            message_text: usize::MAX,
+            caption: usize::MAX,
+            poll_question: usize::MAX,
+            poll_option: usize::MAX,
+            poll_options: usize::MAX,
+            media_group_items: usize::MAX,
+            callback_data: usize::MAX,
+            inline_query_results: usize::MAX,
+        }
+    }
+}
+
+impl Default for ApiLimits {
+    fn default() -> Self {
+        Self::telegram()
+    }
+}
+
+// Goes through the handler tree and does what the policies say with the unhandled updates and
+// errors. The result is still passed on, so the real dispatcher can give it to its default
+// handler and error handler.
+fn apply_failure_policies(
+    handler_tree: UpdateHandler<BotError>,
+    unhandled_updates: FailurePolicy,
+    handler_errors: FailurePolicy,
+    state: Arc<Mutex<State>>,
+) -> UpdateHandler<BotError> {
+    dptree::from_fn(move |deps: DependencyMap, cont| {
+        let handler_tree = handler_tree.clone();
+        let state = state.clone();
+        async move {
+            let update: Arc<Update> = deps.get();
+            let result = handler_tree.dispatch(deps).await;
+            {
+                let state = state.lock().unwrap();
+                if state.unsupported_methods == UnsupportedMethods::Panic
+                    && !state.responses.unsupported_methods.is_empty()
+                {
+                    panic!(
+                        "The bot called methods that are not supported by teloxide_tests: {:?}",
+                        state.responses.unsupported_methods
+                    );
+                }
+            }
+            match result {
+                ControlFlow::Break(Err(error)) => {
+                    match handler_errors {
+                        FailurePolicy::Panic => panic!("Error in handler: {:?}", error),
+                        FailurePolicy::Ignore => {}
+                        FailurePolicy::Collect => state
+                            .lock()
+                            .unwrap()
+                            .responses
+                            .handler_errors
+                            .push(HandlerError {
+                                update: (*update).clone(),
+                                error: format!("{:?}", error),
+                            }),
+                    }
+                    ControlFlow::Break(Err(error))
+                }
+                ControlFlow::Continue(deps) => {
+                    match unhandled_updates {
+                        FailurePolicy::Panic => {
+                            log::error!("Update didn't get handled!");
+                            panic!("Unhandled update!");
+                        }
+                        FailurePolicy::Ignore => {}
+                        FailurePolicy::Collect => state
+                            .lock()
+                            .unwrap()
+                            .responses
+                            .unhandled_updates
+                            .push((*update).clone()),
+                    }
+                    cont(deps).await
+                }
+                result => result,
+            }
+        }
+    })
 }
 
 /// A mocked bot that sends requests to the fake server
@@ -119,19 +531,30 @@ pub struct MockBot {
     pub dependencies: Mutex<DependencyMap>,
     /// Caught responses from the server
     pub responses: Mutex<Option<Responses>>,
-    bot_lock: Mutex<Option<MutexGuard<'static, ()>>>, // Maybe in the future ill make something like an atomic
-                                                      // bool that says if the bot is locked or not, and implement a custom Drop trait
+    /// What to do with the updates that weren't handled, panic by default
+    pub unhandled_updates: Mutex<FailurePolicy>,
+    /// What to do with the errors returned by the handlers, panic by default
+    pub handler_errors: Mutex<FailurePolicy>,
+    /// What to answer to the methods that aren't supported, an error by default
+    pub unsupported_methods: Mutex<UnsupportedMethods>,
+    /// The port of the fake server, assigned by the OS, so every bot has its own
+    pub port: u16,
+    // The socket is bound for the whole life of the bot, so no other bot can take the port
+    listener: TcpListener,
+    // Messages, files and responses of this bot's fake server
+    state: Arc<Mutex<State>>,
+    // The fake server, started on the first dispatch and stopped when the bot is dropped
+    server: Mutex<Option<ServerManager>>,
+    // Builds and runs a real dispatcher, if the bot was asked to use one
+    dispatcher: Mutex<Option<RunDispatcher>>,
 }
 
 impl MockBot {
     const CURRENT_UPDATE_ID: AtomicI32 = AtomicI32::new(0); // So that every update is different
-    const PORT: Mutex<u16> = Mutex::new(6504);
 
     /// Creates a new MockBot, using something that can be turned into Updates, and a handler tree.
-    /// You can't create a new bot while you have another bot in scope. Otherwise you will have a
-    /// lot of race conditions. If you still somehow manage to create two bots at the same time
-    /// (idk how),
-    /// please look into [this crate for serial testing](https://crates.io/crates/serial_test)
+    /// Every bot gets its own fake server on a free port, with its own messages and files, so
+    /// the tests can run in parallel without any locks.
     ///
     /// The `update` is just any Mock type, like `MockMessageText` or `MockCallbackQuery` or
     /// `vec![MockMessagePhoto]` if you want! All updates will be sent consecutively and asynchronously.
@@ -181,24 +604,19 @@ impl MockBot {
     where
         T: IntoUpdate, // And that code just "proves" that it can be turned into an update
     {
-        unsafe {
-            env::set_var(
-                // So that teloxide bot doesn't complain
-                "TELOXIDE_TOKEN",
-                "1234567890:QWERTYUIOPASDFGHJKLZXCVBNMQWERTYUIO",
-            );
-        }
         let _ = pretty_env_logger::try_init();
 
-        let bot = Bot::from_env().set_api_url(
-            reqwest::Url::parse(&format!(
-                "http://localhost:{}",
-                Self::PORT.lock().unwrap().clone()
-            ))
-            .unwrap(),
-        );
-        let lock = BOT_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
-        // If the lock is poisoned, we don't care, some other bot panicked and can't do anything
+        // Port 0 means that the OS will give us any free port
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind the fake server!");
+        let port = listener.local_addr().unwrap().port();
+
+        // The token isn't checked by the fake server, it only has to look like a real one
+        let bot = Bot::new("1234567890:QWERTYUIOPASDFGHJKLZXCVBNMQWERTYUIO")
+            .set_api_url(reqwest::Url::parse(&format!("http://127.0.0.1:{}", port)).unwrap());
+        let mut state = State::default();
+        for (file_id, content) in update.file_contents() {
+            state.files.set_content(&file_id, content);
+        }
         Self {
             bot,
             me: Mutex::new(MockMe::new().build()),
@@ -206,9 +624,14 @@ impl MockBot {
             handler_tree,
             responses: Mutex::new(None),
             dependencies: Mutex::new(DependencyMap::new()),
-            bot_lock: Mutex::new(Some(lock)), // This makes a lock that forbids the creation of
-                                              // other bots until this one goes out of scope. That way there will be no race
-                                              // conditions!
+            unhandled_updates: Mutex::new(FailurePolicy::default()),
+            handler_errors: Mutex::new(FailurePolicy::default()),
+            unsupported_methods: Mutex::new(UnsupportedMethods::default()),
+            port,
+            listener,
+            state: Arc::new(Mutex::new(state)),
+            server: Mutex::new(None),
+            dispatcher: Mutex::new(None),
         }
     }
 
@@ -222,141 +645,349 @@ impl MockBot {
 
     /// Sets the bot parameters, like supports_inline_queries, first_name, etc.
     pub fn me(&self, me: MockMe) {
+        self.state.lock().unwrap().administrator_rights = me.administrator_rights.clone();
         *self.me.lock().unwrap() = me.build();
     }
 
+    /// Sets what the bot does with the updates that went through the handler tree without being
+    /// handled. By default the test panics, `FailurePolicy::Collect` puts them into
+    /// `responses.unhandled_updates`, so you can check that an update is ignored on purpose.
+    pub fn unhandled_updates(&self, policy: FailurePolicy) {
+        *self.unhandled_updates.lock().unwrap() = policy;
+    }
+
+    /// Sets what the bot does with the errors returned by the handlers. By default the test
+    /// panics, `FailurePolicy::Collect` puts them into `responses.handler_errors`, so you can
+    /// check that a handler returns the right error.
+    pub fn handler_errors(&self, policy: FailurePolicy) {
+        *self.handler_errors.lock().unwrap() = policy;
+    }
+
+    /// Sets what the fake server answers to the methods it doesn't support. By default it is a
+    /// telegram 404 error, `UnsupportedMethods::Panic` also fails the test, and
+    /// `UnsupportedMethods::Stub(json!(true))` makes the calls succeed with that result.
+    pub fn unsupported_methods(&self, policy: UnsupportedMethods) {
+        *self.unsupported_methods.lock().unwrap() = policy;
+    }
+
+    /// Makes the next call to the method fail with the error, like
+    /// `bot.fail_next("SendMessage", ApiError::BotBlocked)`. The bot gets the same response that
+    /// telegram would give, so it is `RequestError::Api(error)` for teloxide. The error stays
+    /// until the method is called, even between dispatches.
+    pub fn fail_next(&self, method: &str, error: ApiError) {
+        let method = method.to_string();
+        self.state
+            .lock()
+            .unwrap()
+            .injected_errors
+            .push(InjectedError {
+                predicate: Box::new(move |call| call.is_method(&method)),
+                error,
+                once: true,
+            });
+    }
+
+    /// Makes every call that matches the predicate fail with the error. The predicate gets the
+    /// method, the body and the number of the call, so the failures can be scoped to a chat or
+    /// to some calls only:
+    ///
+    /// ```ignore
+    /// bot.fail_when(
+    ///     |call| call.is_method("SendMessage") && call.chat_id() == Some(1234),
+    ///     ApiError::BotBlocked,
+    /// );
+    /// bot.fail_when(|call| call.call_number == 2, ApiError::MessageNotModified);
+    /// ```
+    pub fn fail_when<F>(&self, predicate: F, error: ApiError)
+    where
+        F: Fn(&ApiCall) -> bool + Send + Sync + 'static,
+    {
+        self.state
+            .lock()
+            .unwrap()
+            .injected_errors
+            .push(InjectedError {
+                predicate: Box::new(predicate),
+                error,
+                once: false,
+            });
+    }
+
+    /// Turns on the flood control of the fake server, like
+    /// `bot.rate_limits(RateLimits::telegram())`. The calls over the limits are answered with a
+    /// 429 error and are still recorded in `responses.requests`, so you can check that your
+    /// throttling works. The flood control is off by default.
+    pub fn rate_limits(&self, limits: RateLimits) {
+        self.state.lock().unwrap().rate_limits = limits;
+    }
+
+    /// Sets the Bot API limits that the fake server checks, like the max length of a message.
+    /// They are the telegram ones by default, so the bot gets the same errors as in production,
+    /// use `bot.api_limits(ApiLimits::unlimited())` to allow anything.
+    pub fn api_limits(&self, limits: ApiLimits) {
+        self.state.lock().unwrap().api_limits = limits;
+    }
+
+    /// Sets the members of the chat, that the bot gets with `get_chat_member`,
+    /// `get_chat_administrators` and `get_chat_member_count`, like
+    /// `bot.chat_members(MockGroupChat::ID, vec![MockChatMember::administrator()])`. Banning,
+    /// restricting and promoting the users changes them, and the users that send messages to the
+    /// chat become its members.
+    pub fn chat_members(&self, chat_id: i64, members: Vec<MockChatMember>) {
+        let members = members.into_iter().map(|member| member.build()).collect();
+        self.state
+            .lock()
+            .unwrap()
+            .chat_members
+            .set_members(chat_id, members);
+    }
+
+    /// Adds the chat that the bot gets with `get_chat`, like
+    /// `bot.chat(MockSupergroupChat::new().title("Chat").build())`, replacing the one with the
+    /// same id. The chats of the updates are added by themselves, and the bot can change them
+    /// with `set_chat_title`, `set_chat_photo` and so on, the changes stay between dispatches.
+    pub fn chat(&self, chat: Chat) {
+        self.state.lock().unwrap().chats.set_chat(chat);
+    }
+
+    /// Checks if the user is banned in the chat right now. The bans with an `until_date` are
+    /// over when that date passes
+    pub fn is_banned(&self, chat_id: i64, user_id: u64) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .chat_members
+            .is_banned(chat_id, user_id)
+    }
+
+    /// Checks if the user is restricted in the chat right now. The restrictions with an
+    /// `until_date` are over when that date passes
+    pub fn is_restricted(&self, chat_id: i64, user_id: u64) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .chat_members
+            .is_restricted(chat_id, user_id)
+    }
+
+    /// Makes the bot dispatch the updates through a real teloxide `Dispatcher`, instead of giving
+    /// them to the handler tree one by one. This way the distribution function, per-chat ordering,
+    /// default handler and error handler all work just like in production.
+    ///
+    /// The `build` function gets the dispatcher builder with the bot, the handler tree and the
+    /// dependencies already set, so you only need to add what you use in your `main`.
+    ///
+    /// Keep in mind that the unhandled updates and handler errors still go through
+    /// `unhandled_updates` and `handler_errors` policies first, so if you want to test your own
+    /// default handler or error handler, set the policy to `FailurePolicy::Ignore` or
+    /// `FailurePolicy::Collect`.
+    ///
+    /// The dispatcher calls `get_me` when it starts, that call isn't in `responses.requests` and
+    /// doesn't count for `fail_next`, `fail_when` and the rate limits, so the bot sees the same
+    /// calls with or without a dispatcher.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use teloxide::dispatching::UpdateHandler;
+    /// use teloxide::prelude::*;
+    /// use teloxide_tests::{MockBot, MockMessageText};
+    ///
+    /// fn handler_tree() -> UpdateHandler<Box<dyn std::error::Error + Send + Sync + 'static>> {
+    ///     teloxide::dptree::entry() /* your handlers go here */
+    /// }
+    ///
+    /// fn distribution_function(update: &Update) -> Option<ChatId> {
+    ///     update.chat().map(|chat| chat.id)
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let bot = MockBot::new(vec![MockMessageText::new(); 3], handler_tree());
+    ///     bot.dispatcher(|builder| builder.distribution_function(distribution_function));
+    ///     bot.dispatch().await;
+    /// }
+    /// ```
+    pub fn dispatcher<Key, F>(&self, build: F)
+    where
+        F: Fn(
+                DispatcherBuilder<Bot, BotError, DefaultKey>,
+            ) -> DispatcherBuilder<Bot, BotError, Key>
+            + Send
+            + Sync
+            + 'static,
+        Key: Hash + Eq + Clone + Send + 'static,
+    {
+        *self.dispatcher.lock().unwrap() = Some(Arc::new(
+            move |bot, handler_tree, dependencies, listener| {
+                let mut dispatcher =
+                    build(Dispatcher::builder(bot, handler_tree).dependencies(dependencies))
+                        .build();
+                Box::pin(async move {
+                    dispatcher
+                        .dispatch_with_listener(listener, LoggingErrorHandler::new())
+                        .await;
+                })
+            },
+        ));
+    }
+
     /// Sets the updates. Useful for reusing the same mocked bot instance in different tests
     /// Reminder: You can pass in vec![MockMessagePhoto] or something else!
     pub fn update<T: IntoUpdate>(&self, update: T) {
+        let mut state = self.state.lock().unwrap();
+        for (file_id, content) in update.file_contents() {
+            state.files.set_content(&file_id, content);
+        }
         *self.updates.lock().unwrap() = update.into_update(Self::CURRENT_UPDATE_ID);
     }
 
-    fn collect_handles(&self, handles: &mut Vec<JoinHandle<()>>) {
-        let updates_lock = self.updates.lock().unwrap().clone();
-        let self_deps = self.dependencies.lock().unwrap().clone();
-        for mut update_lock in updates_lock {
-            match update_lock.kind.clone() {
+    fn prepare_updates(&self) -> Vec<Update> {
+        let mut updates = self.updates.lock().unwrap().clone();
+        let mut state = self.state.lock().unwrap();
+        for update in updates.iter_mut() {
+            match update.kind.clone() {
                 UpdateKind::Message(mut message) => {
                     // Add the message to the list of messages, so the bot can interact with it
-                    add_message(&mut message);
-                    update_lock.kind = UpdateKind::Message(message.clone());
+                    add_message(&mut state, &mut message);
+                    state.chats.add_chat(message.chat.clone());
+                    add_payment(&mut state, &message);
+                    add_forum_topic(&mut state, &message);
+                    add_chat_member(&mut state, &message);
+                    update.kind = UpdateKind::Message(message.clone());
+                }
+                UpdateKind::ChannelPost(mut message) => {
+                    add_message(&mut state, &mut message);
+                    state.chats.add_chat(message.chat.clone());
+                    update.kind = UpdateKind::ChannelPost(message.clone());
+                }
+                UpdateKind::EditedMessage(message) | UpdateKind::EditedChannelPost(message) => {
+                    add_edited_message(&mut state, &message);
                 }
                 UpdateKind::CallbackQuery(mut callback) => {
-                    if let Some(MaybeInaccessibleMessage::Regular(ref mut message)) =
+                    if let Some(inline_message_id) = callback.inline_message_id.clone() {
+                        // Telegram doesn't send the inline messages, so the attached message is
+                        // only the content of the inline message
+                        let message = match callback.message.take() {
+                            Some(MaybeInaccessibleMessage::Regular(message)) => Some(message),
+                            _ => None,
+                        };
+                        add_inline_message(&mut state, inline_message_id, message);
+                    } else if let Some(MaybeInaccessibleMessage::Regular(ref mut message)) =
                         callback.message
                     {
-                        add_message(message);
+                        add_message(&mut state, message);
+                    }
+                    update.kind = UpdateKind::CallbackQuery(callback.clone());
+                }
+                UpdateKind::ChatMember(updated) | UpdateKind::MyChatMember(updated) => {
+                    state.chats.add_chat(updated.chat.clone());
+                    state
+                        .chat_members
+                        .set_member(updated.chat.id.0, updated.new_chat_member);
+                }
+                UpdateKind::Poll(poll) => update_poll(&mut state, &poll),
+                UpdateKind::PollAnswer(answer) => add_poll_answer(&mut state, &answer),
+                UpdateKind::ChosenInlineResult(result) => {
+                    // The message is sent via inline mode only if it has an inline message id
+                    if let Some(inline_message_id) = result.inline_message_id.clone() {
+                        let message = make_inline_message(&state, &result);
+                        add_inline_message(&mut state, inline_message_id, Some(message));
                     }
-                    update_lock.kind = UpdateKind::CallbackQuery(callback.clone());
                 }
                 _ => {}
             }
+        }
+        updates
+    }
 
+    fn collect_handles(
+        &self,
+        handler_tree: UpdateHandler<BotError>,
+        updates: Vec<Update>,
+        handles: &mut Vec<JoinHandle<()>>,
+    ) {
+        let self_deps = self.dependencies.lock().unwrap().clone();
+        for update in updates {
             let mut deps = deps![
                 self.bot.clone(),
                 self.me.lock().unwrap().clone(),
-                update_lock.clone() // This actually makes an update go through the dptree
+                update // This actually makes an update go through the dptree
             ];
 
             deps.insert_container(self_deps.clone()); // These are nessessary for the dispatch
 
-            // This, too, will need to be redone in the ideal world, but it just waits until the server is up
-            let handler_tree = self.handler_tree.clone();
+            let handler_tree = handler_tree.clone();
 
             handles.push(tokio::spawn(async move {
-                let result = handler_tree.dispatch(deps.clone()).await;
-                if let ControlFlow::Break(result) = result {
-                    // If it returned `ControlFlow::Break`, everything is fine, but we need to check, if the
-                    // handler didn't error out
-                    assert!(result.is_ok(), "Error in handler: {:?}", result);
-                } else {
-                    log::error!("Update didn't get handled!");
-                    panic!("Unhandled update!");
-                }
+                // The unhandled updates and errors are already taken care of by the policies
+                let _ = handler_tree.dispatch(deps).await;
             }));
         }
     }
 
-    async fn close_bot(&self) {
-        stop_server().await;
-        *self.bot_lock.lock().unwrap() = None;
-    }
-
     /// Actually dispatches the bot, calling the update through the handler tree.
     /// All the requests made through the bot will be stored in `responses`, and can be retrieved
     /// with `get_responses`. All the responses are unique to that dispatch, and will be erased for
     /// every new dispatch.
+    ///
+    /// The fake server is started on the first dispatch, and lives until the bot is dropped, so
+    /// dispatching many times in a row is cheap.
     pub async fn dispatch(&self) {
-        let runtime = tokio::runtime::Handle::current();
-        // If the user presses ctrl-c, the server will be shut down
-        let _ = ctrlc::set_handler(move || {
-            runtime.block_on(stop_server());
-            std::process::exit(1);
-        });
-
-        // In the future, this will need to be redone nicely, but right now it works.
-        // It prevents a race condition for different bot instances to try to use the same server
-        // (like in docstring)
-        stop_server().await;
-        let mut left_tries = 200;
-        while reqwest::get(format!(
-            "http://127.0.0.1:{}/ping",
-            Self::PORT.lock().unwrap().clone()
-        ))
-        .await
-        .is_ok()
-        {
-            left_tries -= 1;
-            if left_tries == 0 {
-                self.close_bot().await;
-                panic!(
-                    "Failed to unbind the server on the port {}!",
-                    Self::PORT.lock().unwrap().clone()
-                );
-            }
+        if self.server.lock().unwrap().is_none() {
+            let server = ServerManager::start(
+                self.listener
+                    .try_clone()
+                    .expect("Failed to clone the fake server socket!"),
+                self.state.clone(),
+            )
+            .await;
+            *self.server.lock().unwrap() = Some(server);
         }
 
-        let server = tokio::spawn(server::main(Self::PORT, self.me.lock().unwrap().clone())); // This starts the server in the background
-
-        let mut left_tries = 200;
-        while reqwest::get(format!(
-            "http://127.0.0.1:{}/ping",
-            Self::PORT.lock().unwrap().clone()
-        ))
-        .await
-        .is_err()
         {
-            left_tries -= 1;
-            if left_tries == 0 {
-                self.close_bot().await;
-                panic!(
-                    "Failed to get the server on the port {}!",
-                    Self::PORT.lock().unwrap().clone()
-                );
-            }
+            let mut state = self.state.lock().unwrap();
+            // Messages and files don't care if they are cleaned or not
+            state.responses = Responses::default();
+            state.me = self.me.lock().unwrap().clone();
+            state.unsupported_methods = self.unsupported_methods.lock().unwrap().clone();
         }
 
         // Gets all of the updates to send
-        let mut handles = vec![];
-        self.collect_handles(&mut handles);
-
-        for handle in handles {
-            // Waits until every update has been sent
-            match handle.await {
-                Ok(_) => {}
-                Err(_) => {
-                    // Something panicked, we need to free the bot lock and exit
-                    self.close_bot().await;
+        let updates = self.prepare_updates();
+        let handler_tree = apply_failure_policies(
+            self.handler_tree.clone(),
+            *self.unhandled_updates.lock().unwrap(),
+            *self.handler_errors.lock().unwrap(),
+            self.state.clone(),
+        );
+
+        let run_dispatcher = self.dispatcher.lock().unwrap().clone();
+        if let Some(run_dispatcher) = run_dispatcher {
+            // The dispatcher gets `me` before anything else, that call isn't the bot's
+            self.state.lock().unwrap().dispatcher_get_me = true;
+            let deps = self.dependencies.lock().unwrap().clone();
+            run_dispatcher(
+                self.bot.clone(),
+                handler_tree,
+                deps,
+                MockUpdateListener::new(updates),
+            )
+            .await;
+        } else {
+            let mut handles = vec![];
+            self.collect_handles(handler_tree, updates, &mut handles);
+
+            for handle in handles {
+                // Waits until every update has been sent
+                if handle.await.is_err() {
+                    // Something panicked, the server will be stopped when the bot is dropped
                     panic!("Something went wrong and the bot panicked!");
                 }
-            };
+            }
         }
 
-        *self.responses.lock().unwrap() = Some(server::RESPONSES.lock().unwrap().clone()); // Store the responses
-                                                                                           // before they are erased
-
-        stop_server().await;
-        server.await.unwrap(); // Waits before the server is shut down
+        *self.responses.lock().unwrap() = Some(self.state.lock().unwrap().responses.clone());
+        // Store the responses before they are erased
     }
 
     /// Returns the responses stored in `responses`
@@ -382,39 +1013,12 @@ impl MockBot {
     where
         S: Send + 'static + Clone,
     {
-        let get_potential_storage_lock = GET_POTENTIAL_STORAGE_LOCK.lock();
-        // If not this lock, some panic messages will make it to stderr, even with gag, because
-        // race condition.
-        let default_panic = panic::take_hook();
-        let in_mem_storage: Option<Arc<Arc<InMemStorage<S>>>>;
-        let erased_storage: Option<Arc<Arc<ErasedStorage<S>>>>;
+        // Removing from a copy of the map doesn't panic if the storage isn't there, unlike
+        // getting it, so nothing has to be silenced
+        let mut dependencies = self.dependencies.lock().unwrap().clone();
+        let in_mem_storage = dependencies.remove::<Arc<InMemStorage<S>>>();
+        let erased_storage = dependencies.remove::<Arc<ErasedStorage<S>>>();
         // No trace storage cuz who uses it
-        let dependencies = Arc::new(self.dependencies.lock().unwrap().clone());
-        // Get dependencies into Arc cuz otherwise it complaints about &self being moved
-
-        panic::set_hook(Box::new(|_| {
-            // Do nothing to ignore the panic
-        }));
-        let print_gag = Gag::stderr().unwrap(); // Otherwise the panic will be printed
-        in_mem_storage = std::thread::spawn(move || {
-            // Try to convert one of dptrees fields into an InMemStorage
-            dependencies.get()
-        })
-        .join()
-        .ok();
-
-        let dependencies = Arc::new(self.dependencies.lock().unwrap().clone());
-        // Dependencies were moved to a prev. thread, so create a new one
-        erased_storage = std::thread::spawn(move || {
-            // The same for ErasedStorage
-            dependencies.get()
-        })
-        .join()
-        .ok();
-
-        panic::set_hook(default_panic); // Restore the default panic hook
-        drop(print_gag);
-        drop(get_potential_storage_lock);
         (in_mem_storage, erased_storage)
     }
 
@@ -494,7 +1098,6 @@ impl MockBot {
                 Some(id) => ChatId(id),
                 None => {
                     log::error!("No chat id was detected in the update! Did you send an update without a chat identifier? Like MockCallbackQuery without an attached message?");
-                    self.close_bot().await;
                     panic!("No chat id was detected!");
                 }
             },
@@ -514,7 +1117,6 @@ impl MockBot {
                 .await
                 .expect("Failed to update dialogue");
         } else {
-            self.close_bot().await;
             log::error!("No storage was detected! Did you add it to bot.dependencies(deps![get_bot_storage().await]); ?");
             panic!("No storage was detected!");
         }
@@ -536,7 +1138,6 @@ impl MockBot {
             None => match find_chat_id(serde_json::to_value(&update_lock).unwrap()) {
                 Some(id) => ChatId(id),
                 None => {
-                    *self.bot_lock.lock().unwrap() = None;
                     panic!("No chat id was detected!");
                 }
             },
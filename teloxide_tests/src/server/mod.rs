@@ -1,24 +1,57 @@
 //! A fake telegram bot API for testing purposes. Read more in teloxide_tests crate.
+pub mod parse_mode;
 pub mod routes;
-use actix_web::{dev::ServerHandle, web, App, HttpResponse, HttpServer, Responder};
-use actix_web_lab::extract::Path;
-use lazy_static::lazy_static;
+use actix_multipart::Multipart;
+use actix_web::{
+    body::{to_bytes, BoxBody},
+    dev::{Payload, ServerHandle, ServiceRequest, ServiceResponse},
+    error::PayloadError,
+    middleware::{from_fn, Next},
+    rt::System,
+    web::{self, Bytes, BytesMut},
+    App, HttpMessage, HttpResponse, HttpServer, Responder,
+};
+use chrono::{DateTime, Utc};
+use futures_util::{stream, Stream, StreamExt};
 use routes::{
-    answer_callback_query::*, ban_chat_member::*, copy_message::*, delete_message::*,
-    download_file::download_file, edit_message_caption::*, edit_message_reply_markup::*,
-    edit_message_text::*, forward_message::*, get_file::*, pin_chat_message::*,
-    restrict_chat_member::*, send_animation::*, send_audio::*, send_chat_action::*,
-    send_contact::*, send_dice::*, send_document::*, send_location::*, send_media_group::*,
-    send_message::*, send_photo::*, send_poll::*, send_sticker::*, send_venue::*, send_video::*,
-    send_video_note::*, send_voice::*, unban_chat_member::*, unpin_all_chat_messages::*,
-    unpin_chat_message::*, set_message_reaction::*,
+    answer_callback_query::*, answer_inline_query::*, answer_pre_checkout_query::*,
+    answer_shipping_query::*, ban_chat_member::*, close_forum_topic::*, copy_message::*,
+    create_forum_topic::*, delete_chat_photo::*, delete_forum_topic::*, delete_message::*,
+    download_file::download_file, edit_forum_topic::*, edit_message_caption::*,
+    edit_message_reply_markup::*, edit_message_text::*, forward_message::*, get_chat::*,
+    get_chat_administrators::*, get_chat_member::*, get_chat_member_count::*, get_file::*,
+    get_forum_topic_icon_stickers::*, get_me::*, get_raw_multipart_fields, make_retry_after_error,
+    make_telegram_error, pin_chat_message::*, promote_chat_member::*, refund_star_payment::*,
+    reopen_forum_topic::*, restrict_chat_member::*, send_animation::*, send_audio::*,
+    send_chat_action::*, send_contact::*, send_dice::*, send_document::*, send_invoice::*,
+    send_location::*, send_media_group::*, send_message::*, send_photo::*, send_poll::*,
+    send_sticker::*, send_venue::*, send_video::*, send_video_note::*, send_voice::*,
+    set_chat_administrator_custom_title::*, set_chat_description::*, set_chat_permissions::*,
+    set_chat_photo::*, set_chat_title::*, set_message_reaction::*, stop_poll::*,
+    unban_chat_member::*, unknown_method::*, unpin_all_chat_messages::*, unpin_chat_message::*,
+    Attachment, BodyChatId,
 };
 use serde::Serialize;
-use std::sync::{
-    atomic::{AtomicI32, Ordering},
-    Mutex,
+use std::{
+    collections::{HashMap, HashSet},
+    net::TcpListener,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+    time::Instant,
+};
+use teloxide::types::{
+    Chat, ChatAdministratorRights, ChatMember, ChatMemberKind, ChatPhoto, File, FileMeta,
+    ForumTopic, InlineQueryResult, Me, Message, MessageId, Poll, ReplyMarkup, ThreadId, UntilDate,
+    Update, User,
+};
+use teloxide::ApiError;
+use tokio::sync::Notify;
+
+use crate::{
+    dataset::MockMe,
+    mock_bot::{ApiLimits, RateLimit, RateLimits, UnsupportedMethods},
 };
-use teloxide::types::{File, Me, Message, MessageId, ReplyMarkup};
 
 #[derive(Clone, Debug)]
 pub struct SentMessageText {
@@ -99,6 +132,15 @@ pub struct SentMessagePoll {
     pub bot_request: SendMessagePollBody,
 }
 
+#[derive(Clone, Debug)]
+pub struct SentMessageInvoice {
+    pub message: Message,
+    /// The message that the invoice replies to. Teloxide's invoice messages can't have a reply,
+    /// so it isn't in the `.message`
+    pub reply_to_message: Option<Message>,
+    pub bot_request: SendMessageInvoiceBody,
+}
+
 #[derive(Clone, Debug)]
 pub struct SentMessageSticker {
     pub message: Message,
@@ -152,6 +194,115 @@ pub struct SetMessageReaction {
     pub bot_request: SetMessageReactionBody,
 }
 
+#[derive(Clone, Debug)]
+pub struct StoppedPoll {
+    pub poll: Poll,
+    pub bot_request: StopPollBody,
+}
+
+#[derive(Clone, Debug)]
+pub struct CreatedForumTopic {
+    pub topic: ForumTopic,
+    pub bot_request: CreateForumTopicBody,
+}
+
+#[derive(Clone, Debug)]
+pub struct SetChatPhoto {
+    pub photo: ChatPhoto,
+    pub bot_request: SetChatPhotoBody,
+}
+
+#[derive(Clone, Debug)]
+pub struct HandlerError {
+    pub update: Update,
+    /// The `Debug` output of the returned error
+    pub error: String,
+}
+
+/// The body of a request that was sent to the fake server
+#[derive(Clone, Debug)]
+pub enum RequestBody {
+    Json(serde_json::Value),
+    Multipart {
+        fields: HashMap<String, String>,
+        attachments: HashMap<String, Attachment>,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct BotRequest {
+    /// The name of the called method, like `SendMessage`
+    pub method: String,
+    pub body: RequestBody,
+    /// The json the fake server responded with
+    pub response: serde_json::Value,
+    /// When the request came to the fake server
+    pub timestamp: DateTime<Utc>,
+}
+
+impl RequestBody {
+    /// Returns the chat id that the request was sent to, if there is one
+    pub fn chat_id(&self) -> Option<i64> {
+        let chat_id = match self {
+            RequestBody::Json(json) => serde_json::from_value(json.get("chat_id")?.clone()).ok()?,
+            RequestBody::Multipart { fields, .. } => {
+                let chat_id = fields.get("chat_id")?;
+                match chat_id.parse() {
+                    Ok(id) => BodyChatId::Id(id),
+                    Err(_) => BodyChatId::Text(chat_id.clone()),
+                }
+            }
+        };
+        Some(chat_id.id())
+    }
+}
+
+impl BotRequest {
+    /// Returns the chat id that the request was sent to, if there is one
+    pub fn chat_id(&self) -> Option<i64> {
+        self.body.chat_id()
+    }
+}
+
+/// A payment from a successful payment message that the bot got, so it can be refunded
+#[derive(Clone, Debug)]
+pub struct Payment {
+    pub user_id: u64,
+    pub telegram_payment_charge_id: String,
+    pub is_refunded: bool,
+}
+
+/// A call to the fake server that hasn't been answered yet, it is given to the
+/// `MockBot::fail_when` predicates
+#[derive(Clone, Debug)]
+pub struct ApiCall {
+    /// The name of the called method, like `SendMessage`
+    pub method: String,
+    pub body: RequestBody,
+    /// Which call to this method it is in the current dispatch, starting from 1
+    pub call_number: usize,
+}
+
+impl ApiCall {
+    /// Checks if it is a call to the method, the case of the name doesn't matter
+    pub fn is_method(&self, method: &str) -> bool {
+        self.method.eq_ignore_ascii_case(method)
+    }
+
+    /// Returns the chat id that the call was made to, if there is one
+    pub fn chat_id(&self) -> Option<i64> {
+        self.body.chat_id()
+    }
+}
+
+/// An error that the fake server gives instead of answering the calls that match it
+pub(crate) struct InjectedError {
+    pub predicate: Box<dyn Fn(&ApiCall) -> bool + Send + Sync>,
+    pub error: ApiError,
+    /// The errors from `MockBot::fail_next` are used only once
+    pub once: bool,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct Responses {
     /// All of the sent messages, including text, photo, audio, etc.
@@ -228,6 +379,11 @@ pub struct Responses {
     /// has the request that was sent to the fake server
     pub sent_messages_sticker: Vec<SentMessageSticker>,
 
+    /// This has only messages that are invoices, sent by the bot.
+    /// The `.message` field has the sent by bot message, and `.bot_request`
+    /// has the request that was sent to the fake server
+    pub sent_messages_invoice: Vec<SentMessageInvoice>,
+
     /// This has only messages that are media group messages, sent by the bot.
     /// The `.messages` field has the sent by bot messages, and `.bot_request`
     /// has the request that was sent to the fake server
@@ -268,6 +424,26 @@ pub struct Responses {
     /// `.message` field.
     pub answered_callback_queries: Vec<AnswerCallbackQueryBody>,
 
+    /// This has only the requests that were sent to the fake server to answer inline queries.
+    /// Telegram doesn't return anything, because there isn't anything to return, so there is no
+    /// `.message` field.
+    pub answered_inline_queries: Vec<AnswerInlineQueryBody>,
+
+    /// This has only the requests that were sent to the fake server to answer pre-checkout
+    /// queries. Telegram doesn't return anything, because there isn't anything to return, so
+    /// there is no `.message` field.
+    pub answered_pre_checkout_queries: Vec<AnswerPreCheckoutQueryBody>,
+
+    /// This has only the requests that were sent to the fake server to answer shipping queries.
+    /// Telegram doesn't return anything, because there isn't anything to return, so there is no
+    /// `.message` field.
+    pub answered_shipping_queries: Vec<AnswerShippingQueryBody>,
+
+    /// This has only the requests that were sent to the fake server to refund star payments.
+    /// Telegram doesn't return anything, because there isn't anything to return, so there is no
+    /// `.message` field.
+    pub refunded_star_payments: Vec<RefundStarPaymentBody>,
+
     /// This has only the requests that were sent to the fake server to pin messages.
     /// Telegram doesn't return anything, because there isn't anything to return, so there is no
     /// `.message` field.
@@ -298,141 +474,713 @@ pub struct Responses {
     /// `.message` field.
     pub restricted_chat_members: Vec<RestrictChatMemberBody>,
 
+    /// This has only the requests that were sent to the fake server to promote chat members.
+    /// Telegram doesn't return anything, because there isn't anything to return, so there is no
+    /// `.message` field.
+    pub promoted_chat_members: Vec<PromoteChatMemberBody>,
+
+    /// This has only the requests that were sent to the fake server to set the custom titles of
+    /// the administrators. Telegram doesn't return anything, because there isn't anything to
+    /// return, so there is no `.message` field.
+    pub set_chat_administrator_custom_titles: Vec<SetChatAdministratorCustomTitleBody>,
+
+    /// This has only the requests that were sent to the fake server to set chat titles.
+    /// Telegram doesn't return anything, because there isn't anything to return, so there is no
+    /// `.message` field.
+    pub set_chat_titles: Vec<SetChatTitleBody>,
+
+    /// This has only the requests that were sent to the fake server to set chat descriptions.
+    /// Telegram doesn't return anything, because there isn't anything to return, so there is no
+    /// `.message` field.
+    pub set_chat_descriptions: Vec<SetChatDescriptionBody>,
+
+    /// This has only the chat photos that were set by the bot.
+    /// The `.photo` field has the new chat photo, and `.bot_request`
+    /// has the request that was sent to the fake server
+    pub set_chat_photos: Vec<SetChatPhoto>,
+
+    /// This has only the requests that were sent to the fake server to delete chat photos.
+    /// Telegram doesn't return anything, because there isn't anything to return, so there is no
+    /// `.message` field.
+    pub deleted_chat_photos: Vec<DeleteChatPhotoBody>,
+
+    /// This has only the requests that were sent to the fake server to set chat permissions.
+    /// Telegram doesn't return anything, because there isn't anything to return, so there is no
+    /// `.message` field.
+    pub set_chat_permissions: Vec<SetChatPermissionsBody>,
+
     /// This has only the requests that were sent to the fake server to send chat actions.
     /// Telegram doesn't return anything, because there isn't anything to return, so there is no
     /// `.message` field.
     pub sent_chat_actions: Vec<SendChatActionBody>,
-    
+
     /// This has only the requests that were sent to the fake server to set message reactions.
     /// Telegram doesn't return anything, because there isn't anything to return, so there is no
     /// `.message` field.
     pub set_message_reaction: Vec<SetMessageReaction>,
+
+    /// This has only the polls that were stopped by the bot.
+    /// The `.poll` field has the final poll, and `.bot_request`
+    /// has the request that was sent to the fake server
+    pub stopped_polls: Vec<StoppedPoll>,
+
+    /// This has only the forum topics that were created by the bot.
+    /// The `.topic` field has the created topic, and `.bot_request`
+    /// has the request that was sent to the fake server
+    pub created_forum_topics: Vec<CreatedForumTopic>,
+
+    /// This has only the requests that were sent to the fake server to edit forum topics.
+    /// Telegram doesn't return anything, because there isn't anything to return, so there is no
+    /// `.message` field.
+    pub edited_forum_topics: Vec<EditForumTopicBody>,
+
+    /// This has only the requests that were sent to the fake server to close forum topics.
+    /// Telegram doesn't return anything, because there isn't anything to return, so there is no
+    /// `.message` field.
+    pub closed_forum_topics: Vec<CloseForumTopicBody>,
+
+    /// This has only the requests that were sent to the fake server to reopen forum topics.
+    /// Telegram doesn't return anything, because there isn't anything to return, so there is no
+    /// `.message` field.
+    pub reopened_forum_topics: Vec<ReopenForumTopicBody>,
+
+    /// This has only the requests that were sent to the fake server to delete forum topics.
+    /// Telegram doesn't return anything, because there isn't anything to return, so there is no
+    /// `.message` field.
+    pub deleted_forum_topics: Vec<DeleteForumTopicBody>,
+
+    /// This has only the updates that went through the handler tree without being handled.
+    /// They are collected only if the bot was told to with
+    /// `.unhandled_updates(FailurePolicy::Collect)`
+    pub unhandled_updates: Vec<Update>,
+
+    /// This has only the errors returned by the handlers. The `.update` field has the update
+    /// that caused the error, and `.error` has the `Debug` output of the error.
+    /// They are collected only if the bot was told to with
+    /// `.handler_errors(FailurePolicy::Collect)`
+    pub handler_errors: Vec<HandlerError>,
+
+    /// This has every request that was sent to the fake server, in the order they came in,
+    /// with the responses to them. Useful to check the order of the calls, which the lists above
+    /// can't show.
+    pub requests: Vec<BotRequest>,
+
+    /// This has only the names of the called methods that the fake server doesn't support. What
+    /// the bot got in return depends on `MockBot::unsupported_methods`.
+    pub unsupported_methods: Vec<String>,
 }
 
-lazy_static! {
-    pub static ref MESSAGES: Mutex<Vec<Message>> = Mutex::new(vec![]);  // Messages storage, just in case
-    pub static ref FILES: Mutex<Vec<File>> = Mutex::new(vec![]);  // Messages storage, just in case
-    pub static ref RESPONSES: Mutex<Responses> = Mutex::new(Responses::default());  //
-    pub static ref LAST_MESSAGE_ID: AtomicI32 = AtomicI32::new(0);
+impl Responses {
+    /// Returns the requests to the method, like `SendMessage`. The case of the name doesn't
+    /// matter, just like in telegram.
+    pub fn requests_by_method(&self, method: &str) -> Vec<&BotRequest> {
+        self.requests
+            .iter()
+            .filter(|request| request.method.eq_ignore_ascii_case(method))
+            .collect()
+    }
+
+    /// Returns the requests that were sent to the chat
+    pub fn requests_by_chat(&self, chat_id: i64) -> Vec<&BotRequest> {
+        self.requests
+            .iter()
+            .filter(|request| request.chat_id() == Some(chat_id))
+            .collect()
+    }
+}
+
+/// Messages that the fake server knows about, so the bot can interact with them.
+/// Like in telegram, messages are identified by their chat and their id, and every chat has its
+/// own sequence of message ids
+#[derive(Default)]
+pub struct Messages {
+    messages: Vec<Message>,
+    last_message_ids: HashMap<i64, i32>,
+    // Messages sent via inline mode, they are known only by their inline message id
+    inline_messages: HashMap<String, Message>,
+}
+
+fn edit_message_field<T>(message: &Message, field: &str, value: T) -> Option<Message>
+where
+    T: Serialize,
+{
+    let mut json = serde_json::to_value(message).ok()?; // Convert the message to JSON
+    json[field] = serde_json::to_value(value).ok()?; // Edit the field
+    serde_json::from_value(json).ok() // Convert back to Message
 }
 
-impl MESSAGES {
-    pub fn max_message_id(&self) -> i32 {
-        LAST_MESSAGE_ID.load(Ordering::Relaxed)
+impl Messages {
+    pub fn max_message_id(&self, chat_id: i64) -> i32 {
+        self.last_message_ids.get(&chat_id).copied().unwrap_or(0)
     }
 
-    pub fn edit_message<T>(&self, message_id: i32, field: &str, value: T) -> Option<Message>
+    pub fn edit_message<T>(
+        &mut self,
+        chat_id: i64,
+        message_id: i32,
+        field: &str,
+        value: T,
+    ) -> Option<Message>
     where
         T: Serialize,
     {
-        let mut messages = self.lock().unwrap(); // Get the message lock
-        let message = messages.iter().find(|m| m.id.0 == message_id)?; // Find the message
-                                                                       // (return None if not found)
+        let message = self.get_message(chat_id, message_id)?; // Find the message
+                                                              // (return None if not found)
 
-        let mut json = serde_json::to_value(&message).ok()?; // Convert the message to JSON
-        json[field] = serde_json::to_value(value).ok()?; // Edit the field
-        let new_message: Message = serde_json::from_value(json).ok()?; // Convert back to Message
+        let new_message = edit_message_field(&message, field, value)?;
 
-        messages.retain(|m| m.id.0 != message_id); // Remove the old message
-        messages.push(new_message.clone()); // Add the new message
+        self.messages
+            .retain(|m| !(m.chat.id.0 == chat_id && m.id.0 == message_id)); // Remove the old message
+        self.messages.push(new_message.clone()); // Add the new message
         Some(new_message) // Profit!
     }
 
     pub fn edit_message_reply_markup(
-        &self,
+        &mut self,
+        chat_id: i64,
         message_id: i32,
         reply_markup: Option<ReplyMarkup>,
     ) -> Option<Message> {
         match reply_markup {
             // Only the inline keyboard can be inside of a message
             Some(ReplyMarkup::InlineKeyboard(reply_markup)) => {
-                MESSAGES.edit_message(message_id, "reply_markup", reply_markup)
+                self.edit_message(chat_id, message_id, "reply_markup", reply_markup)
             }
-            _ => MESSAGES.get_message(message_id),
+            _ => self.get_message(chat_id, message_id),
         }
     }
 
-    pub fn add_message(&self, message: Message) -> Message {
-        self.lock().unwrap().push(message.clone());
-        LAST_MESSAGE_ID.fetch_add(1, Ordering::Relaxed);
+    pub fn add_message(&mut self, message: Message) -> Message {
+        self.messages.push(message.clone());
+        let last_id = self.last_message_ids.entry(message.chat.id.0).or_insert(0);
+        *last_id = (*last_id).max(message.id.0);
         message
     }
 
-    pub fn get_message(&self, message_id: i32) -> Option<Message> {
-        self.lock()
-            .unwrap()
+    pub fn get_message(&self, chat_id: i64, message_id: i32) -> Option<Message> {
+        self.messages
             .iter()
-            .find(|m| m.id.0 == message_id)
+            .find(|m| m.chat.id.0 == chat_id && m.id.0 == message_id)
             .cloned()
     }
 
-    pub fn delete_message(&self, message_id: i32) -> Option<Message> {
-        let mut messages = self.lock().unwrap();
-        let message = messages.iter().find(|m| m.id.0 == message_id).cloned()?;
-        messages.retain(|m| m.id.0 != message_id);
+    pub fn delete_message(&mut self, chat_id: i64, message_id: i32) -> Option<Message> {
+        let message = self.get_message(chat_id, message_id)?;
+        self.messages
+            .retain(|m| !(m.chat.id.0 == chat_id && m.id.0 == message_id));
         Some(message)
     }
+
+    /// Deletes the messages in the thread, including the message that started it
+    pub fn delete_thread_messages(&mut self, chat_id: i64, thread_id: ThreadId) -> Vec<Message> {
+        let (deleted, kept) = self.messages.drain(..).partition(|m| {
+            m.chat.id.0 == chat_id && (m.thread_id == Some(thread_id) || m.id == thread_id.0)
+        });
+        self.messages = kept;
+        deleted
+    }
+
+    pub fn all(&self) -> &[Message] {
+        &self.messages
+    }
+
+    pub fn add_inline_message(&mut self, inline_message_id: String, message: Message) -> Message {
+        self.inline_messages
+            .insert(inline_message_id, message.clone());
+        message
+    }
+
+    pub fn get_inline_message(&self, inline_message_id: &str) -> Option<Message> {
+        self.inline_messages.get(inline_message_id).cloned()
+    }
+
+    pub fn edit_inline_message<T>(
+        &mut self,
+        inline_message_id: &str,
+        field: &str,
+        value: T,
+    ) -> Option<Message>
+    where
+        T: Serialize,
+    {
+        let message = self.inline_messages.get(inline_message_id)?;
+        let new_message = edit_message_field(message, field, value)?;
+        self.inline_messages
+            .insert(inline_message_id.to_string(), new_message.clone());
+        Some(new_message)
+    }
 }
 
-pub async fn ping() -> impl Responder {
-    "pong"
+/// Files that the bot can get with /GetFile and download, with their content
+#[derive(Default)]
+pub struct Files {
+    files: Vec<File>,
+    // Content of the files by their file_id
+    contents: HashMap<String, Vec<u8>>,
 }
 
-#[allow(dead_code)]
-pub async fn log_request(body: web::Json<serde_json::Value>) -> impl Responder {
-    dbg!(body);
-    HttpResponse::Ok()
+impl Files {
+    /// Adds the file, replacing the one with the same id. The path is made out of the id, so
+    /// every file is downloaded with its own content. `None` content keeps the known one.
+    pub fn add_file(&mut self, meta: FileMeta, file_name: &str, content: Option<Vec<u8>>) -> File {
+        let file = File {
+            path: format!("{}_{}", meta.id, file_name),
+            meta,
+        };
+        if let Some(content) = content {
+            self.set_content(&file.meta.id, content);
+        }
+        self.files.retain(|f| f.meta.id != file.meta.id);
+        self.files.push(file.clone());
+        file
+    }
+
+    pub fn set_content(&mut self, file_id: &str, content: Vec<u8>) {
+        self.contents.insert(file_id.to_string(), content);
+    }
+
+    pub fn get_file(&self, file_id: &str) -> Option<File> {
+        self.files.iter().find(|f| f.meta.id == file_id).cloned()
+    }
+
+    /// Returns the content of the file with that path. Files without known content are empty
+    pub fn get_content(&self, file_path: &str) -> Option<Vec<u8>> {
+        let file = self.files.iter().find(|f| f.path == file_path)?;
+        Some(
+            self.contents
+                .get(&file.meta.id)
+                .cloned()
+                .unwrap_or_default(),
+        )
+    }
 }
 
+/// A forum topic with the state that isn't a part of `ForumTopic`
+#[derive(Clone, Debug)]
+pub struct Topic {
+    pub topic: ForumTopic,
+    pub is_closed: bool,
+}
+
+/// Forum topics of the supergroups, by the chat id. The chats become forums when the bot gets a
+/// message from a forum chat or creates a topic there
 #[derive(Default)]
-struct StopHandle {
-    inner: parking_lot::Mutex<Option<ServerHandle>>,
+pub struct ForumTopics {
+    forums: HashSet<i64>,
+    topics: HashMap<i64, Vec<Topic>>,
 }
 
-impl StopHandle {
-    /// Sets the server handle to stop.
-    pub(crate) fn register(&self, handle: ServerHandle) {
-        *self.inner.lock() = Some(handle);
+impl ForumTopics {
+    pub fn add_forum(&mut self, chat_id: i64) {
+        self.forums.insert(chat_id);
     }
 
-    /// Sends stop signal through contained server handle.
-    pub(crate) fn stop(&self, graceful: bool) {
-        #[allow(clippy::let_underscore_future)]
-        let _ = self.inner.lock().as_ref().unwrap().stop(graceful);
+    pub fn is_forum(&self, chat_id: i64) -> bool {
+        self.forums.contains(&chat_id)
+    }
+
+    /// Adds the topic, replacing the one with the same thread id
+    pub fn add_topic(&mut self, chat_id: i64, topic: ForumTopic) -> Topic {
+        self.add_forum(chat_id);
+        let topics = self.topics.entry(chat_id).or_default();
+        topics.retain(|t| t.topic.thread_id != topic.thread_id);
+        let topic = Topic {
+            topic,
+            is_closed: false,
+        };
+        topics.push(topic.clone());
+        topic
+    }
+
+    pub fn get_topic(&self, chat_id: i64, thread_id: i32) -> Option<&Topic> {
+        self.topics
+            .get(&chat_id)?
+            .iter()
+            .find(|t| t.topic.thread_id.0 .0 == thread_id)
+    }
+
+    pub fn get_topic_mut(&mut self, chat_id: i64, thread_id: i32) -> Option<&mut Topic> {
+        self.topics
+            .get_mut(&chat_id)?
+            .iter_mut()
+            .find(|t| t.topic.thread_id.0 .0 == thread_id)
+    }
+
+    pub fn delete_topic(&mut self, chat_id: i64, thread_id: i32) -> Option<Topic> {
+        let topic = self.get_topic(chat_id, thread_id)?.clone();
+        self.topics
+            .get_mut(&chat_id)?
+            .retain(|t| t.topic.thread_id.0 .0 != thread_id);
+        Some(topic)
+    }
+}
+
+/// Members of the chats, by the chat id. They are set by `MockBot::chat_members`, and the
+/// routes and updates change them
+#[derive(Default)]
+pub struct ChatMembers {
+    members: HashMap<i64, Vec<ChatMember>>,
+}
+
+impl ChatMembers {
+    /// Replaces all of the members of the chat
+    pub fn set_members(&mut self, chat_id: i64, members: Vec<ChatMember>) {
+        self.members.insert(chat_id, members);
+    }
+
+    /// Adds the member, replacing the one with the same user id
+    pub fn set_member(&mut self, chat_id: i64, member: ChatMember) {
+        let members = self.members.entry(chat_id).or_default();
+        members.retain(|m| m.user.id != member.user.id);
+        members.push(member);
+    }
+
+    /// Returns the member as it is now, the bans and restrictions that are over are lifted
+    pub fn get_member(&self, chat_id: i64, user_id: u64) -> Option<ChatMember> {
+        self.members
+            .get(&chat_id)?
+            .iter()
+            .find(|m| m.user.id.0 == user_id)
+            .map(current_member)
+    }
+
+    /// Returns the members as they are now, the bans and restrictions that are over are lifted
+    pub fn members(&self, chat_id: i64) -> Vec<ChatMember> {
+        self.members
+            .get(&chat_id)
+            .map(|members| members.iter().map(current_member).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn is_banned(&self, chat_id: i64, user_id: u64) -> bool {
+        self.get_member(chat_id, user_id)
+            .is_some_and(|member| member.kind.is_banned())
+    }
+
+    pub fn is_restricted(&self, chat_id: i64, user_id: u64) -> bool {
+        self.get_member(chat_id, user_id)
+            .is_some_and(|member| member.kind.is_restricted())
+    }
+
+    /// Finds the user in any of the chats
+    pub fn find_user(&self, user_id: u64) -> Option<&User> {
+        self.members
+            .values()
+            .flatten()
+            .map(|m| &m.user)
+            .find(|user| user.id.0 == user_id)
+    }
+}
+
+/// The chats that the bot knows about, by the chat id. They are added when the bot gets an update
+/// from the chat or by `MockBot::chat`, and the routes like `/SetChatTitle` change them
+#[derive(Default)]
+pub struct Chats {
+    chats: HashMap<i64, Chat>,
+}
+
+impl Chats {
+    /// Adds the chat, if the bot doesn't know about it yet. The chats from the updates don't
+    /// overwrite the changes that the bot made
+    pub fn add_chat(&mut self, chat: Chat) {
+        self.chats.entry(chat.id.0).or_insert(chat);
+    }
+
+    /// Adds the chat, replacing the one with the same id
+    pub fn set_chat(&mut self, chat: Chat) {
+        self.chats.insert(chat.id.0, chat);
+    }
+
+    pub fn get_chat(&self, chat_id: i64) -> Option<&Chat> {
+        self.chats.get(&chat_id)
+    }
+
+    pub fn get_chat_mut(&mut self, chat_id: i64) -> Option<&mut Chat> {
+        self.chats.get_mut(&chat_id)
     }
 }
 
-async fn stop(Path(graceful): Path<bool>, stop_handle: web::Data<StopHandle>) -> HttpResponse {
-    stop_handle.stop(graceful);
-    HttpResponse::NoContent().finish()
+fn current_member(member: &ChatMember) -> ChatMember {
+    let (until_date, is_member) = match &member.kind {
+        ChatMemberKind::Banned(banned) => (banned.until_date, false),
+        ChatMemberKind::Restricted(restricted) => (restricted.until_date, restricted.is_member),
+        _ => return member.clone(),
+    };
+    match until_date {
+        UntilDate::Date(date) if date <= Utc::now() => ChatMember {
+            user: member.user.clone(),
+            kind: if is_member {
+                ChatMemberKind::Member
+            } else {
+                ChatMemberKind::Left
+            },
+        },
+        _ => member.clone(),
+    }
 }
 
-pub async fn main(port: Mutex<u16>, me: Me) {
-    // MESSAGES don't care if they are cleaned or not
-    *RESPONSES.lock().unwrap() = Responses::default();
+/// Everything the fake server stores. Every `MockBot` owns its own state, and it is given to
+/// the routes as `web::Data<Mutex<State>>`, so different bots never see each other's data.
+pub struct State {
+    /// Messages storage, messages don't care if they are cleaned or not
+    pub messages: Messages,
+    /// Files that can be retrieved with /GetFile and downloaded
+    pub files: Files,
+    /// Forum topics of the supergroups, so the messages can be sent to them
+    pub forum_topics: ForumTopics,
+    /// Members of the chats, set by `MockBot::chat_members`
+    pub chat_members: ChatMembers,
+    /// The chats from the updates and `MockBot::chat`, with the changes that the bot made
+    pub chats: Chats,
+    /// The rights of the bot in the chats where it isn't a member, set by
+    /// `MockMe::administrator_rights`. `None` means all of the rights
+    pub administrator_rights: Option<ChatAdministratorRights>,
+    /// Responses of the current dispatch, erased for every new dispatch
+    pub responses: Responses,
+    /// The bot that sends the messages, updated by `MockBot` on every dispatch
+    pub me: Me,
+    /// Results of all the answered inline queries, so the chosen ones can become inline messages
+    pub inline_query_results: Vec<InlineQueryResult>,
+    /// What to answer to the methods without a route, updated by `MockBot` on every dispatch
+    pub unsupported_methods: UnsupportedMethods,
+    /// Errors to answer with instead of the real responses, added by `MockBot::fail_next` and
+    /// `MockBot::fail_when`
+    pub(crate) injected_errors: Vec<InjectedError>,
+    /// Payments from the dispatched successful payment messages
+    pub payments: Vec<Payment>,
+    /// Votes in the polls that the bot knows about, by poll id and then by voter id, so the
+    /// voters can change or retract them
+    pub poll_votes: HashMap<String, HashMap<i64, Vec<u8>>>,
+    /// The flood control, set by `MockBot::rate_limits`
+    pub rate_limits: RateLimits,
+    /// The Bot API limits that the routes check, set by `MockBot::api_limits`
+    pub api_limits: ApiLimits,
+    // When the calls that count for the flood control were made, and to what chat
+    limited_calls: Vec<(i64, Instant)>,
+    // The next /GetMe is the startup call of a real `Dispatcher`, so it isn't recorded
+    pub(crate) dispatcher_get_me: bool,
+}
 
-    let pong = reqwest::get(format!("http://127.0.0.1:{}/ping", port.lock().unwrap())).await;
+impl State {
+    /// Returns the error that the call should fail with, if there is one
+    fn take_injected_error(&mut self, call: &ApiCall) -> Option<ApiError> {
+        let index = self
+            .injected_errors
+            .iter()
+            .position(|injected| (injected.predicate)(call))?;
+        if self.injected_errors[index].once {
+            return Some(self.injected_errors.remove(index).error);
+        }
+        Some(self.injected_errors[index].error.clone())
+    }
 
-    if pong.is_err()
-    // If it errored, no server is running, we need to start it
+    /// Returns the error for the calls that send something to a chat, if the bot can't do it
+    /// there, because it was blocked, kicked or restricted
+    fn check_chat_access(&self, call: &ApiCall) -> Option<ApiError> {
+        let method = call.method.to_lowercase();
+        let is_sending = method.starts_with("send")
+            || method.starts_with("forwardmessage")
+            || method.starts_with("copymessage");
+        if !is_sending {
+            return None;
+        }
+        let chat_id = call.chat_id()?;
+        let me = self.chat_members.get_member(chat_id, self.me.id.0);
+        if chat_id > 0 {
+            // The user blocked the bot, or the bot banned the user
+            let user_is_banned = self.chat_members.is_banned(chat_id, chat_id as u64);
+            let bot_is_banned = me.as_ref().is_some_and(|me| me.kind.is_banned());
+            return (user_is_banned || bot_is_banned).then_some(ApiError::BotBlocked);
+        }
+        match me?.kind {
+            ChatMemberKind::Banned(_) => Some(ApiError::BotKicked),
+            ChatMemberKind::Left => Some(ApiError::Unknown(
+                "Forbidden: bot is not a member of the group chat".to_string(),
+            )),
+            ChatMemberKind::Restricted(restricted) if !restricted.can_send_messages => {
+                Some(ApiError::Unknown(
+                    "Bad Request: not enough rights to send text messages to the chat".to_string(),
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// Counts the call for the flood control. Returns how many seconds to wait if there were
+    /// too many calls, the call isn't counted then.
+    fn check_rate_limits(&mut self, call: &ApiCall) -> Option<u32> {
+        let chat_id = call.chat_id()?;
+        let now = Instant::now();
+        let retry_after = |limit: Option<RateLimit>, chat: Option<i64>| {
+            let limit = limit?;
+            let times: Vec<Instant> = self
+                .limited_calls
+                .iter()
+                .filter(|(id, time)| {
+                    (chat.is_none() || chat == Some(*id)) && now.duration_since(*time) < limit.per
+                })
+                .map(|(_, time)| *time)
+                .collect();
+            if times.len() < limit.calls {
+                return None;
+            }
+            // The call can be made when enough of the old calls are out of the window
+            let freed_at = times[times.len() - limit.calls] + limit.per;
+            Some((freed_at - now).as_secs_f64().ceil().max(1.0) as u32)
+        };
+
+        let retry_after = retry_after(self.rate_limits.per_chat, Some(chat_id))
+            .or_else(|| retry_after(self.rate_limits.global, None));
+        if retry_after.is_none() {
+            self.limited_calls.push((chat_id, now));
+        }
+        retry_after
+    }
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            messages: Messages::default(),
+            files: Files::default(),
+            forum_topics: ForumTopics::default(),
+            chat_members: ChatMembers::default(),
+            chats: Chats::default(),
+            administrator_rights: None,
+            responses: Responses::default(),
+            me: MockMe::new().build(),
+            inline_query_results: vec![],
+            unsupported_methods: UnsupportedMethods::default(),
+            injected_errors: vec![],
+            payments: vec![],
+            poll_votes: HashMap::new(),
+            rate_limits: RateLimits::default(),
+            api_limits: ApiLimits::default(),
+            limited_calls: vec![],
+            dispatcher_get_me: false,
+        }
+    }
+}
+
+/// Records every request to the bot api with its response in `Responses::requests`
+async fn record_request(
+    mut req: ServiceRequest,
+    next: Next<BoxBody>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    // The api methods are at /bot{token}/{method}, everything else (like files) is not recorded
+    let method = match req.path().split('/').collect::<Vec<_>>()[..] {
+        ["", bot, method] if bot.starts_with("bot") => method.to_string(),
+        _ => return next.call(req).await,
+    };
+    let state = req.app_data::<web::Data<Mutex<State>>>().unwrap().clone();
+    // The startup call of the dispatcher isn't made by the bot, so it can't be failed either
+    if method.eq_ignore_ascii_case("GetMe")
+        && std::mem::take(&mut state.lock().unwrap().dispatcher_get_me)
     {
-        let stop_handle = web::Data::new(StopHandle::default());
+        return next.call(req).await;
+    }
+    let timestamp = Utc::now();
+
+    let mut raw_body = BytesMut::new();
+    let mut payload = req.take_payload();
+    while let Some(chunk) = payload.next().await {
+        raw_body.extend_from_slice(&chunk?);
+    }
+    let raw_body = raw_body.freeze();
+    // The route still needs the body, so it is put back
+    req.set_payload(bytes_to_payload(raw_body.clone()));
+
+    let body = if req.content_type().starts_with("multipart/form-data") {
+        let mut multipart = Multipart::new(req.headers(), bytes_to_payload(raw_body));
+        let (fields, attachments) = get_raw_multipart_fields(&mut multipart).await;
+        RequestBody::Multipart {
+            fields,
+            attachments,
+        }
+    } else {
+        RequestBody::Json(serde_json::from_slice(&raw_body).unwrap_or_default())
+    };
+
+    let error_response = {
+        let mut lock = state.lock().unwrap();
+        let call = ApiCall {
+            call_number: lock.responses.requests_by_method(&method).len() + 1,
+            method: method.clone(),
+            body: body.clone(),
+        };
+        match lock
+            .take_injected_error(&call)
+            .or_else(|| lock.check_chat_access(&call))
+        {
+            Some(error) => Some(make_telegram_error(error)),
+            None => lock.check_rate_limits(&call).map(make_retry_after_error),
+        }
+    };
+    let (req, res) = match error_response {
+        // The route isn't called at all, just like telegram doesn't do anything on errors
+        Some(error_response) => (req.into_parts().0, error_response),
+        None => next.call(req).await?.into_parts(),
+    };
+    let (res, response_body) = res.into_parts();
+    let response_body = to_bytes(response_body).await?;
+    let response = serde_json::from_slice(&response_body).unwrap_or_else(|_| {
+        // Errors like "Bad Request" are just text
+        serde_json::Value::String(String::from_utf8_lossy(&response_body).to_string())
+    });
+
+    state.lock().unwrap().responses.requests.push(BotRequest {
+        method,
+        body,
+        response,
+        timestamp,
+    });
+
+    let res = res.set_body(response_body).map_into_boxed_body();
+    Ok(ServiceResponse::new(req, res))
+}
+
+fn bytes_to_payload(bytes: Bytes) -> Payload {
+    let stream: Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>> =
+        Box::pin(stream::once(async move { Ok(bytes) }));
+    Payload::from(stream)
+}
+
+#[allow(dead_code)]
+pub async fn log_request(body: web::Json<serde_json::Value>) -> impl Responder {
+    dbg!(body);
+    HttpResponse::Ok()
+}
+
+/// The running fake server of a `MockBot`. It has its own thread and actix system, so it doesn't
+/// care about the runtime of the tests, and it is stopped when this is dropped.
+pub struct ServerManager {
+    handle: ServerHandle,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ServerManager {
+    /// Starts the fake server on the `listener`, and returns only when it is ready to take
+    /// requests
+    pub async fn start(listener: TcpListener, state: Arc<Mutex<State>>) -> Self {
+        let ready = Arc::new(Notify::new());
+        let state = web::Data::from(state);
         // let _ = env_logger::builder()
         //     .filter_level(log::LevelFilter::Info)
         //     .format_target(false)
         //     .format_timestamp(None)
         //     .try_init();
         let server = HttpServer::new({
-            let stop_handle = stop_handle.clone();
+            let ready = ready.clone();
 
             move || {
+                // This is called when the worker starts, and there is only one worker
+                ready.notify_one();
                 App::new()
                     // .wrap(actix_web::middleware::Logger::default())
-                    .app_data(stop_handle.clone())
-                    .app_data(web::Data::new(me.clone()))
-                    .route("/ping", web::get().to(ping))
-                    .route("/stop/{graceful}", web::post().to(stop))
+                    .wrap(from_fn(record_request))
+                    .app_data(state.clone())
                     .route("/bot{token}/GetFile", web::post().to(get_file))
+                    .route("/bot{token}/GetMe", web::post().to(get_me))
                     .route("/bot{token}/SendMessage", web::post().to(send_message))
                     .route("/bot{token}/SendPhoto", web::post().to(send_photo))
                     .route("/bot{token}/SendVideo", web::post().to(send_video))
@@ -477,6 +1225,10 @@ pub async fn main(port: Mutex<u16>, me: Me) {
                         "/bot{token}/AnswerCallbackQuery",
                         web::post().to(answer_callback_query),
                     )
+                    .route(
+                        "/bot{token}/AnswerInlineQuery",
+                        web::post().to(answer_inline_query),
+                    )
                     .route(
                         "/bot{token}/PinChatMessage",
                         web::post().to(pin_chat_message),
@@ -502,113 +1254,257 @@ pub async fn main(port: Mutex<u16>, me: Me) {
                         "/bot{token}/SetMessageReaction",
                         web::post().to(set_message_reaction),
                     )
+                    .route("/bot{token}/StopPoll", web::post().to(stop_poll))
+                    .route("/bot{token}/SendInvoice", web::post().to(send_invoice))
+                    .route(
+                        "/bot{token}/AnswerPreCheckoutQuery",
+                        web::post().to(answer_pre_checkout_query),
+                    )
+                    .route(
+                        "/bot{token}/AnswerShippingQuery",
+                        web::post().to(answer_shipping_query),
+                    )
+                    .route(
+                        "/bot{token}/RefundStarPayment",
+                        web::post().to(refund_star_payment),
+                    )
+                    .route(
+                        "/bot{token}/CreateForumTopic",
+                        web::post().to(create_forum_topic),
+                    )
+                    .route(
+                        "/bot{token}/EditForumTopic",
+                        web::post().to(edit_forum_topic),
+                    )
+                    .route(
+                        "/bot{token}/CloseForumTopic",
+                        web::post().to(close_forum_topic),
+                    )
+                    .route(
+                        "/bot{token}/ReopenForumTopic",
+                        web::post().to(reopen_forum_topic),
+                    )
+                    .route(
+                        "/bot{token}/DeleteForumTopic",
+                        web::post().to(delete_forum_topic),
+                    )
+                    .route(
+                        "/bot{token}/GetForumTopicIconStickers",
+                        web::post().to(get_forum_topic_icon_stickers),
+                    )
+                    .route("/bot{token}/GetChatMember", web::post().to(get_chat_member))
+                    .route(
+                        "/bot{token}/GetChatAdministrators",
+                        web::post().to(get_chat_administrators),
+                    )
+                    .route(
+                        "/bot{token}/GetChatMemberCount",
+                        web::post().to(get_chat_member_count),
+                    )
+                    .route(
+                        "/bot{token}/PromoteChatMember",
+                        web::post().to(promote_chat_member),
+                    )
+                    .route(
+                        "/bot{token}/SetChatAdministratorCustomTitle",
+                        web::post().to(set_chat_administrator_custom_title),
+                    )
+                    .route("/bot{token}/GetChat", web::post().to(get_chat))
+                    .route("/bot{token}/SetChatTitle", web::post().to(set_chat_title))
+                    .route(
+                        "/bot{token}/SetChatDescription",
+                        web::post().to(set_chat_description),
+                    )
+                    .route("/bot{token}/SetChatPhoto", web::post().to(set_chat_photo))
+                    .route(
+                        "/bot{token}/DeleteChatPhoto",
+                        web::post().to(delete_chat_photo),
+                    )
+                    .route(
+                        "/bot{token}/SetChatPermissions",
+                        web::post().to(set_chat_permissions),
+                    )
                     .route("/file/bot{token}/{file_name}", web::get().to(download_file))
+                    // Has to be the last one, it catches the methods that aren't supported
+                    .route("/bot{token}/{method}", web::post().to(unknown_method))
             }
         })
-        .bind(format!("127.0.0.1:{}", port.lock().unwrap().to_string()))
-        .unwrap()
+        .listen(listener)
+        .expect("Failed to listen on the fake server socket!")
         .workers(1)
         .run();
 
-        stop_handle.register(server.handle());
+        let handle = server.handle();
+        let thread = std::thread::spawn(move || {
+            System::new()
+                .block_on(server)
+                .expect("The fake server crashed!");
+        });
+        ready.notified().await;
+
+        Self {
+            handle,
+            thread: Some(thread),
+        }
+    }
+}
 
-        server.await.unwrap();
-    };
+impl Drop for ServerManager {
+    fn drop(&mut self) {
+        // The stop command is sent right away, the future only waits for the result
+        #[allow(clippy::let_underscore_future)]
+        let _ = self.handle.stop(false);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::dataset::*;
-    use serial_test::serial;
-    use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+    use teloxide::types::{Banned, InlineKeyboardButton, InlineKeyboardMarkup};
+
+    // Mocked messages are sent to the private chat with the mocked user by default
+    const CHAT_ID: i64 = MockUser::ID as i64;
 
     #[test]
-    #[serial]
     fn test_add_messages() {
-        MESSAGES.lock().unwrap().clear();
-        LAST_MESSAGE_ID.store(0, Ordering::Relaxed);
-        MESSAGES.add_message(
+        let mut messages = Messages::default();
+        messages.add_message(
             message_common::MockMessageText::new()
                 .text("123")
                 .id(1)
                 .build(),
         );
-        MESSAGES.add_message(
+        messages.add_message(
             message_common::MockMessageText::new()
                 .text("123")
                 .id(2)
                 .build(),
         );
-        MESSAGES.add_message(
+        messages.add_message(
             message_common::MockMessageText::new()
                 .text("123")
                 .id(3)
                 .build(),
         );
-        assert_eq!(MESSAGES.max_message_id(), 3);
+        assert_eq!(messages.max_message_id(CHAT_ID), 3);
+    }
+
+    #[test]
+    fn test_messages_are_per_chat() {
+        let mut messages = Messages::default();
+        messages.add_message(
+            message_common::MockMessageText::new()
+                .text("private")
+                .id(1)
+                .build(),
+        );
+        messages.add_message(
+            message_common::MockMessageText::new()
+                .text("group")
+                .chat(MockGroupChat::new().build())
+                .id(1)
+                .build(),
+        );
+        assert_eq!(messages.max_message_id(CHAT_ID), 1);
+        assert_eq!(messages.max_message_id(MockGroupChat::ID), 1);
+        assert_eq!(messages.max_message_id(-1234), 0);
+
+        messages.delete_message(MockGroupChat::ID, 1);
+        assert_eq!(messages.get_message(MockGroupChat::ID, 1), None);
+        assert_eq!(
+            messages.get_message(CHAT_ID, 1).unwrap().text().unwrap(),
+            "private"
+        );
     }
 
     #[test]
-    #[serial]
     fn test_edit_messages() {
-        MESSAGES.lock().unwrap().clear();
-        MESSAGES.add_message(
+        let mut messages = Messages::default();
+        messages.add_message(
             message_common::MockMessageText::new()
                 .text("123")
                 .id(1)
                 .build(),
         );
-        MESSAGES.edit_message(1, "text", "1234");
-        assert_eq!(MESSAGES.get_message(1).unwrap().text().unwrap(), "1234");
+        messages.edit_message(CHAT_ID, 1, "text", "1234");
+        assert_eq!(
+            messages.get_message(CHAT_ID, 1).unwrap().text().unwrap(),
+            "1234"
+        );
     }
 
     #[test]
-    #[serial]
     fn test_get_messages() {
-        MESSAGES.lock().unwrap().clear();
-        MESSAGES.add_message(
+        let mut messages = Messages::default();
+        messages.add_message(
             message_common::MockMessageText::new()
                 .text("123")
                 .id(1)
                 .build(),
         );
-        assert_eq!(MESSAGES.get_message(1).unwrap().text().unwrap(), "123");
+        assert_eq!(
+            messages.get_message(CHAT_ID, 1).unwrap().text().unwrap(),
+            "123"
+        );
     }
 
     #[test]
-    #[serial]
     fn test_delete_messages() {
-        MESSAGES.lock().unwrap().clear();
-        MESSAGES.add_message(
+        let mut messages = Messages::default();
+        messages.add_message(
             message_common::MockMessageText::new()
                 .text("123")
                 .id(1)
                 .build(),
         );
-        MESSAGES.delete_message(1);
-        assert_eq!(MESSAGES.get_message(1), None);
+        messages.delete_message(CHAT_ID, 1);
+        assert_eq!(messages.get_message(CHAT_ID, 1), None);
+    }
+
+    #[test]
+    fn test_edit_inline_messages() {
+        let mut messages = Messages::default();
+        messages.add_inline_message(
+            "inline_id".to_string(),
+            message_common::MockMessageText::new().text("123").build(),
+        );
+        messages.edit_inline_message("inline_id", "text", "1234");
+        assert_eq!(
+            messages
+                .get_inline_message("inline_id")
+                .unwrap()
+                .text()
+                .unwrap(),
+            "1234"
+        );
+        // Inline messages are not chat messages
+        assert_eq!(messages.max_message_id(CHAT_ID), 0);
+        assert!(messages.all().is_empty());
     }
 
     #[test]
-    #[serial]
     fn test_edit_message_reply_markup() {
-        MESSAGES.lock().unwrap().clear();
-        MESSAGES.add_message(
+        let mut messages = Messages::default();
+        messages.add_message(
             message_common::MockMessageText::new()
                 .text("123")
                 .id(1)
                 .build(),
         );
-        MESSAGES.edit_message_reply_markup(
+        messages.edit_message_reply_markup(
+            CHAT_ID,
             1,
             Some(ReplyMarkup::InlineKeyboard(InlineKeyboardMarkup::new(
                 vec![vec![InlineKeyboardButton::callback("123", "123")]],
             ))),
         );
         assert_eq!(
-            MESSAGES
-                .get_message(1)
+            messages
+                .get_message(CHAT_ID, 1)
                 .unwrap()
                 .reply_markup()
                 .unwrap()
@@ -617,4 +1513,44 @@ mod tests {
             "123"
         );
     }
+
+    #[test]
+    fn test_add_chats() {
+        let mut chats = Chats::default();
+        chats.add_chat(MockGroupChat::new().title("Changed").build());
+        // The chat from an update doesn't overwrite the known one
+        chats.add_chat(MockGroupChat::new().title("Original").build());
+        assert_eq!(
+            chats.get_chat(MockGroupChat::ID).unwrap().title(),
+            Some("Changed")
+        );
+
+        chats.set_chat(MockGroupChat::new().title("Replaced").build());
+        assert_eq!(
+            chats.get_chat(MockGroupChat::ID).unwrap().title(),
+            Some("Replaced")
+        );
+        assert!(chats.get_chat(MockSupergroupChat::ID - 1).is_none());
+    }
+
+    #[test]
+    fn test_expired_restrictions() {
+        let mut chat_members = ChatMembers::default();
+        let banned = ChatMember {
+            user: MockUser::new().id(1).build(),
+            kind: ChatMemberKind::Banned(Banned {
+                until_date: UntilDate::Date(Utc::now() - chrono::TimeDelta::seconds(1)),
+            }),
+        };
+        let restricted = ChatMember {
+            user: MockUser::new().id(2).build(),
+            ..MockChatMember::restricted().build()
+        };
+        chat_members.set_members(CHAT_ID, vec![banned, restricted]);
+
+        // The ban is over, so the user just isn't in the chat
+        assert!(!chat_members.is_banned(CHAT_ID, 1));
+        assert!(chat_members.get_member(CHAT_ID, 1).unwrap().kind.is_left());
+        assert!(chat_members.is_restricted(CHAT_ID, 2));
+    }
 }
@@ -0,0 +1,874 @@
+//! Turns the text with `parse_mode` markup into the plain text and its entities, the same way
+//! telegram does it
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use teloxide::types::{MessageEntity, MessageEntityKind, ParseMode};
+use teloxide::ApiError;
+
+use crate::dataset::MockUser;
+
+/// Gets the text and the entities that telegram puts into the message.
+///
+/// If there is a parse mode, the text is parsed and the explicit entities are ignored,
+/// otherwise the text is left as is, with the explicit entities.
+pub fn parse_text(
+    text: &str,
+    parse_mode: Option<ParseMode>,
+    entities: Option<Vec<MessageEntity>>,
+) -> Result<(String, Vec<MessageEntity>), ApiError> {
+    #[allow(deprecated)]
+    let parsed = match parse_mode {
+        None => return Ok((text.to_string(), entities.unwrap_or_default())),
+        Some(ParseMode::Html) => parse_html(text),
+        Some(ParseMode::MarkdownV2) => parse_markdown_v2(text),
+        Some(ParseMode::Markdown) => parse_markdown(text),
+    };
+    parsed.map_err(|error| {
+        ApiError::CantParseEntities(format!("Bad Request: can't parse entities: {error}"))
+    })
+}
+
+#[derive(Default)]
+struct ParsedText {
+    text: String,
+    // Telegram counts entity offsets and lengths in UTF-16 code units
+    utf16_len: usize,
+    entities: Vec<MessageEntity>,
+}
+
+impl ParsedText {
+    fn push(&mut self, c: char) {
+        self.text.push(c);
+        self.utf16_len += c.len_utf16();
+    }
+
+    fn add_entity(&mut self, kind: MessageEntityKind, offset: usize) {
+        // Empty entities are dropped by telegram
+        if self.utf16_len > offset {
+            self.entities.push(MessageEntity {
+                kind,
+                offset,
+                length: self.utf16_len - offset,
+            });
+        }
+    }
+
+    fn finish(mut self) -> (String, Vec<MessageEntity>) {
+        // Outer entities go before the inner ones that start at the same place
+        self.entities
+            .sort_by_key(|entity| (entity.offset, Reverse(entity.length)));
+        (self.text, self.entities)
+    }
+}
+
+/// Makes a text link out of the url, or a text mention, if it is a link to a user
+fn text_link(url: &str) -> Option<MessageEntityKind> {
+    if let Some(user_id) = url.strip_prefix("tg://user?id=") {
+        let user = MockUser::new().id(user_id.parse::<u64>().ok()?).build();
+        return Some(MessageEntityKind::TextMention { user });
+    }
+    // Telegram adds the missing protocol, and skips the links it can't understand
+    let url = reqwest::Url::parse(url)
+        .or_else(|_| reqwest::Url::parse(&format!("http://{url}")))
+        .ok()?;
+    Some(MessageEntityKind::TextLink { url })
+}
+
+fn custom_emoji(url: &str) -> Result<MessageEntityKind, String> {
+    match url.strip_prefix("tg://emoji?id=") {
+        Some(custom_emoji_id) if !custom_emoji_id.is_empty() => {
+            Ok(MessageEntityKind::CustomEmoji {
+                custom_emoji_id: custom_emoji_id.to_string(),
+            })
+        }
+        _ => Err("Custom emoji entity must contain a tg://emoji URL".to_string()),
+    }
+}
+
+//
+// HTML
+//
+
+struct HtmlTag {
+    name: String,
+    kind: Option<MessageEntityKind>,
+    // The links without href use their text as the url
+    is_link: bool,
+    offset: usize,
+    text_start: usize,
+}
+
+/// Decodes `&lt;`, `&gt;`, `&amp;`, `&quot;` and the numeric entities, returns the character and
+/// the length of the entity
+fn decode_html_entity(text: &str) -> Option<(char, usize)> {
+    let (end, _) = text.char_indices().take(12).find(|(_, c)| *c == ';')?;
+    let name = &text[1..end];
+    let c = match name {
+        "lt" => '<',
+        "gt" => '>',
+        "amp" => '&',
+        "quot" => '"',
+        _ => {
+            let code = match name.strip_prefix("#x").or(name.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => name.strip_prefix('#')?.parse().ok()?,
+            };
+            char::from_u32(code)?
+        }
+    };
+    Some((c, end + 1))
+}
+
+fn decode_html(text: &str) -> String {
+    let mut decoded = String::new();
+    let mut i = 0;
+    while let Some(c) = text[i..].chars().next() {
+        if c == '&' {
+            if let Some((decoded_char, len)) = decode_html_entity(&text[i..]) {
+                decoded.push(decoded_char);
+                i += len;
+                continue;
+            }
+        }
+        decoded.push(c);
+        i += c.len_utf8();
+    }
+    decoded
+}
+
+/// Reads the tag that starts at `start` with a `<`, returns its name, its attributes and the
+/// position right after the `>`
+fn parse_html_tag(
+    text: &str,
+    start: usize,
+) -> Result<(String, HashMap<String, String>, usize), String> {
+    let bytes = text.as_bytes();
+    let unclosed = || format!("Unclosed start tag at byte offset {start}");
+    let is_name_byte = |b: u8| b.is_ascii_alphanumeric() || b == b'-' || b == b'_';
+
+    let mut i = start + 1;
+    while i < bytes.len() && is_name_byte(bytes[i]) {
+        i += 1;
+    }
+    let name = text[start + 1..i].to_lowercase();
+    if !matches!(
+        name.as_str(),
+        "a" | "b"
+            | "strong"
+            | "i"
+            | "em"
+            | "u"
+            | "ins"
+            | "s"
+            | "strike"
+            | "del"
+            | "span"
+            | "tg-spoiler"
+            | "tg-emoji"
+            | "code"
+            | "pre"
+            | "blockquote"
+    ) {
+        return Err(format!(
+            "Unsupported start tag \"{name}\" at byte offset {start}"
+        ));
+    }
+
+    let mut attributes = HashMap::new();
+    loop {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        match bytes.get(i) {
+            None => return Err(unclosed()),
+            Some(b'>') => return Ok((name, attributes, i + 1)),
+            _ => {}
+        }
+
+        let attribute_start = i;
+        while i < bytes.len() && is_name_byte(bytes[i]) {
+            i += 1;
+        }
+        if i == attribute_start {
+            return Err(format!(
+                "Empty attribute name in the tag \"{name}\" at byte offset {start}"
+            ));
+        }
+        let attribute = text[attribute_start..i].to_lowercase();
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if bytes.get(i) != Some(&b'=') {
+            // Attributes without a value, like `expandable`
+            attributes.insert(attribute, String::new());
+            continue;
+        }
+        i += 1;
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let value = match bytes.get(i) {
+            None => return Err(unclosed()),
+            Some(&quote) if quote == b'"' || quote == b'\'' => {
+                let value_end = text[i + 1..].find(quote as char).ok_or_else(unclosed)? + i + 1;
+                let value = &text[i + 1..value_end];
+                i = value_end + 1;
+                value
+            }
+            Some(_) => {
+                let value_start = i;
+                while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'>' {
+                    i += 1;
+                }
+                &text[value_start..i]
+            }
+        };
+        attributes.insert(attribute, decode_html(value));
+    }
+}
+
+fn parse_html(text: &str) -> Result<(String, Vec<MessageEntity>), String> {
+    let mut parsed = ParsedText::default();
+    let mut open_tags: Vec<HtmlTag> = vec![];
+    let mut i = 0;
+
+    while let Some(c) = text[i..].chars().next() {
+        if c == '&' {
+            match decode_html_entity(&text[i..]) {
+                Some((decoded, len)) => {
+                    parsed.push(decoded);
+                    i += len;
+                }
+                None => {
+                    parsed.push('&');
+                    i += 1;
+                }
+            }
+            continue;
+        }
+        if c != '<' {
+            parsed.push(c);
+            i += c.len_utf8();
+            continue;
+        }
+
+        if text[i + 1..].starts_with('/') {
+            let tag_end = match text[i..].find('>') {
+                Some(end) => i + end,
+                None => return Err(format!("Unclosed end tag at byte offset {i}")),
+            };
+            let name = text[i + 2..tag_end].trim().to_lowercase();
+            let Some(open_tag) = open_tags.pop() else {
+                return Err(format!("Unexpected end tag at byte offset {i}"));
+            };
+            if open_tag.name != name {
+                return Err(format!(
+                    "Unmatched end tag at byte offset {i}, expected \"</{}>\", found \"</{name}>\"",
+                    open_tag.name
+                ));
+            }
+            let kind = match open_tag.kind {
+                None if open_tag.is_link => text_link(&parsed.text[open_tag.text_start..]),
+                kind => kind,
+            };
+            if let Some(kind) = kind {
+                parsed.add_entity(kind, open_tag.offset);
+            }
+            i = tag_end + 1;
+            continue;
+        }
+
+        let (name, attributes, tag_end) = parse_html_tag(text, i)?;
+        let mut is_link = false;
+        let kind = match name.as_str() {
+            "b" | "strong" => Some(MessageEntityKind::Bold),
+            "i" | "em" => Some(MessageEntityKind::Italic),
+            "u" | "ins" => Some(MessageEntityKind::Underline),
+            "s" | "strike" | "del" => Some(MessageEntityKind::Strikethrough),
+            "tg-spoiler" => Some(MessageEntityKind::Spoiler),
+            "span" => match attributes.get("class").map(String::as_str) {
+                Some("tg-spoiler") => Some(MessageEntityKind::Spoiler),
+                _ => {
+                    return Err(format!(
+                        "Tag \"span\" must have class \"tg-spoiler\" at byte offset {i}"
+                    ))
+                }
+            },
+            "tg-emoji" => match attributes.get("emoji-id") {
+                Some(emoji_id) => Some(custom_emoji(&format!("tg://emoji?id={emoji_id}"))?),
+                None => {
+                    return Err(format!(
+                        "Tag \"tg-emoji\" must have attribute \"emoji-id\" at byte offset {i}"
+                    ))
+                }
+            },
+            "blockquote" => Some(MessageEntityKind::Blockquote),
+            "pre" => Some(MessageEntityKind::Pre { language: None }),
+            "code" => {
+                let language = attributes
+                    .get("class")
+                    .and_then(|class| class.strip_prefix("language-"));
+                match (open_tags.last_mut(), language) {
+                    // `<pre><code class="language-rust">` is a single pre entity with a language
+                    (Some(pre), Some(language))
+                        if pre.name == "pre" && pre.offset == parsed.utf16_len =>
+                    {
+                        pre.kind = Some(MessageEntityKind::Pre {
+                            language: Some(language.to_string()),
+                        });
+                        None
+                    }
+                    _ => Some(MessageEntityKind::Code),
+                }
+            }
+            "a" => match attributes.get("href") {
+                Some(href) => text_link(href),
+                None => {
+                    is_link = true;
+                    None
+                }
+            },
+            _ => unreachable!("unsupported tags are rejected by parse_html_tag"),
+        };
+        open_tags.push(HtmlTag {
+            name,
+            kind,
+            is_link,
+            offset: parsed.utf16_len,
+            text_start: parsed.text.len(),
+        });
+        i = tag_end;
+    }
+
+    if let Some(open_tag) = open_tags.pop() {
+        return Err(format!(
+            "Can't find end tag corresponding to start tag \"{}\"",
+            open_tag.name
+        ));
+    }
+    Ok(parsed.finish())
+}
+
+//
+// MarkdownV2
+//
+
+#[derive(Clone, PartialEq)]
+enum Markup {
+    Bold,
+    Italic,
+    Underline,
+    Strikethrough,
+    Spoiler,
+    Code,
+    Pre(Option<String>),
+    TextUrl,
+    CustomEmoji,
+}
+
+impl Markup {
+    // The names telegram uses in its errors
+    fn name(&self) -> &'static str {
+        match self {
+            Markup::Bold => "Bold",
+            Markup::Italic => "Italic",
+            Markup::Underline => "Underline",
+            Markup::Strikethrough => "Strikethrough",
+            Markup::Spoiler => "Spoiler",
+            Markup::Code => "Code",
+            Markup::Pre(_) => "Pre",
+            Markup::TextUrl => "TextUrl",
+            Markup::CustomEmoji => "CustomEmoji",
+        }
+    }
+}
+
+struct OpenMarkup {
+    markup: Markup,
+    offset: usize,
+    text_start: usize,
+    byte_offset: usize,
+}
+
+fn reserved_character(c: char) -> String {
+    format!("Character '{c}' is reserved and must be escaped with the preceding '\\'")
+}
+
+fn parse_markdown_v2(text: &str) -> Result<(String, Vec<MessageEntity>), String> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let char_at = |i: usize| chars.get(i).map(|(_, c)| *c);
+
+    let mut parsed = ParsedText::default();
+    let mut open_markups: Vec<OpenMarkup> = vec![];
+    // Blockquotes are made of lines that start with `>`
+    let mut quote_offset: Option<usize> = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (byte_offset, c) = chars[i];
+        let next = char_at(i + 1);
+
+        // Any ASCII character can be escaped, even inside of the code
+        if c == '\\' && next.is_some_and(|next| next.is_ascii() && next != '\0') {
+            parsed.push(next.unwrap());
+            i += 2;
+            continue;
+        }
+
+        let top = open_markups.last().map(|open| open.markup.clone());
+        let is_code = matches!(top, Some(Markup::Code | Markup::Pre(_)));
+        let is_line_start = i == 0 || chars[i - 1].1 == '\n';
+
+        if c == '\n' && quote_offset.is_some() && next != Some('>') {
+            parsed.add_entity(MessageEntityKind::Blockquote, quote_offset.take().unwrap());
+        }
+        if c == '>' && is_line_start && !is_code {
+            quote_offset.get_or_insert(parsed.utf16_len);
+            i += 1;
+            continue;
+        }
+
+        let is_end = match top {
+            Some(Markup::Bold) => c == '*',
+            Some(Markup::Italic) => {
+                c == '_'
+                    && (next != Some('_')
+                        || matches!(
+                            open_markups.iter().rev().nth(1),
+                            Some(OpenMarkup {
+                                markup: Markup::Underline,
+                                ..
+                            })
+                        ))
+            }
+            Some(Markup::Underline) => c == '_' && next == Some('_'),
+            Some(Markup::Strikethrough) => c == '~',
+            Some(Markup::Spoiler) => c == '|' && next == Some('|'),
+            Some(Markup::Code) => c == '`',
+            Some(Markup::Pre(_)) => c == '`' && next == Some('`') && char_at(i + 2) == Some('`'),
+            Some(Markup::TextUrl | Markup::CustomEmoji) => c == ']',
+            None => false,
+        };
+
+        if is_end {
+            let open = open_markups.pop().unwrap();
+            let kind = match open.markup {
+                Markup::Bold => Some(MessageEntityKind::Bold),
+                Markup::Italic => Some(MessageEntityKind::Italic),
+                Markup::Underline => {
+                    i += 1;
+                    Some(MessageEntityKind::Underline)
+                }
+                Markup::Strikethrough => Some(MessageEntityKind::Strikethrough),
+                Markup::Spoiler => {
+                    i += 1;
+                    Some(MessageEntityKind::Spoiler)
+                }
+                Markup::Code => Some(MessageEntityKind::Code),
+                Markup::Pre(language) => {
+                    i += 2;
+                    Some(MessageEntityKind::Pre { language })
+                }
+                Markup::TextUrl | Markup::CustomEmoji => {
+                    let url = if next == Some('(') {
+                        let mut url = String::new();
+                        let mut j = i + 2;
+                        loop {
+                            match char_at(j) {
+                                None => {
+                                    return Err(format!(
+                                        "Can't find end of a URL at byte offset {}",
+                                        chars[i + 1].0
+                                    ))
+                                }
+                                Some(')') => break,
+                                Some('\\') if char_at(j + 1).is_some() => {
+                                    url.push(char_at(j + 1).unwrap());
+                                    j += 2;
+                                }
+                                Some(url_char) => {
+                                    url.push(url_char);
+                                    j += 1;
+                                }
+                            }
+                        }
+                        i = j;
+                        url
+                    } else {
+                        parsed.text[open.text_start..].to_string()
+                    };
+                    match open.markup {
+                        Markup::CustomEmoji => Some(custom_emoji(&url)?),
+                        _ => text_link(&url),
+                    }
+                }
+            };
+            if let Some(kind) = kind {
+                parsed.add_entity(kind, open.offset);
+            }
+            i += 1;
+            continue;
+        }
+
+        if is_code || !"_*~|[]`!".contains(c) {
+            if !is_code && "()>#+-={}.".contains(c) {
+                return Err(reserved_character(c));
+            }
+            parsed.push(c);
+            i += 1;
+            continue;
+        }
+
+        let markup = match c {
+            '_' if next == Some('_') => {
+                i += 2;
+                Markup::Underline
+            }
+            '|' if next == Some('|') => {
+                i += 2;
+                Markup::Spoiler
+            }
+            '!' if next == Some('[') => {
+                i += 2;
+                Markup::CustomEmoji
+            }
+            '`' if next == Some('`') && char_at(i + 2) == Some('`') => {
+                i += 3;
+                // The language is the first word right after the backticks
+                let mut language_end = i;
+                while char_at(language_end).is_some_and(|c| !c.is_whitespace() && c != '`') {
+                    language_end += 1;
+                }
+                let mut language = None;
+                if language_end != i && char_at(language_end).is_some_and(|c| c != '`') {
+                    language = Some(chars[i..language_end].iter().map(|(_, c)| c).collect());
+                    i = language_end;
+                }
+                // The first new line is not a part of the code
+                if char_at(i) == Some('\r') && char_at(i + 1) == Some('\n') {
+                    i += 2;
+                } else if char_at(i) == Some('\n') {
+                    i += 1;
+                }
+                Markup::Pre(language)
+            }
+            '_' | '*' | '~' | '[' | '`' => {
+                i += 1;
+                match c {
+                    '_' => Markup::Italic,
+                    '*' => Markup::Bold,
+                    '~' => Markup::Strikethrough,
+                    '[' => Markup::TextUrl,
+                    _ => Markup::Code,
+                }
+            }
+            _ => return Err(reserved_character(c)),
+        };
+        open_markups.push(OpenMarkup {
+            markup,
+            offset: parsed.utf16_len,
+            text_start: parsed.text.len(),
+            byte_offset,
+        });
+    }
+
+    if let Some(open) = open_markups.last() {
+        return Err(format!(
+            "Can't find end of {} entity at byte offset {}",
+            open.markup.name(),
+            open.byte_offset
+        ));
+    }
+    if let Some(offset) = quote_offset {
+        parsed.add_entity(MessageEntityKind::Blockquote, offset);
+    }
+    Ok(parsed.finish())
+}
+
+//
+// Legacy Markdown
+//
+
+fn parse_markdown(text: &str) -> Result<(String, Vec<MessageEntity>), String> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let char_at = |i: usize| chars.get(i).map(|(_, c)| *c);
+
+    let mut parsed = ParsedText::default();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (byte_offset, c) = chars[i];
+        let next = char_at(i + 1);
+
+        if c == '\\' && next.is_some_and(|next| "_*`[".contains(next)) {
+            parsed.push(next.unwrap());
+            i += 2;
+            continue;
+        }
+        if !"_*`[".contains(c) {
+            parsed.push(c);
+            i += 1;
+            continue;
+        }
+
+        // No nesting and no escaping inside of the entities
+        let is_pre = c == '`' && next == Some('`') && char_at(i + 2) == Some('`');
+        let end_char = if c == '[' { ']' } else { c };
+        let is_end = |i: usize| {
+            if is_pre {
+                char_at(i) == Some('`')
+                    && char_at(i + 1) == Some('`')
+                    && char_at(i + 2) == Some('`')
+            } else {
+                char_at(i) == Some(end_char)
+            }
+        };
+
+        i += if is_pre { 3 } else { 1 };
+        let mut language = None;
+        if is_pre {
+            let mut language_end = i;
+            while char_at(language_end).is_some_and(|c| !c.is_whitespace() && c != '`') {
+                language_end += 1;
+            }
+            if language_end != i && char_at(language_end).is_some_and(|c| c != '`') {
+                language = Some(chars[i..language_end].iter().map(|(_, c)| c).collect());
+                i = language_end;
+            }
+            if char_at(i) == Some('\n') {
+                i += 1;
+            }
+        }
+
+        let offset = parsed.utf16_len;
+        let text_start = parsed.text.len();
+        while i < chars.len() && !is_end(i) {
+            parsed.push(chars[i].1);
+            i += 1;
+        }
+        if i == chars.len() {
+            return Err(format!(
+                "Can't find end of the entity starting at byte offset {byte_offset}"
+            ));
+        }
+        i += if is_pre { 3 } else { 1 };
+
+        let kind = match c {
+            '_' => Some(MessageEntityKind::Italic),
+            '*' => Some(MessageEntityKind::Bold),
+            '`' if is_pre => Some(MessageEntityKind::Pre { language }),
+            '`' => Some(MessageEntityKind::Code),
+            _ => {
+                let mut url = parsed.text[text_start..].to_string();
+                if char_at(i) == Some('(') {
+                    let url_end = (i + 1..chars.len())
+                        .find(|j| chars[*j].1 == ')')
+                        .ok_or_else(|| {
+                            format!("Can't find end of a URL at byte offset {}", chars[i].0)
+                        })?;
+                    url = chars[i + 1..url_end].iter().map(|(_, c)| c).collect();
+                    i = url_end + 1;
+                }
+                text_link(&url)
+            }
+        };
+        if let Some(kind) = kind {
+            parsed.add_entity(kind, offset);
+        }
+    }
+
+    Ok(parsed.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(kind: MessageEntityKind, offset: usize, length: usize) -> MessageEntity {
+        MessageEntity {
+            kind,
+            offset,
+            length,
+        }
+    }
+
+    fn parse(text: &str, parse_mode: ParseMode) -> (String, Vec<MessageEntity>) {
+        parse_text(text, Some(parse_mode), None).unwrap()
+    }
+
+    fn parse_error(text: &str, parse_mode: ParseMode) -> String {
+        match parse_text(text, Some(parse_mode), None) {
+            Err(ApiError::CantParseEntities(description)) => description,
+            result => panic!("Expected an error, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_no_parse_mode() {
+        let entities = vec![entity(MessageEntityKind::Bold, 0, 2)];
+        assert_eq!(
+            parse_text("<b>", None, Some(entities.clone())).unwrap(),
+            ("<b>".to_string(), entities)
+        );
+    }
+
+    #[test]
+    fn test_html() {
+        let (text, entities) = parse(
+            "<b>bold <i>italic</i></b> &lt;tag&gt; <a href=\"https://example.com\">link</a>",
+            ParseMode::Html,
+        );
+        assert_eq!(text, "bold italic <tag> link");
+        assert_eq!(
+            entities,
+            vec![
+                entity(MessageEntityKind::Bold, 0, 11),
+                entity(MessageEntityKind::Italic, 5, 6),
+                entity(
+                    MessageEntityKind::TextLink {
+                        url: reqwest::Url::parse("https://example.com").unwrap()
+                    },
+                    18,
+                    4
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_html_pre_with_language() {
+        let (text, entities) = parse(
+            "<pre><code class=\"language-rust\">let a = 1;</code></pre>",
+            ParseMode::Html,
+        );
+        assert_eq!(text, "let a = 1;");
+        assert_eq!(
+            entities,
+            vec![entity(
+                MessageEntityKind::Pre {
+                    language: Some("rust".to_string())
+                },
+                0,
+                10
+            )]
+        );
+    }
+
+    #[test]
+    fn test_html_utf16_offsets() {
+        let (text, entities) = parse("😀 <u>😀</u> &#128512;", ParseMode::Html);
+        assert_eq!(text, "😀 😀 😀");
+        assert_eq!(entities, vec![entity(MessageEntityKind::Underline, 3, 2)]);
+    }
+
+    #[test]
+    fn test_html_errors() {
+        assert_eq!(
+            parse_error("<b>bold", ParseMode::Html),
+            "Bad Request: can't parse entities: Can't find end tag corresponding to start tag \"b\""
+        );
+        assert_eq!(
+            parse_error("<b>bold</i>", ParseMode::Html),
+            "Bad Request: can't parse entities: Unmatched end tag at byte offset 7, expected \"</b>\", found \"</i>\""
+        );
+        assert_eq!(
+            parse_error("1 <br> 2", ParseMode::Html),
+            "Bad Request: can't parse entities: Unsupported start tag \"br\" at byte offset 2"
+        );
+    }
+
+    #[test]
+    fn test_markdown_v2() {
+        let (text, entities) = parse(
+            "*bold _italic_* __underline__ ~strike~ ||spoiler|| `code` [link](https://example.com/a\\)b) 1\\.",
+            ParseMode::MarkdownV2,
+        );
+        assert_eq!(text, "bold italic underline strike spoiler code link 1.");
+        assert_eq!(
+            entities,
+            vec![
+                entity(MessageEntityKind::Bold, 0, 11),
+                entity(MessageEntityKind::Italic, 5, 6),
+                entity(MessageEntityKind::Underline, 12, 9),
+                entity(MessageEntityKind::Strikethrough, 22, 6),
+                entity(MessageEntityKind::Spoiler, 29, 7),
+                entity(MessageEntityKind::Code, 37, 4),
+                entity(
+                    MessageEntityKind::TextLink {
+                        url: reqwest::Url::parse("https://example.com/a)b").unwrap()
+                    },
+                    42,
+                    4
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_markdown_v2_pre_and_quote() {
+        let (text, entities) = parse(
+            "```rust\nlet a = *b*;```\n>quoted\n>lines\nend",
+            ParseMode::MarkdownV2,
+        );
+        assert_eq!(text, "let a = *b*;\nquoted\nlines\nend");
+        assert_eq!(
+            entities,
+            vec![
+                entity(
+                    MessageEntityKind::Pre {
+                        language: Some("rust".to_string())
+                    },
+                    0,
+                    12
+                ),
+                entity(MessageEntityKind::Blockquote, 13, 12),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_markdown_v2_errors() {
+        assert_eq!(
+            parse_error("Hello!", ParseMode::MarkdownV2),
+            "Bad Request: can't parse entities: Character '!' is reserved and must be escaped with the preceding '\\'"
+        );
+        assert_eq!(
+            parse_error("1. *bold", ParseMode::MarkdownV2),
+            "Bad Request: can't parse entities: Character '.' is reserved and must be escaped with the preceding '\\'"
+        );
+        assert_eq!(
+            parse_error("some *bold", ParseMode::MarkdownV2),
+            "Bad Request: can't parse entities: Can't find end of Bold entity at byte offset 5"
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_markdown() {
+        let (text, entities) = parse(
+            "*bold* _it_ `code` [link](https://example.com) 1.5 \\_",
+            ParseMode::Markdown,
+        );
+        assert_eq!(text, "bold it code link 1.5 _");
+        assert_eq!(
+            entities,
+            vec![
+                entity(MessageEntityKind::Bold, 0, 4),
+                entity(MessageEntityKind::Italic, 5, 2),
+                entity(MessageEntityKind::Code, 8, 4),
+                entity(
+                    MessageEntityKind::TextLink {
+                        url: reqwest::Url::parse("https://example.com").unwrap()
+                    },
+                    13,
+                    4
+                ),
+            ]
+        );
+        assert_eq!(
+            parse_error("snake_case", ParseMode::Markdown),
+            "Bad Request: can't parse entities: Can't find end of the entity starting at byte offset 5"
+        );
+    }
+}
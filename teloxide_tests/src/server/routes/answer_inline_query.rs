@@ -0,0 +1,35 @@
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
+use serde::Deserialize;
+use teloxide::types::{InlineQueryResult, InlineQueryResultsButton};
+use teloxide::ApiError;
+
+use crate::server::State;
+
+use super::{make_telegram_error, make_telegram_result};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AnswerInlineQueryBody {
+    pub inline_query_id: String,
+    pub results: Vec<InlineQueryResult>,
+    pub cache_time: Option<u32>,
+    pub is_personal: Option<bool>,
+    pub next_offset: Option<String>,
+    pub button: Option<InlineQueryResultsButton>,
+}
+
+pub async fn answer_inline_query(
+    body: web::Json<AnswerInlineQueryBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    if body.results.len() > lock.api_limits.inline_query_results {
+        return make_telegram_error(ApiError::TooMuchInlineQueryResults);
+    }
+    lock.inline_query_results.extend(body.results.clone());
+    lock.responses
+        .answered_inline_queries
+        .push(body.into_inner());
+    make_telegram_result(true)
+}
@@ -0,0 +1,26 @@
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
+use serde::Deserialize;
+
+use crate::server::State;
+
+use super::make_telegram_result;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AnswerPreCheckoutQueryBody {
+    pub pre_checkout_query_id: String,
+    pub ok: bool,
+    pub error_message: Option<String>,
+}
+
+pub async fn answer_pre_checkout_query(
+    body: web::Json<AnswerPreCheckoutQueryBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    lock.responses
+        .answered_pre_checkout_queries
+        .push(body.into_inner());
+    make_telegram_result(true)
+}
@@ -0,0 +1,28 @@
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
+use serde::Deserialize;
+use teloxide::types::ShippingOption;
+
+use crate::server::State;
+
+use super::make_telegram_result;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AnswerShippingQueryBody {
+    pub shipping_query_id: String,
+    pub ok: bool,
+    pub shipping_options: Option<Vec<ShippingOption>>,
+    pub error_message: Option<String>,
+}
+
+pub async fn answer_shipping_query(
+    body: web::Json<AnswerShippingQueryBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    lock.responses
+        .answered_shipping_queries
+        .push(body.into_inner());
+    make_telegram_result(true)
+}
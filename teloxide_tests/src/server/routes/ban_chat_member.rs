@@ -1,10 +1,16 @@
+use std::sync::Mutex;
+
 use actix_web::{web, Responder};
 use serde::Deserialize;
+use teloxide::types::{Banned, ChatMember, ChatMemberKind};
+use teloxide::ApiError;
 
 use crate::server::routes::make_telegram_result;
-use crate::server::{MESSAGES, RESPONSES};
+use crate::server::State;
 
-use super::BodyChatId;
+use super::{
+    chat_member_user, check_can_be_restricted, check_limits, check_my_right, until_date, BodyChatId,
+};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct BanChatMemberBody {
@@ -14,21 +20,37 @@ pub struct BanChatMemberBody {
     pub revoke_messages: Option<bool>,
 }
 
-pub async fn ban_chat_member(body: web::Json<BanChatMemberBody>) -> impl Responder {
+pub async fn ban_chat_member(
+    body: web::Json<BanChatMemberBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
     let chat_id = body.chat_id.id();
-    let messages = MESSAGES.lock().unwrap().clone();
+    check_limits!(check_my_right(
+        &lock,
+        chat_id,
+        |rights| rights.can_restrict_members,
+        ApiError::NotEnoughRightsToRestrict
+    ));
+    check_limits!(check_can_be_restricted(&lock, chat_id, body.user_id));
+    let messages = lock.messages.all().to_vec();
     if body.revoke_messages.is_some() && body.revoke_messages.unwrap() {
         for message in messages {
             if message.chat.id.0 == chat_id
                 && message.from.is_some()
                 && message.from.unwrap().id.0 == body.user_id
             {
-                MESSAGES.delete_message(message.id.0);
+                lock.messages.delete_message(chat_id, message.id.0);
             }
         }
     }
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock.banned_chat_members.push(body.into_inner());
+    let user = chat_member_user(&lock, body.user_id);
+    let kind = ChatMemberKind::Banned(Banned {
+        until_date: until_date(body.until_date),
+    });
+    lock.chat_members
+        .set_member(chat_id, ChatMember { user, kind });
+    lock.responses.banned_chat_members.push(body.into_inner());
 
     make_telegram_result(true)
 }
@@ -0,0 +1,47 @@
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
+use serde::Deserialize;
+use teloxide::types::ThreadId;
+use teloxide::ApiError;
+
+use crate::dataset::MockMessageForumTopicClosed;
+use crate::server::State;
+
+use super::{
+    add_forum_topic_message, check_if_topic_exists, make_telegram_error, make_telegram_result,
+    BodyChatId,
+};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct CloseForumTopicBody {
+    pub chat_id: BodyChatId,
+    pub message_thread_id: ThreadId,
+}
+
+pub async fn close_forum_topic(
+    body: web::Json<CloseForumTopicBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    let chat_id = body.chat_id.id();
+    check_if_topic_exists!(lock, chat_id, body.message_thread_id);
+    let topic = lock
+        .forum_topics
+        .get_topic_mut(chat_id, body.message_thread_id.0 .0)
+        .unwrap();
+    if topic.is_closed {
+        return make_telegram_error(ApiError::Unknown(
+            "Bad Request: TOPIC_NOT_MODIFIED".to_string(),
+        ));
+    }
+    topic.is_closed = true;
+
+    let message = MockMessageForumTopicClosed::new()
+        .chat(body.chat_id.chat())
+        .build();
+    add_forum_topic_message(&mut lock, body.message_thread_id, message);
+    lock.responses.closed_forum_topics.push(body.into_inner());
+
+    make_telegram_result(true)
+}
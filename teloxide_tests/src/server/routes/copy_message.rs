@@ -1,16 +1,24 @@
-use actix_web::error::ErrorBadRequest;
+use std::sync::Mutex;
+
 use actix_web::{web, Responder};
 use serde::Deserialize;
 use serde_json::json;
 use teloxide::types::{
-    Me, MediaAnimation, MediaAudio, MediaDocument, MediaKind, MediaPhoto, MediaVideo, MediaVoice,
+    MediaAnimation, MediaAudio, MediaDocument, MediaKind, MediaPhoto, MediaVideo, MediaVoice,
     MessageEntity, MessageId, MessageKind, ParseMode, ReplyMarkup,
 };
+use teloxide::ApiError;
 
 use crate::server::CopiedMessage;
-use crate::server::{routes::check_if_message_exists, MESSAGES, RESPONSES};
+use crate::server::{
+    routes::{check_if_message_exists, parse_caption},
+    State,
+};
 
-use super::{make_telegram_result, BodyChatId};
+use super::{
+    check_caption, check_limits, check_message_thread, check_reply_markup, make_telegram_error,
+    make_telegram_result, put_in_thread, BodyChatId,
+};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct CopyMessageBody {
@@ -28,12 +36,42 @@ pub struct CopyMessageBody {
     pub reply_markup: Option<ReplyMarkup>,
 }
 
-pub async fn copy_message(body: web::Json<CopyMessageBody>, me: web::Data<Me>) -> impl Responder {
+pub async fn copy_message(
+    body: web::Json<CopyMessageBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    check_limits!(check_reply_markup(
+        &lock.api_limits,
+        body.reply_markup.as_ref()
+    ));
+    let thread = check_message_thread!(lock, body.chat_id.id(), body.message_thread_id);
     let chat = body.chat_id.chat();
-    check_if_message_exists!(body.message_id);
-    let mut message = MESSAGES.get_message(body.message_id).unwrap();
+    let (new_caption, new_caption_entities) = parse_caption!(
+        body.caption.as_deref(),
+        body.parse_mode,
+        body.caption_entities.clone()
+    );
+    check_limits!(check_caption(&lock.api_limits, new_caption.as_deref()));
+    check_if_message_exists!(
+        lock,
+        body.from_chat_id.id(),
+        body.message_id,
+        ApiError::MessageToCopyNotFound
+    );
+    let mut message = lock
+        .messages
+        .get_message(body.from_chat_id.id(), body.message_id)
+        .unwrap();
+
+    if message.has_protected_content() {
+        return make_telegram_error(ApiError::Unknown(
+            "Bad Request: message can't be copied".to_string(),
+        ));
+    }
+
     message.chat = chat;
-    message.from = Some(me.user.clone());
+    message.from = Some(lock.me.user.clone());
 
     if let MessageKind::Common(ref mut common) = message.kind {
         common.forward_origin = None;
@@ -69,8 +107,8 @@ pub async fn copy_message(body: web::Json<CopyMessageBody>, me: web::Data<Me>) -
                 ref mut caption_entities,
                 ..
             }) => {
-                *caption = body.caption.clone();
-                *caption_entities = body.caption_entities.clone().unwrap_or_default();
+                *caption = new_caption;
+                *caption_entities = new_caption_entities;
             }
             _ => {}
         };
@@ -80,14 +118,17 @@ pub async fn copy_message(body: web::Json<CopyMessageBody>, me: web::Data<Me>) -
         common.has_protected_content = body.protect_content.unwrap_or(false);
     }
 
-    let last_id = MESSAGES.max_message_id();
+    let last_id = lock.messages.max_message_id(body.chat_id.id());
     message.id = MessageId(last_id + 1);
     message.chat = body.chat_id.chat();
-    let message = MESSAGES.add_message(message);
+    message.thread_id = None;
+    message.is_topic_message = false;
+    let message = lock
+        .messages
+        .add_message(put_in_thread(message, thread.as_ref()));
 
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock.sent_messages.push(message.clone());
-    responses_lock.copied_messages.push(CopiedMessage {
+    lock.responses.sent_messages.push(message.clone());
+    lock.responses.copied_messages.push(CopiedMessage {
         message_id: message.id,
         bot_request: body.into_inner(),
     });
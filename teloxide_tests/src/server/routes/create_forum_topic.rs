@@ -0,0 +1,63 @@
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
+use serde::Deserialize;
+use teloxide::types::{ForumTopic, MessageId, ThreadId};
+use teloxide::ApiError;
+
+use crate::dataset::MockMessageForumTopicCreated;
+use crate::server::{CreatedForumTopic, State};
+
+use super::{add_forum_topic_message, make_telegram_error, make_telegram_result, BodyChatId};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct CreateForumTopicBody {
+    pub chat_id: BodyChatId,
+    pub name: String,
+    pub icon_color: Option<u32>,
+    pub icon_custom_emoji_id: Option<String>,
+}
+
+pub async fn create_forum_topic(
+    body: web::Json<CreateForumTopicBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    let chat_id = body.chat_id.id();
+    if !lock.forum_topics.is_forum(chat_id) {
+        return make_telegram_error(ApiError::Unknown(
+            "Bad Request: the chat is not a forum".to_string(),
+        ));
+    }
+    let icon_color = body
+        .icon_color
+        .unwrap_or(MockMessageForumTopicCreated::ICON_COLOR);
+    let icon_custom_emoji_id = body
+        .icon_custom_emoji_id
+        .clone()
+        .filter(|id| !id.is_empty());
+
+    // The topic's thread id is the id of the message that created it
+    let thread_id = ThreadId(MessageId(lock.messages.max_message_id(chat_id) + 1));
+    let mut message = MockMessageForumTopicCreated::new()
+        .name(body.name.clone())
+        .icon_color(icon_color)
+        .chat(body.chat_id.chat());
+    message.icon_custom_emoji_id = icon_custom_emoji_id.clone();
+    add_forum_topic_message(&mut lock, thread_id, message.build());
+
+    let [_, red, green, blue] = icon_color.to_be_bytes();
+    let topic = ForumTopic {
+        thread_id,
+        name: body.name.clone(),
+        icon_color: [red, green, blue],
+        icon_custom_emoji_id,
+    };
+    lock.forum_topics.add_topic(chat_id, topic.clone());
+    lock.responses.created_forum_topics.push(CreatedForumTopic {
+        topic: topic.clone(),
+        bot_request: body.into_inner(),
+    });
+
+    make_telegram_result(topic)
+}
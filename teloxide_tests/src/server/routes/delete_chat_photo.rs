@@ -0,0 +1,46 @@
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
+use serde::Deserialize;
+use teloxide::ApiError;
+
+use crate::server::State;
+
+use super::{
+    check_if_chat_exists, check_limits, check_my_right, make_telegram_error, make_telegram_result,
+    public_chat_mut, BodyChatId,
+};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DeleteChatPhotoBody {
+    pub chat_id: BodyChatId,
+}
+
+pub async fn delete_chat_photo(
+    body: web::Json<DeleteChatPhotoBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    let chat_id = body.chat_id.id();
+    check_if_chat_exists!(lock, chat_id);
+    check_limits!(check_my_right(
+        &lock,
+        chat_id,
+        |rights| rights.can_change_info,
+        ApiError::Unknown("Bad Request: CHAT_ADMIN_REQUIRED".to_string())
+    ));
+
+    let chat = match public_chat_mut(&mut lock, chat_id) {
+        Ok(chat) => chat,
+        Err(error) => return make_telegram_error(error),
+    };
+    if chat.photo.take().is_none() {
+        return make_telegram_error(ApiError::Unknown(
+            "Bad Request: CHAT_NOT_MODIFIED".to_string(),
+        ));
+    }
+    lock.responses.deleted_chat_photos.push(body.into_inner());
+
+    // Telegram returns `true`, but teloxide expects a string here
+    make_telegram_result("true")
+}
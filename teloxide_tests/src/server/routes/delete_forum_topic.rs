@@ -0,0 +1,32 @@
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
+use serde::Deserialize;
+use teloxide::types::ThreadId;
+
+use crate::server::State;
+
+use super::{check_if_topic_exists, make_telegram_result, BodyChatId};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DeleteForumTopicBody {
+    pub chat_id: BodyChatId,
+    pub message_thread_id: ThreadId,
+}
+
+pub async fn delete_forum_topic(
+    body: web::Json<DeleteForumTopicBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    let chat_id = body.chat_id.id();
+    check_if_topic_exists!(lock, chat_id, body.message_thread_id);
+    // Deleting a topic deletes all of its messages
+    lock.forum_topics
+        .delete_topic(chat_id, body.message_thread_id.0 .0);
+    lock.messages
+        .delete_thread_messages(chat_id, body.message_thread_id);
+    lock.responses.deleted_forum_topics.push(body.into_inner());
+
+    make_telegram_result(true)
+}
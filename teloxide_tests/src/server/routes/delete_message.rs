@@ -1,11 +1,13 @@
-use actix_web::error::ErrorBadRequest;
+use std::sync::Mutex;
+
 use actix_web::{web, Responder};
 use serde::Deserialize;
+use teloxide::ApiError;
 
 use crate::server::routes::make_telegram_result;
-use crate::server::{DeletedMessage, MESSAGES, RESPONSES};
+use crate::server::{DeletedMessage, State};
 
-use super::{check_if_message_exists, BodyChatId};
+use super::{check_if_message_exists, check_limits, check_my_right, BodyChatId};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct DeleteMessageBody {
@@ -13,11 +15,35 @@ pub struct DeleteMessageBody {
     pub message_id: i32,
 }
 
-pub async fn delete_message(body: web::Json<DeleteMessageBody>) -> impl Responder {
-    check_if_message_exists!(body.message_id);
-    let deleted_message = MESSAGES.delete_message(body.message_id).unwrap();
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock.deleted_messages.push(DeletedMessage {
+pub async fn delete_message(
+    body: web::Json<DeleteMessageBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    check_if_message_exists!(
+        lock,
+        body.chat_id.id(),
+        body.message_id,
+        ApiError::MessageToDeleteNotFound
+    );
+    let message = lock
+        .messages
+        .get_message(body.chat_id.id(), body.message_id)
+        .unwrap();
+    // The bot can always delete its own messages
+    if message.from.as_ref().map(|user| user.id) != Some(lock.me.id) {
+        check_limits!(check_my_right(
+            &lock,
+            body.chat_id.id(),
+            |rights| rights.can_delete_messages,
+            ApiError::MessageCantBeDeleted
+        ));
+    }
+    let deleted_message = lock
+        .messages
+        .delete_message(body.chat_id.id(), body.message_id)
+        .unwrap();
+    lock.responses.deleted_messages.push(DeletedMessage {
         message: deleted_message.clone(),
         bot_request: body.into_inner(),
     });
@@ -1,4 +1,5 @@
 use std::fmt::Error;
+use std::sync::Mutex;
 
 use actix_web::{
     error::ErrorBadRequest,
@@ -7,23 +8,18 @@ use actix_web::{
 };
 use futures_util::{future::ok, stream::once};
 
-use crate::server::FILES;
+use crate::server::State;
 
-pub async fn download_file(path: web::Path<(String, String)>) -> HttpResponse {
-    if FILES
-        .lock()
-        .unwrap()
-        .clone()
-        .into_iter()
-        .find(|f| f.path == path.1)
-        .is_none()
-    {
+pub async fn download_file(
+    path: web::Path<(String, String)>,
+    state: web::Data<Mutex<State>>,
+) -> HttpResponse {
+    let lock = state.lock().unwrap();
+    let Some(content) = lock.files.get_content(&path.1) else {
         return ErrorBadRequest("No such file found").into();
-    }
+    };
 
-    let stream = once(ok::<_, Error>(Bytes::copy_from_slice(
-        "Hello, world!".as_bytes(),
-    )));
+    let stream = once(ok::<_, Error>(Bytes::from(content)));
 
     HttpResponse::Ok().streaming(stream)
 }
@@ -0,0 +1,65 @@
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
+use serde::Deserialize;
+use teloxide::types::ThreadId;
+use teloxide::ApiError;
+
+use crate::dataset::MockMessageForumTopicEdited;
+use crate::server::State;
+
+use super::{
+    add_forum_topic_message, check_if_topic_exists, make_telegram_error, make_telegram_result,
+    BodyChatId,
+};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct EditForumTopicBody {
+    pub chat_id: BodyChatId,
+    pub message_thread_id: ThreadId,
+    pub name: Option<String>,
+    pub icon_custom_emoji_id: Option<String>,
+}
+
+pub async fn edit_forum_topic(
+    body: web::Json<EditForumTopicBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    let chat_id = body.chat_id.id();
+    check_if_topic_exists!(lock, chat_id, body.message_thread_id);
+    let topic = lock
+        .forum_topics
+        .get_topic(chat_id, body.message_thread_id.0 .0)
+        .unwrap()
+        .topic
+        .clone();
+
+    let name = body.name.clone().unwrap_or(topic.name.clone());
+    // An empty string removes the icon
+    let icon_custom_emoji_id = match &body.icon_custom_emoji_id {
+        Some(id) if id.is_empty() => None,
+        Some(id) => Some(id.clone()),
+        None => topic.icon_custom_emoji_id.clone(),
+    };
+    if name == topic.name && icon_custom_emoji_id == topic.icon_custom_emoji_id {
+        return make_telegram_error(ApiError::Unknown(
+            "Bad Request: TOPIC_NOT_MODIFIED".to_string(),
+        ));
+    }
+
+    let mut message = MockMessageForumTopicEdited::new().chat(body.chat_id.chat());
+    message.name = body.name.clone();
+    message.icon_custom_emoji_id = body.icon_custom_emoji_id.clone();
+    add_forum_topic_message(&mut lock, body.message_thread_id, message.build());
+
+    let topic = lock
+        .forum_topics
+        .get_topic_mut(chat_id, body.message_thread_id.0 .0)
+        .unwrap();
+    topic.topic.name = name;
+    topic.topic.icon_custom_emoji_id = icon_custom_emoji_id;
+    lock.responses.edited_forum_topics.push(body.into_inner());
+
+    make_telegram_result(true)
+}
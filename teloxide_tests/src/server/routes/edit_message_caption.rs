@@ -1,12 +1,18 @@
-use actix_web::error::ErrorBadRequest;
+use std::sync::Mutex;
+
 use actix_web::{web, Responder};
 use serde::Deserialize;
 use teloxide::types::{MessageEntity, ParseMode, ReplyMarkup};
+use teloxide::ApiError;
 
 use crate::server::routes::make_telegram_result;
-use crate::server::{EditedMessageCaption, MESSAGES, RESPONSES};
+use crate::server::{EditedMessageCaption, State};
 
-use super::{check_if_message_exists, BodyChatId};
+use super::{
+    check_caption, check_if_inline_message_exists, check_if_message_exists, check_limits,
+    check_reply_markup, edited_reply_markup, is_message_modified, make_telegram_error, parse_text,
+    BodyChatId,
+};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct EditMessageCaptionBody {
@@ -21,27 +27,54 @@ pub struct EditMessageCaptionBody {
     pub reply_markup: Option<ReplyMarkup>,
 }
 
-pub async fn edit_message_caption(body: web::Json<EditMessageCaptionBody>) -> impl Responder {
+pub async fn edit_message_caption(
+    body: web::Json<EditMessageCaptionBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    check_limits!(check_reply_markup(
+        &lock.api_limits,
+        body.reply_markup.as_ref()
+    ));
+    let (caption, caption_entities) = parse_text!(
+        &body.caption,
+        body.parse_mode,
+        body.caption_entities.clone()
+    );
+    check_limits!(check_caption(&lock.api_limits, Some(&caption)));
     match (
         body.chat_id.clone(),
         body.message_id,
         body.inline_message_id.clone(),
     ) {
-        (Some(_), Some(message_id), None) => {
-            check_if_message_exists!(message_id);
-            MESSAGES.edit_message(message_id, "caption", body.caption.clone());
-            MESSAGES.edit_message(
+        (Some(chat_id), Some(message_id), None) => {
+            let chat_id = chat_id.id();
+            check_if_message_exists!(lock, chat_id, message_id, ApiError::MessageToEditNotFound);
+            let old_message = lock.messages.get_message(chat_id, message_id).unwrap();
+            lock.messages
+                .edit_message(chat_id, message_id, "caption", caption.clone());
+            lock.messages.edit_message(
+                chat_id,
                 message_id,
                 "caption_entities",
-                body.caption_entities.clone().unwrap_or_default(),
+                caption_entities.clone(),
             );
 
-            let message = MESSAGES
-                .edit_message_reply_markup(message_id, body.reply_markup.clone())
+            let message = lock
+                .messages
+                .edit_message(
+                    chat_id,
+                    message_id,
+                    "reply_markup",
+                    edited_reply_markup(body.reply_markup.clone()),
+                )
                 .unwrap();
 
-            let mut responses_lock = RESPONSES.lock().unwrap();
-            responses_lock
+            if !is_message_modified(&old_message, &message) {
+                return make_telegram_error(ApiError::MessageNotModified);
+            }
+
+            lock.responses
                 .edited_messages_caption
                 .push(EditedMessageCaption {
                     message: message.clone(),
@@ -50,7 +83,43 @@ pub async fn edit_message_caption(body: web::Json<EditMessageCaptionBody>) -> im
 
             make_telegram_result(message)
         }
-        (None, None, Some(_)) => make_telegram_result(true),
-        _ => ErrorBadRequest("No message_id or inline_message_id were provided").into(),
+        (None, None, Some(inline_message_id)) => {
+            check_if_inline_message_exists!(lock, &inline_message_id);
+            let old_message = lock
+                .messages
+                .get_inline_message(&inline_message_id)
+                .unwrap();
+            lock.messages
+                .edit_inline_message(&inline_message_id, "caption", caption.clone());
+            lock.messages.edit_inline_message(
+                &inline_message_id,
+                "caption_entities",
+                caption_entities.clone(),
+            );
+
+            let message = lock
+                .messages
+                .edit_inline_message(
+                    &inline_message_id,
+                    "reply_markup",
+                    edited_reply_markup(body.reply_markup.clone()),
+                )
+                .unwrap();
+
+            if !is_message_modified(&old_message, &message) {
+                return make_telegram_error(ApiError::MessageNotModified);
+            }
+
+            lock.responses
+                .edited_messages_caption
+                .push(EditedMessageCaption {
+                    message,
+                    bot_request: body.into_inner(),
+                });
+
+            // Inline messages are not returned by telegram
+            make_telegram_result(true)
+        }
+        _ => make_telegram_error(ApiError::MessageIdentifierNotSpecified),
     }
 }
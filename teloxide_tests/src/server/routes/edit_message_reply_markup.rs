@@ -1,12 +1,17 @@
-use crate::server::routes::{check_if_message_exists, make_telegram_result};
-use actix_web::error::ErrorBadRequest;
+use std::sync::Mutex;
+
+use crate::server::routes::{
+    check_if_inline_message_exists, check_if_message_exists, is_message_modified,
+    make_telegram_error, make_telegram_result,
+};
 use actix_web::{web, Responder};
 use serde::Deserialize;
 use teloxide::types::ReplyMarkup;
+use teloxide::ApiError;
 
-use crate::server::{EditedMessageReplyMarkup, MESSAGES, RESPONSES};
+use crate::server::{EditedMessageReplyMarkup, State};
 
-use super::BodyChatId;
+use super::{check_limits, check_reply_markup, BodyChatId};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct EditMessageReplyMarkupBody {
@@ -19,26 +24,39 @@ pub struct EditMessageReplyMarkupBody {
 
 pub async fn edit_message_reply_markup(
     body: web::Json<EditMessageReplyMarkupBody>,
+    state: web::Data<Mutex<State>>,
 ) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    check_limits!(check_reply_markup(
+        &lock.api_limits,
+        body.reply_markup.as_ref()
+    ));
     match (
         body.chat_id.clone(),
         body.message_id,
         body.inline_message_id.clone(),
     ) {
-        (Some(_), Some(message_id), None) => {
-            check_if_message_exists!(message_id);
+        (Some(chat_id), Some(message_id), None) => {
+            let chat_id = chat_id.id();
+            check_if_message_exists!(lock, chat_id, message_id, ApiError::MessageToEditNotFound);
+            let old_message = lock.messages.get_message(chat_id, message_id).unwrap();
 
             let message = match body.reply_markup.clone() {
-                Some(reply_markup) => MESSAGES
-                    .edit_message(message_id, "reply_markup", reply_markup)
+                Some(reply_markup) => lock
+                    .messages
+                    .edit_message(chat_id, message_id, "reply_markup", reply_markup)
                     .unwrap(),
-                None => MESSAGES
-                    .edit_message(message_id, "reply_markup", None::<()>)
+                None => lock
+                    .messages
+                    .edit_message(chat_id, message_id, "reply_markup", None::<()>)
                     .unwrap(),
             };
 
-            let mut response_lock = RESPONSES.lock().unwrap();
-            response_lock
+            if !is_message_modified(&old_message, &message) {
+                return make_telegram_error(ApiError::MessageNotModified);
+            }
+
+            lock.responses
                 .edited_messages_reply_markup
                 .push(EditedMessageReplyMarkup {
                     message: message.clone(),
@@ -47,7 +65,38 @@ pub async fn edit_message_reply_markup(
 
             make_telegram_result(message)
         }
-        (None, None, Some(_)) => make_telegram_result(true),
-        _ => ErrorBadRequest("No message_id or inline_message_id were provided").into(),
+        (None, None, Some(inline_message_id)) => {
+            check_if_inline_message_exists!(lock, &inline_message_id);
+            let old_message = lock
+                .messages
+                .get_inline_message(&inline_message_id)
+                .unwrap();
+
+            let message = match body.reply_markup.clone() {
+                Some(reply_markup) => lock
+                    .messages
+                    .edit_inline_message(&inline_message_id, "reply_markup", reply_markup)
+                    .unwrap(),
+                None => lock
+                    .messages
+                    .edit_inline_message(&inline_message_id, "reply_markup", None::<()>)
+                    .unwrap(),
+            };
+
+            if !is_message_modified(&old_message, &message) {
+                return make_telegram_error(ApiError::MessageNotModified);
+            }
+
+            lock.responses
+                .edited_messages_reply_markup
+                .push(EditedMessageReplyMarkup {
+                    message,
+                    bot_request: body.into_inner(),
+                });
+
+            // Inline messages are not returned by telegram
+            make_telegram_result(true)
+        }
+        _ => make_telegram_error(ApiError::MessageIdentifierNotSpecified),
     }
 }
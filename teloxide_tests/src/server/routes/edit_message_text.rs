@@ -1,10 +1,17 @@
-use actix_web::{error::ErrorBadRequest, web, Responder};
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
 use serde::Deserialize;
 use teloxide::types::{LinkPreviewOptions, MessageEntity, ParseMode, ReplyMarkup};
+use teloxide::ApiError;
 
-use crate::server::{routes::make_telegram_result, EditedMessageText, MESSAGES, RESPONSES};
+use crate::server::{routes::make_telegram_result, EditedMessageText, State};
 
-use super::{check_if_message_exists, BodyChatId};
+use super::{
+    check_if_inline_message_exists, check_if_message_exists, check_limits, check_message_text,
+    check_reply_markup, edited_reply_markup, is_message_modified, make_telegram_error, parse_text,
+    BodyChatId,
+};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct EditMessageTextBody {
@@ -19,35 +26,90 @@ pub struct EditMessageTextBody {
     pub reply_markup: Option<ReplyMarkup>,
 }
 
-pub async fn edit_message_text(body: web::Json<EditMessageTextBody>) -> impl Responder {
+pub async fn edit_message_text(
+    body: web::Json<EditMessageTextBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    check_limits!(check_reply_markup(
+        &lock.api_limits,
+        body.reply_markup.as_ref()
+    ));
+    let (text, entities) = parse_text!(&body.text, body.parse_mode, body.entities.clone());
+    check_limits!(
+        check_message_text(&lock.api_limits, &text).map_err(|error| match error {
+            // Edits have their own error for the long texts
+            ApiError::MessageIsTooLong => ApiError::EditedMessageIsTooLong,
+            error => error,
+        })
+    );
     match (
         body.chat_id.clone(),
         body.message_id,
         body.inline_message_id.clone(),
     ) {
-        (Some(_), Some(message_id), None) => {
-            check_if_message_exists!(message_id);
-
-            MESSAGES.edit_message(message_id, "text", body.text.clone());
-            MESSAGES.edit_message(
-                message_id,
-                "entities",
-                body.entities.clone().unwrap_or(vec![]),
-            );
-            let message = MESSAGES
-                .edit_message_reply_markup(message_id, body.reply_markup.clone())
+        (Some(chat_id), Some(message_id), None) => {
+            let chat_id = chat_id.id();
+            check_if_message_exists!(lock, chat_id, message_id, ApiError::MessageToEditNotFound);
+            let old_message = lock.messages.get_message(chat_id, message_id).unwrap();
+
+            lock.messages
+                .edit_message(chat_id, message_id, "text", text.clone());
+            lock.messages
+                .edit_message(chat_id, message_id, "entities", entities.clone());
+            let message = lock
+                .messages
+                .edit_message(
+                    chat_id,
+                    message_id,
+                    "reply_markup",
+                    edited_reply_markup(body.reply_markup.clone()),
+                )
                 .unwrap();
 
-            let mut responses_lock = RESPONSES.lock().unwrap();
-            responses_lock.edited_messages_text.push(EditedMessageText {
+            if !is_message_modified(&old_message, &message) {
+                return make_telegram_error(ApiError::MessageNotModified);
+            }
+
+            lock.responses.edited_messages_text.push(EditedMessageText {
                 message: message.clone(),
                 bot_request: body.into_inner(),
             });
 
             make_telegram_result(message)
         }
-        // No implementation for inline messages yet, so just return success
-        (None, None, Some(_)) => make_telegram_result(true),
-        _ => ErrorBadRequest("No message_id or inline_message_id were provided").into(),
+        (None, None, Some(inline_message_id)) => {
+            check_if_inline_message_exists!(lock, &inline_message_id);
+            let old_message = lock
+                .messages
+                .get_inline_message(&inline_message_id)
+                .unwrap();
+
+            lock.messages
+                .edit_inline_message(&inline_message_id, "text", text.clone());
+            lock.messages
+                .edit_inline_message(&inline_message_id, "entities", entities.clone());
+            let message = lock
+                .messages
+                .edit_inline_message(
+                    &inline_message_id,
+                    "reply_markup",
+                    edited_reply_markup(body.reply_markup.clone()),
+                )
+                .unwrap();
+
+            if !is_message_modified(&old_message, &message) {
+                return make_telegram_error(ApiError::MessageNotModified);
+            }
+
+            lock.responses.edited_messages_text.push(EditedMessageText {
+                message,
+                bot_request: body.into_inner(),
+            });
+
+            // Inline messages are not returned by telegram
+            make_telegram_result(true)
+        }
+        _ => make_telegram_error(ApiError::MessageIdentifierNotSpecified),
     }
 }
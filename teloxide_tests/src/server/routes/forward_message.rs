@@ -1,11 +1,15 @@
-use actix_web::error::ErrorBadRequest;
+use std::sync::Mutex;
+
+use crate::server::ForwardedMessage;
+use crate::server::{routes::check_if_message_exists, State};
 use actix_web::{web, Responder};
 use serde::Deserialize;
-use teloxide::types::{ChatKind, Me, MessageId, MessageKind, MessageOrigin, PublicChatKind};
-use crate::server::ForwardedMessage;
-use crate::server::{routes::check_if_message_exists, MESSAGES, RESPONSES};
+use teloxide::types::{ChatKind, MessageId, MessageKind, MessageOrigin, PublicChatKind};
+use teloxide::ApiError;
 
-use super::{make_telegram_result, BodyChatId};
+use super::{
+    check_message_thread, make_telegram_error, make_telegram_result, put_in_thread, BodyChatId,
+};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct ForwardMessageBody {
@@ -19,13 +23,29 @@ pub struct ForwardMessageBody {
 
 pub async fn forward_message(
     body: web::Json<ForwardMessageBody>,
-    me: web::Data<Me>,
+    state: web::Data<Mutex<State>>,
 ) -> impl Responder {
-    check_if_message_exists!(body.message_id);
-    let mut message = MESSAGES.get_message(body.message_id).unwrap();
+    let mut lock = state.lock().unwrap();
+    let thread = check_message_thread!(
+        lock,
+        body.chat_id.id(),
+        body.message_thread_id.map(i64::from)
+    );
+    check_if_message_exists!(
+        lock,
+        body.from_chat_id.id(),
+        body.message_id,
+        ApiError::MessageToForwardNotFound
+    );
+    let mut message = lock
+        .messages
+        .get_message(body.from_chat_id.id(), body.message_id)
+        .unwrap();
 
     if message.has_protected_content() {
-        return ErrorBadRequest("Message has protected content").into();
+        return make_telegram_error(ApiError::Unknown(
+            "Bad Request: message can't be forwarded".to_string(),
+        ));
     }
 
     let message_clone = message.clone();
@@ -62,15 +82,18 @@ pub async fn forward_message(
         common.has_protected_content = body.protect_content.unwrap_or(false);
     }
 
-    let last_id = MESSAGES.max_message_id();
+    let last_id = lock.messages.max_message_id(body.chat_id.id());
     message.id = MessageId(last_id + 1);
     message.chat = body.chat_id.chat();
-    message.from = Some(me.user.clone());
-    let message = MESSAGES.add_message(message);
+    message.from = Some(lock.me.user.clone());
+    message.thread_id = None;
+    message.is_topic_message = false;
+    let message = lock
+        .messages
+        .add_message(put_in_thread(message, thread.as_ref()));
 
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock.sent_messages.push(message.clone());
-    responses_lock.forwarded_messages.push(ForwardedMessage {
+    lock.responses.sent_messages.push(message.clone());
+    lock.responses.forwarded_messages.push(ForwardedMessage {
         message: message.clone(),
         bot_request: body.into_inner(),
     });
@@ -0,0 +1,25 @@
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
+use serde::Deserialize;
+use teloxide::ApiError;
+
+use crate::server::State;
+
+use super::{make_telegram_error, make_telegram_result, BodyChatId};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GetChatBody {
+    pub chat_id: BodyChatId,
+}
+
+pub async fn get_chat(
+    body: web::Json<GetChatBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let lock = state.lock().unwrap();
+    match lock.chats.get_chat(body.chat_id.id()) {
+        Some(chat) => make_telegram_result(chat),
+        None => make_telegram_error(ApiError::ChatNotFound),
+    }
+}
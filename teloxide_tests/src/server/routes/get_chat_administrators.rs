@@ -0,0 +1,34 @@
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
+use serde::Deserialize;
+use teloxide::types::ChatMember;
+use teloxide::ApiError;
+
+use crate::server::State;
+
+use super::{chat_members_with_me, make_telegram_error, make_telegram_result, BodyChatId};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GetChatAdministratorsBody {
+    pub chat_id: BodyChatId,
+}
+
+pub async fn get_chat_administrators(
+    body: web::Json<GetChatAdministratorsBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let lock = state.lock().unwrap();
+    let chat_id = body.chat_id.id();
+    if chat_id > 0 {
+        return make_telegram_error(ApiError::Unknown(
+            "Bad Request: there are no administrators in the private chat".to_string(),
+        ));
+    }
+    let administrators: Vec<ChatMember> = chat_members_with_me(&lock, chat_id)
+        .into_iter()
+        .filter(|member| member.kind.is_privileged())
+        .collect();
+
+    make_telegram_result(administrators)
+}
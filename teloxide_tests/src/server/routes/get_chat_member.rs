@@ -0,0 +1,29 @@
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
+use serde::Deserialize;
+use teloxide::ApiError;
+
+use crate::server::State;
+
+use super::{chat_members_with_me, make_telegram_error, make_telegram_result, BodyChatId};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GetChatMemberBody {
+    pub chat_id: BodyChatId,
+    pub user_id: u64,
+}
+
+pub async fn get_chat_member(
+    body: web::Json<GetChatMemberBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let lock = state.lock().unwrap();
+    match chat_members_with_me(&lock, body.chat_id.id())
+        .into_iter()
+        .find(|member| member.user.id.0 == body.user_id)
+    {
+        Some(member) => make_telegram_result(member),
+        None => make_telegram_error(ApiError::UserNotFound),
+    }
+}
@@ -0,0 +1,26 @@
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
+use serde::Deserialize;
+
+use crate::server::State;
+
+use super::{chat_members_with_me, make_telegram_result, BodyChatId};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GetChatMemberCountBody {
+    pub chat_id: BodyChatId,
+}
+
+pub async fn get_chat_member_count(
+    body: web::Json<GetChatMemberCountBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let lock = state.lock().unwrap();
+    let count = chat_members_with_me(&lock, body.chat_id.id())
+        .iter()
+        .filter(|member| member.kind.is_present())
+        .count();
+
+    make_telegram_result(count as u32)
+}
@@ -1,19 +1,25 @@
-use actix_web::{error::ErrorBadRequest, web, Responder};
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
 use serde::Deserialize;
+use teloxide::ApiError;
 
-use crate::server::FILES;
+use crate::server::State;
 
-use super::make_telegram_result;
+use super::{make_telegram_error, make_telegram_result};
 
 #[derive(Deserialize)]
 pub struct GetFileQuery {
     file_id: String,
 }
 
-pub async fn get_file(query: web::Json<GetFileQuery>) -> impl Responder {
-    let lock = FILES.lock().unwrap();
-    let Some(file) = lock.iter().find(|f| f.id == query.file_id) else {
-        return ErrorBadRequest("File not found").into();
+pub async fn get_file(
+    query: web::Json<GetFileQuery>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let lock = state.lock().unwrap();
+    let Some(file) = lock.files.get_file(&query.file_id) else {
+        return make_telegram_error(ApiError::FileIdInvalid);
     };
     make_telegram_result(file)
 }
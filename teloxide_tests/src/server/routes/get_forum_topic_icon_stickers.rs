@@ -0,0 +1,33 @@
+use actix_web::Responder;
+use teloxide::types::{Sticker, StickerKind};
+
+use crate::dataset::MockMessageSticker;
+
+use super::make_telegram_result;
+
+/// The custom emoji ids and emojis of the stickers, that can be used as forum topic icons
+pub const FORUM_TOPIC_ICONS: [(&str, &str); 4] = [
+    ("5312536423851630001", "💡"),
+    ("5312016608254762256", "❗️"),
+    ("5377498341074542641", "⚡️"),
+    ("5350305691942788490", "📈"),
+];
+
+pub async fn get_forum_topic_icon_stickers() -> impl Responder {
+    let stickers: Vec<Sticker> = FORUM_TOPIC_ICONS
+        .iter()
+        .map(|(custom_emoji_id, emoji)| {
+            let message = MockMessageSticker::new()
+                .kind(StickerKind::CustomEmoji {
+                    custom_emoji_id: custom_emoji_id.to_string(),
+                })
+                .emoji(*emoji)
+                .file_id(format!("topic_icon_{custom_emoji_id}"))
+                .file_unique_id(format!("topic_icon_unique_{custom_emoji_id}"))
+                .build();
+            message.sticker().unwrap().clone()
+        })
+        .collect();
+
+    make_telegram_result(stickers)
+}
@@ -0,0 +1,12 @@
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
+
+use crate::server::State;
+
+use super::make_telegram_result;
+
+pub async fn get_me(state: web::Data<Mutex<State>>) -> impl Responder {
+    let lock = state.lock().unwrap();
+    make_telegram_result(lock.me.clone())
+}
@@ -1,29 +1,51 @@
 use std::collections::HashMap;
-use std::str::from_utf8;
 
-use crate::dataset::{MockPrivateChat, MockSupergroupChat};
-use actix_web::HttpResponse;
+use crate::dataset::{MockPrivateChat, MockSupergroupChat, MockUser};
+use crate::mock_bot::ApiLimits;
+use crate::server::State;
+use actix_web::{http::StatusCode, HttpResponse};
+use chrono::{DateTime, TimeDelta, Utc};
 use futures_util::stream::StreamExt as _;
 use futures_util::TryStreamExt;
 use rand::distributions::{Alphanumeric, DistString};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use teloxide::types::{
-    Chat, ForceReply, KeyboardMarkup, KeyboardRemove, MessageEntity, ParseMode, ReplyMarkup,
-    Seconds, True,
+    Administrator, Chat, ChatAdministratorRights, ChatKind, ChatMember, ChatMemberKind, ChatPublic,
+    ForceReply, InlineKeyboardButtonKind, InlineKeyboardMarkup, KeyboardMarkup, KeyboardRemove,
+    Message, MessageEntity, MessageId, ParseMode, PublicChatKind, ReplyMarkup, Seconds, ThreadId,
+    True, UntilDate, User,
 };
+use teloxide::ApiError;
 
 pub mod answer_callback_query;
+pub mod answer_inline_query;
+pub mod answer_pre_checkout_query;
+pub mod answer_shipping_query;
 pub mod ban_chat_member;
+pub mod close_forum_topic;
 pub mod copy_message;
+pub mod create_forum_topic;
+pub mod delete_chat_photo;
+pub mod delete_forum_topic;
 pub mod delete_message;
 pub mod download_file;
+pub mod edit_forum_topic;
 pub mod edit_message_caption;
 pub mod edit_message_reply_markup;
 pub mod edit_message_text;
 pub mod forward_message;
+pub mod get_chat;
+pub mod get_chat_administrators;
+pub mod get_chat_member;
+pub mod get_chat_member_count;
 pub mod get_file;
+pub mod get_forum_topic_icon_stickers;
+pub mod get_me;
 pub mod pin_chat_message;
+pub mod promote_chat_member;
+pub mod refund_star_payment;
+pub mod reopen_forum_topic;
 pub mod restrict_chat_member;
 pub mod send_animation;
 pub mod send_audio;
@@ -31,6 +53,7 @@ pub mod send_chat_action;
 pub mod send_contact;
 pub mod send_dice;
 pub mod send_document;
+pub mod send_invoice;
 pub mod send_location;
 pub mod send_media_group;
 pub mod send_message;
@@ -41,10 +64,17 @@ pub mod send_venue;
 pub mod send_video;
 pub mod send_video_note;
 pub mod send_voice;
+pub mod set_chat_administrator_custom_title;
+pub mod set_chat_description;
+pub mod set_chat_permissions;
+pub mod set_chat_photo;
+pub mod set_chat_title;
+pub mod set_message_reaction;
+pub mod stop_poll;
 pub mod unban_chat_member;
+pub mod unknown_method;
 pub mod unpin_all_chat_messages;
 pub mod unpin_chat_message;
-pub mod set_message_reaction;
 
 /// Telegram accepts both `i64` and `String` for chat_id,
 /// so it is a wrapper for both
@@ -88,7 +118,7 @@ pub enum MediaGroupInputMedia {
 pub struct MediaGroupInputMediaAudio {
     pub r#type: String,
     pub file_name: String,
-    pub file_data: String,
+    pub file_data: Vec<u8>,
     pub caption: Option<String>,
     pub parse_mode: Option<ParseMode>,
     pub caption_entities: Option<Vec<MessageEntity>>,
@@ -101,7 +131,7 @@ pub struct MediaGroupInputMediaAudio {
 pub struct MediaGroupInputMediaDocument {
     pub r#type: String,
     pub file_name: String,
-    pub file_data: String,
+    pub file_data: Vec<u8>,
     pub caption: Option<String>,
     pub parse_mode: Option<ParseMode>,
     pub caption_entities: Option<Vec<MessageEntity>>,
@@ -112,7 +142,7 @@ pub struct MediaGroupInputMediaDocument {
 pub struct MediaGroupInputMediaPhoto {
     pub r#type: String,
     pub file_name: String,
-    pub file_data: String,
+    pub file_data: Vec<u8>,
     pub caption: Option<String>,
     pub parse_mode: Option<ParseMode>,
     pub caption_entities: Option<Vec<MessageEntity>>,
@@ -124,7 +154,7 @@ pub struct MediaGroupInputMediaPhoto {
 pub struct MediaGroupInputMediaVideo {
     pub r#type: String,
     pub file_name: String,
-    pub file_data: String,
+    pub file_data: Vec<u8>,
     pub caption: Option<String>,
     pub parse_mode: Option<ParseMode>,
     pub caption_entities: Option<Vec<MessageEntity>>,
@@ -149,10 +179,11 @@ pub enum FileType {
     Animation,
 }
 
+#[derive(Debug, Clone)]
 pub struct Attachment {
     pub raw_name: String,
     pub file_name: String,
-    pub file_data: String,
+    pub file_data: Vec<u8>,
 }
 
 pub trait SerializeRawFields {
@@ -166,15 +197,104 @@ pub trait SerializeRawFields {
 }
 
 macro_rules! check_if_message_exists {
-    ($msg_id:expr) => {
-        if MESSAGES.get_message($msg_id).is_none() {
-            return ErrorBadRequest("Message not found").into();
+    ($lock:expr, $chat_id:expr, $msg_id:expr, $error:expr) => {
+        if $lock.messages.get_message($chat_id, $msg_id).is_none() {
+            return $crate::server::routes::make_telegram_error($error);
         }
     };
 }
 
 pub(crate) use check_if_message_exists;
 
+macro_rules! check_limits {
+    ($check:expr) => {
+        if let Err(error) = $check {
+            return $crate::server::routes::make_telegram_error(error);
+        }
+    };
+}
+
+pub(crate) use check_limits;
+
+macro_rules! parse_text {
+    ($text:expr, $parse_mode:expr, $entities:expr) => {
+        match $crate::server::parse_mode::parse_text($text, $parse_mode, $entities) {
+            Ok(parsed) => parsed,
+            Err(error) => return $crate::server::routes::make_telegram_error(error),
+        }
+    };
+}
+
+pub(crate) use parse_text;
+
+macro_rules! parse_caption {
+    ($caption:expr, $parse_mode:expr, $entities:expr) => {
+        match $caption {
+            Some(caption) => {
+                let (caption, entities) =
+                    $crate::server::routes::parse_text!(caption, $parse_mode, $entities);
+                (Some(caption), entities)
+            }
+            None => (None, $entities.unwrap_or_default()),
+        }
+    };
+}
+
+pub(crate) use parse_caption;
+
+macro_rules! check_message_thread {
+    ($lock:expr, $chat_id:expr, $message_thread_id:expr) => {
+        match $crate::server::routes::message_thread(&$lock, $chat_id, $message_thread_id) {
+            Ok(thread) => thread,
+            Err(error) => return $crate::server::routes::make_telegram_error(error),
+        }
+    };
+}
+
+pub(crate) use check_message_thread;
+
+macro_rules! check_if_topic_exists {
+    ($lock:expr, $chat_id:expr, $thread_id:expr) => {
+        if $lock
+            .forum_topics
+            .get_topic($chat_id, $thread_id.0 .0)
+            .is_none()
+        {
+            return $crate::server::routes::make_telegram_error(teloxide::ApiError::Unknown(
+                "Bad Request: message thread not found".to_string(),
+            ));
+        }
+    };
+}
+
+pub(crate) use check_if_topic_exists;
+
+macro_rules! check_if_chat_exists {
+    ($lock:expr, $chat_id:expr) => {
+        if $lock.chats.get_chat($chat_id).is_none() {
+            return $crate::server::routes::make_telegram_error(teloxide::ApiError::ChatNotFound);
+        }
+    };
+}
+
+pub(crate) use check_if_chat_exists;
+
+macro_rules! check_if_inline_message_exists {
+    ($lock:expr, $inline_message_id:expr) => {
+        if $lock
+            .messages
+            .get_inline_message($inline_message_id)
+            .is_none()
+        {
+            return $crate::server::routes::make_telegram_error(
+                teloxide::ApiError::MessageIdInvalid,
+            );
+        }
+    };
+}
+
+pub(crate) use check_if_inline_message_exists;
+
 pub async fn get_raw_multipart_fields(
     payload: &mut actix_multipart::Multipart,
 ) -> (HashMap<String, String>, HashMap<String, Attachment>) {
@@ -223,10 +343,7 @@ pub async fn get_raw_multipart_fields(
     // Process the raw_fields as needed.
     let mut fields = HashMap::new();
     for (name, data) in raw_fields {
-        fields.insert(
-            name.to_string(),
-            from_utf8(data.as_slice()).unwrap().to_string(),
-        );
+        fields.insert(name, String::from_utf8_lossy(&data).to_string());
     }
 
     let mut attachments = HashMap::new();
@@ -236,7 +353,7 @@ pub async fn get_raw_multipart_fields(
             Attachment {
                 raw_name: data.0.to_string(),
                 file_name: filename.to_string(),
-                file_data: from_utf8(&data.1).unwrap().to_string(),
+                file_data: data.1,
             },
         );
     }
@@ -244,6 +361,350 @@ pub async fn get_raw_multipart_fields(
     (fields, attachments)
 }
 
+/// Makes the same response that telegram gives for the error, so teloxide turns it back into
+/// `RequestError::Api(error)`
+pub fn make_telegram_error(error: ApiError) -> HttpResponse {
+    let description = match error {
+        // Their descriptions are not the same as the ones that telegram sends
+        ApiError::InvalidToken => "Unauthorized".to_string(),
+        ApiError::Unknown(description) | ApiError::CantParseEntities(description) => description,
+        error => error.to_string(),
+    };
+    let error_code = match description.split(':').next().unwrap_or_default() {
+        "Unauthorized" => 401,
+        "Forbidden" => 403,
+        "Not Found" => 404,
+        "Conflict" => 409,
+        "Request Entity Too Large" => 413,
+        "Too Many Requests" => 429,
+        _ => 400,
+    };
+    HttpResponse::build(StatusCode::from_u16(error_code).unwrap()).body(
+        json!({
+            "ok": false,
+            "error_code": error_code,
+            "description": description,
+        })
+        .to_string(),
+    )
+}
+
+/// Makes the response that telegram gives when the flood control is hit, teloxide turns it into
+/// `RequestError::RetryAfter`
+pub fn make_retry_after_error(retry_after: u32) -> HttpResponse {
+    HttpResponse::TooManyRequests().body(
+        json!({
+            "ok": false,
+            "error_code": 429,
+            "description": format!("Too Many Requests: retry after {retry_after}"),
+            "parameters": {
+                "retry_after": retry_after,
+            },
+        })
+        .to_string(),
+    )
+}
+
+/// Telegram refuses the edits that leave the message exactly as it was
+/// The inline keyboard that the message has after editing its text or caption. Telegram removes
+/// the keyboard if the edit doesn't have one
+pub fn edited_reply_markup(reply_markup: Option<ReplyMarkup>) -> Option<InlineKeyboardMarkup> {
+    match reply_markup {
+        Some(ReplyMarkup::InlineKeyboard(reply_markup)) => Some(reply_markup),
+        _ => None,
+    }
+}
+
+pub fn is_message_modified(old_message: &Message, new_message: &Message) -> bool {
+    old_message.text() != new_message.text()
+        || old_message.entities() != new_message.entities()
+        || old_message.caption() != new_message.caption()
+        || old_message.caption_entities() != new_message.caption_entities()
+        || old_message.reply_markup() != new_message.reply_markup()
+}
+
+/// Telegram counts the lengths of the texts in UTF-16 code units
+pub fn text_length(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+pub fn check_message_text(limits: &ApiLimits, text: &str) -> Result<(), ApiError> {
+    if text.trim().is_empty() {
+        return Err(ApiError::MessageTextIsEmpty);
+    }
+    if text_length(text) > limits.message_text {
+        return Err(ApiError::MessageIsTooLong);
+    }
+    Ok(())
+}
+
+pub fn check_caption(limits: &ApiLimits, caption: Option<&str>) -> Result<(), ApiError> {
+    match caption {
+        Some(caption) if text_length(caption) > limits.caption => Err(ApiError::Unknown(
+            "Bad Request: message caption is too long".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+pub fn check_reply_markup(
+    limits: &ApiLimits,
+    reply_markup: Option<&ReplyMarkup>,
+) -> Result<(), ApiError> {
+    let Some(ReplyMarkup::InlineKeyboard(markup)) = reply_markup else {
+        return Ok(());
+    };
+    for button in markup.inline_keyboard.iter().flatten() {
+        if let InlineKeyboardButtonKind::CallbackData(data) = &button.kind {
+            if data.len() > limits.callback_data {
+                return Err(ApiError::ButtonDataInvalid);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Finds the user with the id in the chat members or in the messages, or makes a `MockUser`
+/// with it, if the bot doesn't know about the user
+pub fn chat_member_user(state: &State, user_id: u64) -> User {
+    if let Some(user) = state.chat_members.find_user(user_id) {
+        return user.clone();
+    }
+    state
+        .messages
+        .all()
+        .iter()
+        .filter_map(|message| message.from.as_ref())
+        .find(|user| user.id.0 == user_id)
+        .cloned()
+        .unwrap_or_else(|| MockUser::new().id(user_id).build())
+}
+
+/// Checks that the user with the record in the chat can be banned or restricted by the bot
+pub fn check_can_be_restricted(state: &State, chat_id: i64, user_id: u64) -> Result<(), ApiError> {
+    if user_id == state.me.id.0 {
+        return Err(ApiError::CantRestrictSelf);
+    }
+    match state
+        .chat_members
+        .get_member(chat_id, user_id)
+        .map(|m| m.kind)
+    {
+        Some(ChatMemberKind::Owner(_)) => Err(ApiError::Unknown(
+            "Bad Request: can't remove chat owner".to_string(),
+        )),
+        // The bot can restrict only the administrators that it promoted
+        Some(ChatMemberKind::Administrator(administrator)) if !administrator.can_be_edited => Err(
+            ApiError::Unknown("Bad Request: user is an administrator of the chat".to_string()),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// The members of the chat, with the bot. If the bot isn't in the members, it is in the chat
+/// anyway, as an administrator with the rights from `MockMe::administrator_rights` in the groups
+/// and as a member in the private chats
+pub fn chat_members_with_me(state: &State, chat_id: i64) -> Vec<ChatMember> {
+    let mut members = state.chat_members.members(chat_id);
+    if members.iter().any(|member| member.user.id == state.me.id) {
+        return members;
+    }
+    let kind = match my_administrator_rights(state, chat_id) {
+        Some(rights) if chat_id < 0 => ChatMemberKind::Administrator(Administrator {
+            custom_title: None,
+            is_anonymous: rights.is_anonymous,
+            can_be_edited: false,
+            can_manage_chat: rights.can_manage_chat,
+            can_change_info: rights.can_change_info,
+            can_post_messages: rights.can_post_messages.unwrap_or(false),
+            can_edit_messages: rights.can_edit_messages.unwrap_or(false),
+            can_delete_messages: rights.can_delete_messages,
+            can_post_stories: rights.can_post_stories.unwrap_or(false),
+            can_edit_stories: rights.can_edit_stories.unwrap_or(false),
+            can_delete_stories: rights.can_delete_stories.unwrap_or(false),
+            can_manage_video_chats: rights.can_manage_video_chats,
+            can_invite_users: rights.can_invite_users,
+            can_restrict_members: rights.can_restrict_members,
+            can_pin_messages: rights.can_pin_messages.unwrap_or(false),
+            can_manage_topics: rights.can_manage_topics.unwrap_or(false),
+            can_promote_members: rights.can_promote_members,
+        }),
+        _ => ChatMemberKind::Member,
+    };
+    members.push(ChatMember {
+        user: state.me.user.clone(),
+        kind,
+    });
+    members
+}
+
+/// The administrator rights of the bot in the chat, or `None` if it isn't an administrator there
+pub fn my_administrator_rights(state: &State, chat_id: i64) -> Option<ChatAdministratorRights> {
+    let Some(me) = state.chat_members.get_member(chat_id, state.me.id.0) else {
+        return Some(
+            state
+                .administrator_rights
+                .clone()
+                .unwrap_or_else(all_administrator_rights),
+        );
+    };
+    match me.kind {
+        ChatMemberKind::Owner(_) => Some(all_administrator_rights()),
+        ChatMemberKind::Administrator(administrator) => Some(ChatAdministratorRights {
+            is_anonymous: administrator.is_anonymous,
+            can_manage_chat: administrator.can_manage_chat,
+            can_delete_messages: administrator.can_delete_messages,
+            can_manage_video_chats: administrator.can_manage_video_chats,
+            can_restrict_members: administrator.can_restrict_members,
+            can_promote_members: administrator.can_promote_members,
+            can_change_info: administrator.can_change_info,
+            can_invite_users: administrator.can_invite_users,
+            can_post_messages: Some(administrator.can_post_messages),
+            can_edit_messages: Some(administrator.can_edit_messages),
+            can_pin_messages: Some(administrator.can_pin_messages),
+            can_post_stories: Some(administrator.can_post_stories),
+            can_edit_stories: Some(administrator.can_edit_stories),
+            can_delete_stories: Some(administrator.can_delete_stories),
+            can_manage_topics: Some(administrator.can_manage_topics),
+        }),
+        _ => None,
+    }
+}
+
+fn all_administrator_rights() -> ChatAdministratorRights {
+    ChatAdministratorRights {
+        is_anonymous: false,
+        can_manage_chat: true,
+        can_delete_messages: true,
+        can_manage_video_chats: true,
+        can_restrict_members: true,
+        can_promote_members: true,
+        can_change_info: true,
+        can_invite_users: true,
+        can_post_messages: Some(true),
+        can_edit_messages: Some(true),
+        can_pin_messages: Some(true),
+        can_post_stories: Some(true),
+        can_edit_stories: Some(true),
+        can_delete_stories: Some(true),
+        can_manage_topics: Some(true),
+    }
+}
+
+/// Checks that the bot has the administrator right in the chat. In the private chats the bot can
+/// do anything
+pub fn check_my_right<F>(
+    state: &State,
+    chat_id: i64,
+    right: F,
+    error: ApiError,
+) -> Result<(), ApiError>
+where
+    F: Fn(&ChatAdministratorRights) -> bool,
+{
+    if chat_id > 0 {
+        return Ok(());
+    }
+    match my_administrator_rights(state, chat_id) {
+        Some(rights) if right(&rights) => Ok(()),
+        _ => Err(error),
+    }
+}
+
+/// Converts the `until_date` of a ban or a restriction. Just like in telegram, if it is less than
+/// 30 seconds or more than 366 days from now, the user is banned or restricted forever
+pub fn until_date(until_date: Option<i64>) -> UntilDate {
+    let Some(date) = until_date.and_then(|timestamp| DateTime::from_timestamp(timestamp, 0)) else {
+        return UntilDate::Forever;
+    };
+    let duration = date - Utc::now();
+    if duration < TimeDelta::seconds(30) || duration > TimeDelta::days(366) {
+        UntilDate::Forever
+    } else {
+        UntilDate::Date(date)
+    }
+}
+
+/// Where the sent messages go, checked with `message_thread`
+pub struct MessageThread {
+    pub thread_id: ThreadId,
+    /// If the thread is a forum topic, not just replies to a message
+    pub is_topic_message: bool,
+}
+
+/// Checks that the thread exists. In forums it has to be a known topic, in other chats it is
+/// the message that the thread is replying to
+pub fn message_thread(
+    state: &State,
+    chat_id: i64,
+    message_thread_id: Option<i64>,
+) -> Result<Option<MessageThread>, ApiError> {
+    let Some(message_thread_id) = message_thread_id else {
+        return Ok(None);
+    };
+    let thread_id = message_thread_id as i32;
+    let is_topic_message = state.forum_topics.is_forum(chat_id);
+    let exists = if is_topic_message {
+        state.forum_topics.get_topic(chat_id, thread_id).is_some()
+    } else {
+        state.messages.get_message(chat_id, thread_id).is_some()
+    };
+    if !exists {
+        return Err(ApiError::Unknown(
+            "Bad Request: message thread not found".to_string(),
+        ));
+    }
+    Ok(Some(MessageThread {
+        thread_id: ThreadId(MessageId(thread_id)),
+        is_topic_message,
+    }))
+}
+
+/// Returns the chat that the bot knows about, if it isn't a private chat, because only the groups,
+/// supergroups and channels have a title, a description and so on
+pub fn public_chat_mut(state: &mut State, chat_id: i64) -> Result<&mut Chat, ApiError> {
+    match state.chats.get_chat_mut(chat_id) {
+        Some(chat) if chat.is_private() => Err(ApiError::Unknown(
+            "Bad Request: method is not available for private chats".to_string(),
+        )),
+        Some(chat) => Ok(chat),
+        None => Err(ApiError::ChatNotFound),
+    }
+}
+
+/// Puts the message into the thread, if there is one
+pub fn put_in_thread(mut message: Message, thread: Option<&MessageThread>) -> Message {
+    let Some(thread) = thread else {
+        return message;
+    };
+    message.thread_id = Some(thread.thread_id);
+    message.is_topic_message = thread.is_topic_message;
+    if thread.is_topic_message {
+        if let ChatKind::Public(ChatPublic {
+            kind: PublicChatKind::Supergroup(ref mut supergroup),
+            ..
+        }) = message.chat.kind
+        {
+            supergroup.is_forum = true;
+        }
+    }
+    message
+}
+
+/// Adds the service message of a forum topic action, like creating or closing it, that the
+/// bot sent to the topic
+pub fn add_forum_topic_message(state: &mut State, thread_id: ThreadId, mut message: Message) {
+    message.id = MessageId(state.messages.max_message_id(message.chat.id.0) + 1);
+    message.from = Some(state.me.user.clone());
+    let thread = MessageThread {
+        thread_id,
+        is_topic_message: true,
+    };
+    state
+        .messages
+        .add_message(put_in_thread(message, Some(&thread)));
+}
+
 pub fn make_telegram_result<T>(result: T) -> HttpResponse
 where
     T: Serialize,
@@ -329,9 +790,7 @@ pub(crate) mod reply_markup_deserialize {
                     Ok(None)
                 }
             }
-            None => {
-                Ok(None)
-            }
+            None => Ok(None),
         }
     }
 
@@ -1,11 +1,13 @@
-use actix_web::error::ErrorBadRequest;
+use std::sync::Mutex;
+
 use actix_web::{web, Responder};
 use serde::Deserialize;
+use teloxide::ApiError;
 
 use crate::server::routes::make_telegram_result;
-use crate::server::{MESSAGES, RESPONSES};
+use crate::server::State;
 
-use super::{check_if_message_exists, BodyChatId};
+use super::{check_if_message_exists, check_limits, check_my_right, BodyChatId};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct PinChatMessageBody {
@@ -14,10 +16,24 @@ pub struct PinChatMessageBody {
     pub disable_notification: Option<bool>,
 }
 
-pub async fn pin_chat_message(body: web::Json<PinChatMessageBody>) -> impl Responder {
-    check_if_message_exists!(body.message_id);
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock.pinned_chat_messages.push(body.into_inner());
+pub async fn pin_chat_message(
+    body: web::Json<PinChatMessageBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    check_limits!(check_my_right(
+        &lock,
+        body.chat_id.id(),
+        |rights| rights.can_pin_messages.unwrap_or(false),
+        ApiError::NotEnoughRightsToPinMessage
+    ));
+    check_if_message_exists!(
+        lock,
+        body.chat_id.id(),
+        body.message_id,
+        ApiError::Unknown("Bad Request: message to pin not found".to_string())
+    );
+    lock.responses.pinned_chat_messages.push(body.into_inner());
 
     make_telegram_result(true)
 }
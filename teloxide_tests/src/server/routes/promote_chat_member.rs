@@ -0,0 +1,184 @@
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
+use serde::Deserialize;
+use teloxide::types::{Administrator, ChatAdministratorRights, ChatMember, ChatMemberKind};
+use teloxide::ApiError;
+
+use crate::server::State;
+
+use super::{
+    chat_member_user, check_limits, check_my_right, make_telegram_error, make_telegram_result,
+    my_administrator_rights, BodyChatId,
+};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PromoteChatMemberBody {
+    pub chat_id: BodyChatId,
+    pub user_id: u64,
+    pub is_anonymous: Option<bool>,
+    pub can_manage_chat: Option<bool>,
+    pub can_post_messages: Option<bool>,
+    pub can_edit_messages: Option<bool>,
+    pub can_delete_messages: Option<bool>,
+    pub can_post_stories: Option<bool>,
+    pub can_edit_stories: Option<bool>,
+    pub can_delete_stories: Option<bool>,
+    pub can_manage_video_chats: Option<bool>,
+    pub can_restrict_members: Option<bool>,
+    pub can_promote_members: Option<bool>,
+    pub can_change_info: Option<bool>,
+    pub can_invite_users: Option<bool>,
+    pub can_pin_messages: Option<bool>,
+    pub can_manage_topics: Option<bool>,
+}
+
+impl PromoteChatMemberBody {
+    /// The administrator with the rights from the request, or `None` if there are no rights,
+    /// which means that the user is demoted
+    pub fn administrator(&self, custom_title: Option<String>) -> Option<Administrator> {
+        let right = |right: Option<bool>| right.unwrap_or(false);
+        let administrator = Administrator {
+            custom_title,
+            is_anonymous: right(self.is_anonymous),
+            can_be_edited: true,
+            can_manage_chat: right(self.can_manage_chat),
+            can_change_info: right(self.can_change_info),
+            can_post_messages: right(self.can_post_messages),
+            can_edit_messages: right(self.can_edit_messages),
+            can_delete_messages: right(self.can_delete_messages),
+            can_post_stories: right(self.can_post_stories),
+            can_edit_stories: right(self.can_edit_stories),
+            can_delete_stories: right(self.can_delete_stories),
+            can_manage_video_chats: right(self.can_manage_video_chats),
+            can_invite_users: right(self.can_invite_users),
+            can_restrict_members: right(self.can_restrict_members),
+            can_pin_messages: right(self.can_pin_messages),
+            can_manage_topics: right(self.can_manage_topics),
+            can_promote_members: right(self.can_promote_members),
+        };
+        let has_rights = [
+            administrator.is_anonymous,
+            administrator.can_manage_chat,
+            administrator.can_change_info,
+            administrator.can_post_messages,
+            administrator.can_edit_messages,
+            administrator.can_delete_messages,
+            administrator.can_post_stories,
+            administrator.can_edit_stories,
+            administrator.can_delete_stories,
+            administrator.can_manage_video_chats,
+            administrator.can_invite_users,
+            administrator.can_restrict_members,
+            administrator.can_pin_messages,
+            administrator.can_manage_topics,
+            administrator.can_promote_members,
+        ]
+        .contains(&true);
+        has_rights.then_some(Administrator {
+            // Every other right implies this one
+            can_manage_chat: true,
+            ..administrator
+        })
+    }
+}
+
+/// Checks that the bot has all of the rights that it gives
+fn can_give_rights(rights: &ChatAdministratorRights, administrator: &Administrator) -> bool {
+    let given = [
+        (administrator.can_change_info, rights.can_change_info),
+        (
+            administrator.can_delete_messages,
+            rights.can_delete_messages,
+        ),
+        (administrator.can_invite_users, rights.can_invite_users),
+        (
+            administrator.can_manage_video_chats,
+            rights.can_manage_video_chats,
+        ),
+        (
+            administrator.can_restrict_members,
+            rights.can_restrict_members,
+        ),
+        (
+            administrator.can_promote_members,
+            rights.can_promote_members,
+        ),
+        (
+            administrator.can_post_messages,
+            rights.can_post_messages.unwrap_or(false),
+        ),
+        (
+            administrator.can_edit_messages,
+            rights.can_edit_messages.unwrap_or(false),
+        ),
+        (
+            administrator.can_pin_messages,
+            rights.can_pin_messages.unwrap_or(false),
+        ),
+        (
+            administrator.can_post_stories,
+            rights.can_post_stories.unwrap_or(false),
+        ),
+        (
+            administrator.can_edit_stories,
+            rights.can_edit_stories.unwrap_or(false),
+        ),
+        (
+            administrator.can_delete_stories,
+            rights.can_delete_stories.unwrap_or(false),
+        ),
+        (
+            administrator.can_manage_topics,
+            rights.can_manage_topics.unwrap_or(false),
+        ),
+    ];
+    given.iter().all(|(given, has)| !given || *has)
+}
+
+pub async fn promote_chat_member(
+    body: web::Json<PromoteChatMemberBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    let chat_id = body.chat_id.id();
+    check_limits!(check_my_right(
+        &lock,
+        chat_id,
+        |rights| rights.can_promote_members,
+        ApiError::Unknown("Bad Request: not enough rights".to_string())
+    ));
+    let member = lock.chat_members.get_member(chat_id, body.user_id);
+    match member.as_ref().map(|member| &member.kind) {
+        Some(ChatMemberKind::Owner(_)) => {
+            return make_telegram_error(ApiError::CantDemoteChatCreator);
+        }
+        // Only the administrators that the bot promoted can be changed by it
+        Some(ChatMemberKind::Administrator(administrator)) if !administrator.can_be_edited => {
+            return make_telegram_error(ApiError::Unknown(
+                "Bad Request: CHAT_ADMIN_REQUIRED".to_string(),
+            ));
+        }
+        _ => {}
+    }
+    let custom_title = member.and_then(|member| member.kind.custom_title().map(str::to_string));
+    let kind = match body.administrator(custom_title) {
+        Some(administrator) => {
+            let rights = my_administrator_rights(&lock, chat_id);
+            if chat_id < 0 && !rights.is_some_and(|rights| can_give_rights(&rights, &administrator))
+            {
+                return make_telegram_error(ApiError::Unknown(
+                    "Bad Request: RIGHT_FORBIDDEN".to_string(),
+                ));
+            }
+            ChatMemberKind::Administrator(administrator)
+        }
+        None => ChatMemberKind::Member,
+    };
+    let user = chat_member_user(&lock, body.user_id);
+    lock.chat_members
+        .set_member(chat_id, ChatMember { user, kind });
+    lock.responses.promoted_chat_members.push(body.into_inner());
+
+    make_telegram_result(true)
+}
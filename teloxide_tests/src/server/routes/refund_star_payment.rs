@@ -0,0 +1,42 @@
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
+use serde::Deserialize;
+use teloxide::ApiError;
+
+use crate::server::State;
+
+use super::{make_telegram_error, make_telegram_result};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RefundStarPaymentBody {
+    pub user_id: u64,
+    pub telegram_payment_charge_id: String,
+}
+
+pub async fn refund_star_payment(
+    body: web::Json<RefundStarPaymentBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    // Only the payments from the dispatched successful payment messages can be refunded
+    let Some(payment) = lock.payments.iter_mut().find(|payment| {
+        payment.user_id == body.user_id
+            && payment.telegram_payment_charge_id == body.telegram_payment_charge_id
+    }) else {
+        return make_telegram_error(ApiError::Unknown(
+            "Bad Request: CHARGE_NOT_FOUND".to_string(),
+        ));
+    };
+    if payment.is_refunded {
+        return make_telegram_error(ApiError::Unknown(
+            "Bad Request: CHARGE_ALREADY_REFUNDED".to_string(),
+        ));
+    }
+    payment.is_refunded = true;
+
+    lock.responses
+        .refunded_star_payments
+        .push(body.into_inner());
+    make_telegram_result(true)
+}
@@ -1,11 +1,16 @@
+use std::sync::Mutex;
+
 use actix_web::{web, Responder};
 use serde::Deserialize;
-use teloxide::types::ChatPermissions;
+use teloxide::types::{ChatMember, ChatMemberKind, ChatPermissions, Restricted};
+use teloxide::ApiError;
 
 use crate::server::routes::make_telegram_result;
-use crate::server::RESPONSES;
+use crate::server::State;
 
-use super::BodyChatId;
+use super::{
+    chat_member_user, check_can_be_restricted, check_limits, check_my_right, until_date, BodyChatId,
+};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct RestrictChatMemberBody {
@@ -16,10 +21,57 @@ pub struct RestrictChatMemberBody {
     pub until_date: Option<i64>,
 }
 
-pub async fn restrict_chat_member(body: web::Json<RestrictChatMemberBody>) -> impl Responder {
-    // Idk what to verify here
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock
+pub async fn restrict_chat_member(
+    body: web::Json<RestrictChatMemberBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    let chat_id = body.chat_id.id();
+    check_limits!(check_my_right(
+        &lock,
+        chat_id,
+        |rights| rights.can_restrict_members,
+        ApiError::NotEnoughRightsToRestrict
+    ));
+    check_limits!(check_can_be_restricted(&lock, chat_id, body.user_id));
+    // `Option::is_none_or` is newer than the MSRV
+    #[allow(clippy::unnecessary_map_or)]
+    let is_member = lock
+        .chat_members
+        .get_member(chat_id, body.user_id)
+        .map_or(true, |member| member.kind.is_present());
+    let permissions = body.permissions;
+    let kind = if permissions.is_all() {
+        // All of the permissions lift the restrictions
+        if is_member {
+            ChatMemberKind::Member
+        } else {
+            ChatMemberKind::Left
+        }
+    } else {
+        ChatMemberKind::Restricted(Restricted {
+            until_date: until_date(body.until_date),
+            is_member,
+            can_send_messages: permissions.can_send_messages(),
+            can_send_audios: permissions.can_send_audios(),
+            can_send_documents: permissions.can_send_documents(),
+            can_send_photos: permissions.can_send_photos(),
+            can_send_videos: permissions.can_send_videos(),
+            can_send_video_notes: permissions.can_send_video_notes(),
+            can_send_voice_notes: permissions.can_send_voice_notes(),
+            can_send_other_messages: permissions.can_send_other_messages(),
+            can_add_web_page_previews: permissions.can_add_web_page_previews(),
+            can_change_info: permissions.can_change_info(),
+            can_invite_users: permissions.can_invite_users(),
+            can_pin_messages: permissions.can_pin_messages(),
+            can_manage_topics: permissions.can_manage_topics(),
+            can_send_polls: permissions.can_send_polls(),
+        })
+    };
+    let user = chat_member_user(&lock, body.user_id);
+    lock.chat_members
+        .set_member(chat_id, ChatMember { user, kind });
+    lock.responses
         .restricted_chat_members
         .push(body.into_inner());
 
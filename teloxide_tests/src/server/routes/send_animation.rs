@@ -4,38 +4,67 @@ use crate::server::SentMessageAnimation;
 use crate::MockMessageAnimation;
 use std::collections::HashMap;
 use std::str::FromStr;
+use std::sync::Mutex;
 
 use crate::proc_macros::SerializeRawFields;
 use actix_multipart::Multipart;
+use actix_web::web;
 use actix_web::Responder;
-use actix_web::{error::ErrorBadRequest, web};
 use mime::Mime;
 use rand::distributions::{Alphanumeric, DistString};
 use serde::Deserialize;
-use teloxide::types::{Me, MessageEntity, ParseMode, ReplyMarkup, ReplyParameters, Seconds};
+use teloxide::types::{MessageEntity, ParseMode, ReplyMarkup, ReplyParameters, Seconds};
+use teloxide::ApiError;
 
-use crate::server::{routes::check_if_message_exists, FILES, MESSAGES, RESPONSES};
+use crate::server::{
+    routes::{check_if_message_exists, parse_caption},
+    State,
+};
 
-use super::{get_raw_multipart_fields, make_telegram_result, BodyChatId};
+use super::{
+    check_caption, check_limits, check_message_thread, check_reply_markup,
+    get_raw_multipart_fields, make_telegram_result, put_in_thread, BodyChatId,
+};
 
-pub async fn send_animation(mut payload: Multipart, me: web::Data<Me>) -> impl Responder {
+pub async fn send_animation(
+    mut payload: Multipart,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
     let (fields, attachments) = get_raw_multipart_fields(&mut payload).await;
     let body =
         SendMessageAnimationBody::serialize_raw_fields(&fields, &attachments, FileType::Animation)
             .unwrap();
+    let mut lock = state.lock().unwrap();
+    check_limits!(check_reply_markup(
+        &lock.api_limits,
+        body.reply_markup.as_ref()
+    ));
+    let thread = check_message_thread!(lock, body.chat_id.id(), body.message_thread_id);
     let chat = body.chat_id.chat();
 
     let mut message = // Creates the message, which will be mutated to fit the needed shape
         MockMessageAnimation::new().chat(chat);
-    message.from = Some(me.user.clone());
+    message.from = Some(lock.me.user.clone());
     message.has_protected_content = body.protect_content.unwrap_or(false);
-    message.caption = body.caption.clone();
-    message.caption_entities = body.caption_entities.clone().unwrap_or_default();
+    (message.caption, message.caption_entities) = parse_caption!(
+        body.caption.as_deref(),
+        body.parse_mode,
+        body.caption_entities.clone()
+    );
+    check_limits!(check_caption(&lock.api_limits, message.caption.as_deref()));
     message.has_media_spoiler = body.has_spoiler.unwrap_or_default();
 
     if let Some(reply_parameters) = &body.reply_parameters {
-        check_if_message_exists!(reply_parameters.message_id.0);
-        let reply_to_message = MESSAGES.get_message(reply_parameters.message_id.0).unwrap();
+        check_if_message_exists!(
+            lock,
+            body.chat_id.id(),
+            reply_parameters.message_id.0,
+            ApiError::MessageToReplyNotFound
+        );
+        let reply_to_message = lock
+            .messages
+            .get_message(body.chat_id.id(), reply_parameters.message_id.0)
+            .unwrap();
         message.reply_to_message = Some(Box::new(reply_to_message.clone()));
     }
     if let Some(ReplyMarkup::InlineKeyboard(markup)) = body.reply_markup.clone() {
@@ -48,7 +77,7 @@ pub async fn send_animation(mut payload: Multipart, me: web::Data<Me>) -> impl R
     message.file_name = Some(body.file_name.clone());
     message.file_id = file_id.clone();
     message.file_unique_id = file_unique_id.clone();
-    message.file_size = body.file_data.bytes().len() as u32;
+    message.file_size = body.file_data.len() as u32;
     message.duration = body.duration.unwrap_or(Seconds::from_seconds(0));
     message.width = body.width.unwrap_or(100);
     message.height = body.height.unwrap_or(100);
@@ -58,16 +87,19 @@ pub async fn send_animation(mut payload: Multipart, me: web::Data<Me>) -> impl R
             .unwrap_or(Mime::from_str("image/gif").unwrap()),
     );
 
-    let last_id = MESSAGES.max_message_id();
-    let message = MESSAGES.add_message(message.id(last_id + 1).build());
+    let last_id = lock.messages.max_message_id(body.chat_id.id());
+    let message = lock.messages.add_message(put_in_thread(
+        message.id(last_id + 1).build(),
+        thread.as_ref(),
+    ));
 
-    FILES.lock().unwrap().push(teloxide::types::File {
-        meta: message.animation().unwrap().file.clone(),
-        path: body.file_name.to_owned(),
-    });
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock.sent_messages.push(message.clone());
-    responses_lock
+    lock.files.add_file(
+        message.animation().unwrap().file.clone(),
+        &body.file_name,
+        Some(body.file_data.clone()),
+    );
+    lock.responses.sent_messages.push(message.clone());
+    lock.responses
         .sent_messages_animation
         .push(SentMessageAnimation {
             message: message.clone(),
@@ -81,7 +113,7 @@ pub async fn send_animation(mut payload: Multipart, me: web::Data<Me>) -> impl R
 pub struct SendMessageAnimationBody {
     pub chat_id: BodyChatId,
     pub file_name: String,
-    pub file_data: String,
+    pub file_data: Vec<u8>,
     pub duration: Option<Seconds>,
     pub width: Option<u32>,
     pub height: Option<u32>,
@@ -1,8 +1,10 @@
+use std::sync::Mutex;
+
 use actix_web::{web, Responder};
 use serde::Deserialize;
 
 use crate::server::routes::make_telegram_result;
-use crate::server::RESPONSES;
+use crate::server::State;
 
 use super::BodyChatId;
 
@@ -13,9 +15,12 @@ pub struct SendChatActionBody {
     pub action: String,
 }
 
-pub async fn send_chat_action(body: web::Json<SendChatActionBody>) -> impl Responder {
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock.sent_chat_actions.push(body.into_inner());
+pub async fn send_chat_action(
+    body: web::Json<SendChatActionBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    lock.responses.sent_chat_actions.push(body.into_inner());
 
     make_telegram_result(true)
 }
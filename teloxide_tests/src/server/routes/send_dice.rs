@@ -1,12 +1,17 @@
+use std::sync::Mutex;
+
 use crate::server::routes::check_if_message_exists;
-use crate::server::{SentMessageDice, MESSAGES, RESPONSES};
+use crate::server::{SentMessageDice, State};
 use crate::MockMessageDice;
-use actix_web::error::ErrorBadRequest;
 use actix_web::{web, Responder};
 use serde::Deserialize;
 use teloxide::types::{DiceEmoji, ReplyMarkup, ReplyParameters};
+use teloxide::ApiError;
 
-use super::{make_telegram_result, BodyChatId};
+use super::{
+    check_limits, check_message_thread, check_reply_markup, make_telegram_result, put_in_thread,
+    BodyChatId,
+};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct SendMessageDiceBody {
@@ -21,7 +26,16 @@ pub struct SendMessageDiceBody {
     pub reply_parameters: Option<ReplyParameters>,
 }
 
-pub async fn send_dice(body: web::Json<SendMessageDiceBody>) -> impl Responder {
+pub async fn send_dice(
+    body: web::Json<SendMessageDiceBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    check_limits!(check_reply_markup(
+        &lock.api_limits,
+        body.reply_markup.as_ref()
+    ));
+    let thread = check_message_thread!(lock, body.chat_id.id(), body.message_thread_id);
     let chat = body.chat_id.chat();
     let mut message = // Creates the message, which will be mutated to fit the needed shape
         MockMessageDice::new().chat(chat);
@@ -29,15 +43,22 @@ pub async fn send_dice(body: web::Json<SendMessageDiceBody>) -> impl Responder {
     // Random from 1 to 5 because it fits all the emoji
     message.value = (1 + rand::random::<u8>() % 5) as u8;
     if let Some(reply_parameters) = &body.reply_parameters {
-        check_if_message_exists!(reply_parameters.message_id.0);
+        check_if_message_exists!(
+            lock,
+            body.chat_id.id(),
+            reply_parameters.message_id.0,
+            ApiError::MessageToReplyNotFound
+        );
     }
 
-    let last_id = MESSAGES.max_message_id();
-    let message = MESSAGES.add_message(message.id(last_id + 1).build());
+    let last_id = lock.messages.max_message_id(body.chat_id.id());
+    let message = lock.messages.add_message(put_in_thread(
+        message.id(last_id + 1).build(),
+        thread.as_ref(),
+    ));
 
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock.sent_messages.push(message.clone());
-    responses_lock.sent_messages_dice.push(SentMessageDice {
+    lock.responses.sent_messages.push(message.clone());
+    lock.responses.sent_messages_dice.push(SentMessageDice {
         message: message.clone(),
         bot_request: body.into_inner(),
     });
@@ -0,0 +1,118 @@
+use std::sync::Mutex;
+
+use crate::server::routes::check_if_message_exists;
+use crate::server::{SentMessageInvoice, State};
+use crate::MockMessageInvoice;
+use actix_web::{web, Responder};
+use serde::Deserialize;
+use teloxide::types::{LabeledPrice, ReplyMarkup, ReplyParameters};
+use teloxide::ApiError;
+
+use super::{
+    check_limits, check_message_thread, check_reply_markup, make_telegram_error,
+    make_telegram_result, put_in_thread, BodyChatId,
+};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SendMessageInvoiceBody {
+    pub chat_id: BodyChatId,
+    pub message_thread_id: Option<i64>,
+    pub title: String,
+    pub description: String,
+    pub payload: String,
+    pub provider_token: Option<String>,
+    pub currency: String,
+    pub prices: Vec<LabeledPrice>,
+    pub max_tip_amount: Option<u32>,
+    pub suggested_tip_amounts: Option<Vec<u32>>,
+    pub start_parameter: Option<String>,
+    pub provider_data: Option<String>,
+    pub photo_url: Option<String>,
+    pub photo_size: Option<u32>,
+    pub photo_width: Option<u32>,
+    pub photo_height: Option<u32>,
+    pub need_name: Option<bool>,
+    pub need_phone_number: Option<bool>,
+    pub need_email: Option<bool>,
+    pub need_shipping_address: Option<bool>,
+    pub send_phone_number_to_provider: Option<bool>,
+    pub send_email_to_provider: Option<bool>,
+    pub is_flexible: Option<bool>,
+    pub disable_notification: Option<bool>,
+    pub protect_content: Option<bool>,
+    pub message_effect_id: Option<String>,
+    #[serde(default, with = "crate::server::routes::reply_markup_deserialize")]
+    pub reply_markup: Option<ReplyMarkup>,
+    pub reply_parameters: Option<ReplyParameters>,
+}
+
+pub async fn send_invoice(
+    body: web::Json<SendMessageInvoiceBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    check_limits!(check_reply_markup(
+        &lock.api_limits,
+        body.reply_markup.as_ref()
+    ));
+    let thread = check_message_thread!(lock, body.chat_id.id(), body.message_thread_id);
+    let has_provider = body
+        .provider_token
+        .as_ref()
+        .is_some_and(|token| !token.is_empty());
+    // Telegram Stars are paid without a provider, everything else needs one
+    if body.currency == "XTR" && has_provider {
+        return make_telegram_error(ApiError::Unknown(
+            "Bad Request: PAYMENT_PROVIDER_INVALID".to_string(),
+        ));
+    }
+    if body.currency != "XTR" && !has_provider {
+        return make_telegram_error(ApiError::Unknown(
+            "Bad Request: PAYMENT_PROVIDER_INVALID".to_string(),
+        ));
+    }
+    if body.prices.is_empty() {
+        return make_telegram_error(ApiError::Unknown(
+            "Bad Request: CURRENCY_TOTAL_AMOUNT_INVALID".to_string(),
+        ));
+    }
+    let mut reply_to_message = None;
+    if let Some(reply_parameters) = &body.reply_parameters {
+        check_if_message_exists!(
+            lock,
+            body.chat_id.id(),
+            reply_parameters.message_id.0,
+            ApiError::MessageToReplyNotFound
+        );
+        reply_to_message = lock
+            .messages
+            .get_message(body.chat_id.id(), reply_parameters.message_id.0);
+    }
+
+    let chat = body.chat_id.chat();
+    let mut message = // Creates the message, which will be mutated to fit the needed shape
+        MockMessageInvoice::new().chat(chat);
+    message.from = Some(lock.me.user.clone());
+    message.title = body.title.clone();
+    message.description = body.description.clone();
+    message.start_parameter = body.start_parameter.clone().unwrap_or_default();
+    message.currency = body.currency.clone();
+    message.total_amount = body.prices.iter().map(|price| price.amount).sum();
+
+    let last_id = lock.messages.max_message_id(body.chat_id.id());
+    let message = lock.messages.add_message(put_in_thread(
+        message.id(last_id + 1).build(),
+        thread.as_ref(),
+    ));
+
+    lock.responses.sent_messages.push(message.clone());
+    lock.responses
+        .sent_messages_invoice
+        .push(SentMessageInvoice {
+            message: message.clone(),
+            reply_to_message,
+            bot_request: body.into_inner(),
+        });
+
+    make_telegram_result(message)
+}
@@ -1,13 +1,18 @@
-use crate::server::{SentMessageLocation, MESSAGES, RESPONSES};
+use std::sync::Mutex;
+
+use crate::server::{SentMessageLocation, State};
 use crate::MockMessageLocation;
-use actix_web::error::ErrorBadRequest;
 use actix_web::{web, Responder};
 use serde::Deserialize;
-use teloxide::types::{Me, ReplyMarkup, ReplyParameters, Seconds};
+use teloxide::types::{ReplyMarkup, ReplyParameters, Seconds};
+use teloxide::ApiError;
 
 use crate::server::routes::check_if_message_exists;
 
-use super::{make_telegram_result, BodyChatId};
+use super::{
+    check_limits, check_message_thread, check_reply_markup, make_telegram_result, put_in_thread,
+    BodyChatId,
+};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct SendMessageLocationBody {
@@ -29,12 +34,18 @@ pub struct SendMessageLocationBody {
 
 pub async fn send_location(
     body: web::Json<SendMessageLocationBody>,
-    me: web::Data<Me>,
+    state: web::Data<Mutex<State>>,
 ) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    check_limits!(check_reply_markup(
+        &lock.api_limits,
+        body.reply_markup.as_ref()
+    ));
+    let thread = check_message_thread!(lock, body.chat_id.id(), body.message_thread_id);
     let chat = body.chat_id.chat();
     let mut message = // Creates the message, which will be mutated to fit the needed shape
         MockMessageLocation::new().chat(chat).latitude(body.latitude).longitude(body.longitude);
-    message.from = Some(me.user.clone());
+    message.from = Some(lock.me.user.clone());
     message.horizontal_accuracy = body.horizontal_accuracy;
     message.live_period = body.live_period;
     message.heading = body.heading;
@@ -42,20 +53,30 @@ pub async fn send_location(
     message.has_protected_content = body.protect_content.unwrap_or(false);
 
     if let Some(reply_parameters) = &body.reply_parameters {
-        check_if_message_exists!(reply_parameters.message_id.0);
-        let reply_to_message = MESSAGES.get_message(reply_parameters.message_id.0).unwrap();
+        check_if_message_exists!(
+            lock,
+            body.chat_id.id(),
+            reply_parameters.message_id.0,
+            ApiError::MessageToReplyNotFound
+        );
+        let reply_to_message = lock
+            .messages
+            .get_message(body.chat_id.id(), reply_parameters.message_id.0)
+            .unwrap();
         message.reply_to_message = Some(Box::new(reply_to_message.clone()));
     }
     if let Some(ReplyMarkup::InlineKeyboard(markup)) = body.reply_markup.clone() {
         message.reply_markup = Some(markup);
     }
 
-    let last_id = MESSAGES.max_message_id();
-    let message = MESSAGES.add_message(message.id(last_id + 1).build());
+    let last_id = lock.messages.max_message_id(body.chat_id.id());
+    let message = lock.messages.add_message(put_in_thread(
+        message.id(last_id + 1).build(),
+        thread.as_ref(),
+    ));
 
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock.sent_messages.push(message.clone());
-    responses_lock
+    lock.responses.sent_messages.push(message.clone());
+    lock.responses
         .sent_messages_location
         .push(SentMessageLocation {
             message: message.clone(),
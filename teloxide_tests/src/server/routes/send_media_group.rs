@@ -1,61 +1,104 @@
-use crate::server::{SentMediaGroup, FILES, MESSAGES, RESPONSES};
+use crate::server::{SentMediaGroup, State};
 use crate::{
     MockMessageAudio, MockMessageDocument, MockMessagePhoto, MockMessageVideo, MockPhotoSize,
     MockVideo,
 };
 use std::collections::HashMap;
+use std::sync::Mutex;
 
 use actix_multipart::Multipart;
+use actix_web::web;
 use actix_web::Responder;
-use actix_web::{error::ErrorBadRequest, web};
 use rand::distributions::{Alphanumeric, DistString};
 use serde::Deserialize;
 use serde_json::Value;
-use teloxide::types::{Me, Message, MessageEntity, MessageId, ParseMode, ReplyParameters, Seconds};
+use teloxide::types::{Message, MessageEntity, MessageId, ParseMode, ReplyParameters, Seconds};
+use teloxide::ApiError;
 
-use crate::server::routes::check_if_message_exists;
+use crate::server::routes::{check_if_message_exists, parse_caption};
 
 use super::{
-    get_raw_multipart_fields, make_telegram_result, Attachment, BodyChatId, MediaGroupInputMedia,
-    MediaGroupInputMediaAudio, MediaGroupInputMediaDocument, MediaGroupInputMediaPhoto,
-    MediaGroupInputMediaVideo,
+    check_caption, check_limits, check_message_thread, get_raw_multipart_fields,
+    make_telegram_error, make_telegram_result, put_in_thread, Attachment, BodyChatId,
+    MediaGroupInputMedia, MediaGroupInputMediaAudio, MediaGroupInputMediaDocument,
+    MediaGroupInputMediaPhoto, MediaGroupInputMediaVideo,
 };
 
-pub async fn send_media_group(mut payload: Multipart, me: web::Data<Me>) -> impl Responder {
+pub async fn send_media_group(
+    mut payload: Multipart,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
     let (fields, attachments) = get_raw_multipart_fields(&mut payload).await;
     let body = SendMediaGroupBody::serialize_raw_fields(&fields, &attachments).unwrap();
-    if body.media.len() > 10 {
-        return ErrorBadRequest("Too many media items").into();
+    let mut lock = state.lock().unwrap();
+    if body.media.len() > lock.api_limits.media_group_items {
+        return make_telegram_error(ApiError::TooMuchMessages);
     } else if body.media.len() < 2 {
-        return ErrorBadRequest("Too few media items").into();
+        return make_telegram_error(ApiError::Unknown(
+            "Bad Request: Too few messages to send as an album".to_string(),
+        ));
     }
+    let thread = check_message_thread!(lock, body.chat_id.id(), body.message_thread_id);
     let chat = body.chat_id.chat();
     let protect_content = body.protect_content;
     let mut reply_to_message = None;
     if let Some(reply_parameters) = &body.reply_parameters {
-        check_if_message_exists!(reply_parameters.message_id.0);
+        check_if_message_exists!(
+            lock,
+            body.chat_id.id(),
+            reply_parameters.message_id.0,
+            ApiError::MessageToReplyNotFound
+        );
         // All of messages in the media group are replying to the same message
-        reply_to_message = Some(Box::new(MESSAGES.get_message(reply_parameters.message_id.0).unwrap()));
+        reply_to_message = Some(Box::new(
+            lock.messages
+                .get_message(body.chat_id.id(), reply_parameters.message_id.0)
+                .unwrap(),
+        ));
+    }
+    // Telegram rejects the whole group if any of the captions can't be parsed
+    let mut captions = vec![];
+    for media in &body.media {
+        let (caption, parse_mode, caption_entities) = match media {
+            MediaGroupInputMedia::InputMediaAudio(audio) => {
+                (&audio.caption, audio.parse_mode, &audio.caption_entities)
+            }
+            MediaGroupInputMedia::InputMediaDocument(document) => (
+                &document.caption,
+                document.parse_mode,
+                &document.caption_entities,
+            ),
+            MediaGroupInputMedia::InputMediaPhoto(photo) => {
+                (&photo.caption, photo.parse_mode, &photo.caption_entities)
+            }
+            MediaGroupInputMedia::InputMediaVideo(video) => {
+                (&video.caption, video.parse_mode, &video.caption_entities)
+            }
+        };
+        let (caption, caption_entities) =
+            parse_caption!(caption.as_deref(), parse_mode, caption_entities.clone());
+        check_limits!(check_caption(&lock.api_limits, caption.as_deref()));
+        captions.push((caption, caption_entities));
     }
     let media_group_id = Alphanumeric.sample_string(&mut rand::thread_rng(), 16);
 
     let mut messages: Vec<Message> = vec![];
 
-    for media in &body.media {
+    for (media, (caption, caption_entities)) in body.media.iter().zip(captions) {
         let file_id = Alphanumeric.sample_string(&mut rand::thread_rng(), 16);
         let file_unique_id = Alphanumeric.sample_string(&mut rand::thread_rng(), 8);
-        let last_id = MESSAGES.max_message_id();
+        let last_id = lock.messages.max_message_id(body.chat_id.id());
         let message: Message;
         match media {
             MediaGroupInputMedia::InputMediaAudio(audio) => {
                 let mut mock_message = MockMessageAudio::new();
                 mock_message.chat = chat.clone();
-                mock_message.from = Some(me.user.clone());
+                mock_message.from = Some(lock.me.user.clone());
 
                 mock_message.has_protected_content = protect_content.unwrap_or(false);
                 mock_message.reply_to_message = reply_to_message.clone();
-                mock_message.caption = audio.caption.clone();
-                mock_message.caption_entities = audio.caption_entities.clone().unwrap_or_default();
+                mock_message.caption = caption;
+                mock_message.caption_entities = caption_entities;
                 mock_message.media_group_id = Some(media_group_id.clone());
                 mock_message.performer = audio.performer.clone();
                 mock_message.title = audio.title.clone();
@@ -64,79 +107,81 @@ pub async fn send_media_group(mut payload: Multipart, me: web::Data<Me>) -> impl
                 mock_message.file_name = Some(audio.file_name.clone());
                 mock_message.file_id = file_id.clone();
                 mock_message.file_unique_id = file_unique_id.clone();
-                mock_message.file_size = audio.file_data.bytes().len() as u32;
+                mock_message.file_size = audio.file_data.len() as u32;
                 mock_message.mime_type = mime_guess::from_path(&audio.file_name).first();
 
                 mock_message.id = MessageId(last_id + 1);
                 message = mock_message.build();
 
-                FILES.lock().unwrap().push(teloxide::types::File {
-                    meta: message.audio().unwrap().file.clone(),
-                    path: audio.file_name.clone(),
-                });
+                lock.files.add_file(
+                    message.audio().unwrap().file.clone(),
+                    &audio.file_name,
+                    Some(audio.file_data.clone()),
+                );
             }
             MediaGroupInputMedia::InputMediaDocument(document) => {
                 let mut mock_message = MockMessageDocument::new();
                 mock_message.chat = chat.clone();
-                mock_message.from = Some(me.user.clone());
+                mock_message.from = Some(lock.me.user.clone());
 
                 mock_message.has_protected_content = protect_content.unwrap_or(false);
                 mock_message.reply_to_message = reply_to_message.clone();
-                mock_message.caption = document.caption.clone();
-                mock_message.caption_entities =
-                    document.caption_entities.clone().unwrap_or_default();
+                mock_message.caption = caption;
+                mock_message.caption_entities = caption_entities;
                 mock_message.media_group_id = Some(media_group_id.clone());
 
                 mock_message.file_name = Some(document.file_name.clone());
                 mock_message.file_id = file_id.clone();
                 mock_message.file_unique_id = file_unique_id.clone();
-                mock_message.file_size = document.file_data.bytes().len() as u32;
+                mock_message.file_size = document.file_data.len() as u32;
                 mock_message.mime_type = mime_guess::from_path(&document.file_name).first();
 
                 mock_message.id = MessageId(last_id + 1);
                 message = mock_message.build();
 
-                FILES.lock().unwrap().push(teloxide::types::File {
-                    meta: message.document().unwrap().file.clone(),
-                    path: document.file_name.clone(),
-                });
+                lock.files.add_file(
+                    message.document().unwrap().file.clone(),
+                    &document.file_name,
+                    Some(document.file_data.clone()),
+                );
             }
             MediaGroupInputMedia::InputMediaPhoto(photo) => {
                 let mut mock_message = MockMessagePhoto::new();
                 mock_message.chat = chat.clone();
-                mock_message.from = Some(me.user.clone());
+                mock_message.from = Some(lock.me.user.clone());
 
                 mock_message.has_protected_content = protect_content.unwrap_or(false);
                 mock_message.reply_to_message = reply_to_message.clone();
-                mock_message.caption = photo.caption.clone();
-                mock_message.caption_entities = photo.caption_entities.clone().unwrap_or_default();
+                mock_message.caption = caption;
+                mock_message.caption_entities = caption_entities;
                 mock_message.media_group_id = Some(media_group_id.clone());
 
                 let mut mock_photo = MockPhotoSize::new();
 
                 mock_photo.file_id = file_id.clone();
                 mock_photo.file_unique_id = file_unique_id.clone();
-                mock_photo.file_size = photo.file_data.bytes().len() as u32;
+                mock_photo.file_size = photo.file_data.len() as u32;
 
                 mock_message.photo = vec![mock_photo.build()];
 
                 mock_message.id = MessageId(last_id + 1);
                 message = mock_message.build();
 
-                FILES.lock().unwrap().push(teloxide::types::File {
-                    meta: message.photo().unwrap().first().unwrap().clone().file,
-                    path: photo.file_name.clone(),
-                });
+                lock.files.add_file(
+                    message.photo().unwrap().first().unwrap().clone().file,
+                    &photo.file_name,
+                    Some(photo.file_data.clone()),
+                );
             }
             MediaGroupInputMedia::InputMediaVideo(video) => {
                 let mut mock_message = MockMessageVideo::new();
                 mock_message.chat = chat.clone();
-                mock_message.from = Some(me.user.clone());
+                mock_message.from = Some(lock.me.user.clone());
 
                 mock_message.has_protected_content = protect_content.unwrap_or(false);
                 mock_message.reply_to_message = reply_to_message.clone();
-                mock_message.caption = video.caption.clone();
-                mock_message.caption_entities = video.caption_entities.clone().unwrap_or_default();
+                mock_message.caption = caption;
+                mock_message.caption_entities = caption_entities;
                 mock_message.media_group_id = Some(media_group_id.clone());
 
                 let mut mock_video = MockVideo::new();
@@ -147,7 +192,7 @@ pub async fn send_media_group(mut payload: Multipart, me: web::Data<Me>) -> impl
                 mock_video.duration = video.duration.unwrap_or(Seconds::from_seconds(1));
                 mock_video.file_id = file_id.clone();
                 mock_video.file_unique_id = file_unique_id.clone();
-                mock_video.file_size = video.file_data.bytes().len() as u32;
+                mock_video.file_size = video.file_data.len() as u32;
                 mock_video.file_name = Some(video.file_name.clone());
 
                 mock_message.video = mock_video.build();
@@ -155,20 +200,21 @@ pub async fn send_media_group(mut payload: Multipart, me: web::Data<Me>) -> impl
                 mock_message.id = MessageId(last_id + 1);
                 message = mock_message.build();
 
-                FILES.lock().unwrap().push(teloxide::types::File {
-                    meta: message.video().unwrap().file.clone(),
-                    path: video.file_name.clone(),
-                });
+                lock.files.add_file(
+                    message.video().unwrap().file.clone(),
+                    &video.file_name,
+                    Some(video.file_data.clone()),
+                );
             }
         }
 
+        let message = put_in_thread(message, thread.as_ref());
         messages.push(message.clone());
-        MESSAGES.add_message(message);
+        lock.messages.add_message(message);
     }
 
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock.sent_messages.extend(messages.clone());
-    responses_lock.sent_media_group.push(SentMediaGroup {
+    lock.responses.sent_messages.extend(messages.clone());
+    lock.responses.sent_media_group.push(SentMediaGroup {
         messages: messages.clone(),
         bot_request: body,
     });
@@ -207,7 +253,7 @@ impl SendMediaGroupBody {
                 file_data = attachment.file_data.clone();
             } else {
                 file_name = None;
-                file_data = raw_media_item.get("media").unwrap().to_string();
+                file_data = raw_media_string.as_bytes().to_vec();
             }
 
             let media_type = raw_media_item.get("type").unwrap();
@@ -1,14 +1,20 @@
+use std::sync::Mutex;
+
 use crate::dataset::message_common::MockMessageText;
-use actix_web::error::ErrorBadRequest;
 use actix_web::{web, Responder};
 use serde::Deserialize;
-use teloxide::types::{
-    LinkPreviewOptions, Me, MessageEntity, ParseMode, ReplyMarkup, ReplyParameters,
-};
+use teloxide::types::{LinkPreviewOptions, MessageEntity, ParseMode, ReplyMarkup, ReplyParameters};
+use teloxide::ApiError;
 
-use crate::server::{routes::check_if_message_exists, SentMessageText, MESSAGES, RESPONSES};
+use crate::server::{
+    routes::{check_if_message_exists, parse_text},
+    SentMessageText, State,
+};
 
-use super::{make_telegram_result, BodyChatId};
+use super::{
+    check_limits, check_message_text, check_message_thread, check_reply_markup,
+    make_telegram_result, put_in_thread, BodyChatId,
+};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct SendMessageTextBody {
@@ -28,30 +34,48 @@ pub struct SendMessageTextBody {
 
 pub async fn send_message(
     body: web::Json<SendMessageTextBody>,
-    me: web::Data<Me>,
+    state: web::Data<Mutex<State>>,
 ) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    check_limits!(check_reply_markup(
+        &lock.api_limits,
+        body.reply_markup.as_ref()
+    ));
+    let thread = check_message_thread!(lock, body.chat_id.id(), body.message_thread_id);
     let chat = body.chat_id.chat();
+    let (text, entities) = parse_text!(&body.text, body.parse_mode, body.entities.clone());
+    check_limits!(check_message_text(&lock.api_limits, &text));
     let mut message = // Creates the message, which will be mutated to fit the needed shape
-        MockMessageText::new().text(&body.text).chat(chat);
-    message.from = Some(me.user.clone());
+        MockMessageText::new().text(&text).chat(chat);
+    message.from = Some(lock.me.user.clone());
     message.has_protected_content = body.protect_content.unwrap_or(false);
 
-    message.entities = body.entities.clone().unwrap_or_default();
+    message.entities = entities;
     if let Some(reply_parameters) = &body.reply_parameters {
-        check_if_message_exists!(reply_parameters.message_id.0);
-        let reply_to_message = MESSAGES.get_message(reply_parameters.message_id.0).unwrap();
+        check_if_message_exists!(
+            lock,
+            body.chat_id.id(),
+            reply_parameters.message_id.0,
+            ApiError::MessageToReplyNotFound
+        );
+        let reply_to_message = lock
+            .messages
+            .get_message(body.chat_id.id(), reply_parameters.message_id.0)
+            .unwrap();
         message.reply_to_message = Some(Box::new(reply_to_message.clone()));
     }
     if let Some(ReplyMarkup::InlineKeyboard(markup)) = body.reply_markup.clone() {
         message.reply_markup = Some(markup);
     }
 
-    let last_id = MESSAGES.max_message_id();
-    let message = MESSAGES.add_message(message.id(last_id + 1).build());
+    let last_id = lock.messages.max_message_id(body.chat_id.id());
+    let message = lock.messages.add_message(put_in_thread(
+        message.id(last_id + 1).build(),
+        thread.as_ref(),
+    ));
 
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock.sent_messages.push(message.clone());
-    responses_lock.sent_messages_text.push(SentMessageText {
+    lock.responses.sent_messages.push(message.clone());
+    lock.responses.sent_messages_text.push(SentMessageText {
         message: message.clone(),
         bot_request: body.into_inner(),
     });
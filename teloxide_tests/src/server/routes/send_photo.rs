@@ -1,38 +1,61 @@
 use crate::server::routes::Attachment;
 use crate::server::routes::{FileType, SerializeRawFields};
 use std::collections::HashMap;
+use std::sync::Mutex;
 
 use crate::dataset::{MockMessagePhoto, MockPhotoSize};
 use crate::proc_macros::SerializeRawFields;
 use actix_multipart::Multipart;
-use actix_web::error::ErrorBadRequest;
 use actix_web::{web, Responder};
 use rand::distributions::{Alphanumeric, DistString};
 use serde::Deserialize;
-use teloxide::types::{LinkPreviewOptions, Me, MessageEntity, ParseMode, ReplyMarkup, ReplyParameters};
+use teloxide::types::{LinkPreviewOptions, MessageEntity, ParseMode, ReplyMarkup, ReplyParameters};
+use teloxide::ApiError;
 
 use crate::server::{
-    routes::check_if_message_exists, SentMessagePhoto, FILES, MESSAGES, RESPONSES,
+    routes::{check_if_message_exists, parse_caption},
+    SentMessagePhoto, State,
 };
 
-use super::{get_raw_multipart_fields, make_telegram_result, BodyChatId};
+use super::{
+    check_caption, check_limits, check_message_thread, check_reply_markup,
+    get_raw_multipart_fields, make_telegram_result, put_in_thread, BodyChatId,
+};
 
-pub async fn send_photo(mut payload: Multipart, me: web::Data<Me>) -> impl Responder {
+pub async fn send_photo(mut payload: Multipart, state: web::Data<Mutex<State>>) -> impl Responder {
     let (fields, attachments) = get_raw_multipart_fields(&mut payload).await;
     let body =
         SendMessagePhotoBody::serialize_raw_fields(&fields, &attachments, FileType::Photo).unwrap();
+    let mut lock = state.lock().unwrap();
+    check_limits!(check_reply_markup(
+        &lock.api_limits,
+        body.reply_markup.as_ref()
+    ));
+    let thread = check_message_thread!(lock, body.chat_id.id(), body.message_thread_id);
     let chat = body.chat_id.chat();
 
     let mut message = // Creates the message, which will be mutated to fit the needed shape
         MockMessagePhoto::new().chat(chat);
-    message.from = Some(me.user.clone());
+    message.from = Some(lock.me.user.clone());
     message.has_protected_content = body.protect_content.unwrap_or(false);
-    message.caption = body.caption.clone();
-    message.caption_entities = body.caption_entities.clone().unwrap_or_default();
+    (message.caption, message.caption_entities) = parse_caption!(
+        body.caption.as_deref(),
+        body.parse_mode,
+        body.caption_entities.clone()
+    );
+    check_limits!(check_caption(&lock.api_limits, message.caption.as_deref()));
 
     if let Some(reply_parameters) = &body.reply_parameters {
-        check_if_message_exists!(reply_parameters.message_id.0);
-        let reply_to_message = MESSAGES.get_message(reply_parameters.message_id.0).unwrap();
+        check_if_message_exists!(
+            lock,
+            body.chat_id.id(),
+            reply_parameters.message_id.0,
+            ApiError::MessageToReplyNotFound
+        );
+        let reply_to_message = lock
+            .messages
+            .get_message(body.chat_id.id(), reply_parameters.message_id.0)
+            .unwrap();
         message.reply_to_message = Some(Box::new(reply_to_message.clone()));
     }
     if let Some(ReplyMarkup::InlineKeyboard(markup)) = body.reply_markup.clone() {
@@ -45,19 +68,22 @@ pub async fn send_photo(mut payload: Multipart, me: web::Data<Me>) -> impl Respo
     message.photo = vec![MockPhotoSize::new()
         .file_id(file_id.clone())
         .file_unique_id(file_unique_id.clone())
-        .file_size(body.file_data.bytes().len() as u32)
+        .file_size(body.file_data.len() as u32)
         .build()];
 
-    let last_id = MESSAGES.max_message_id();
-    let message = MESSAGES.add_message(message.id(last_id + 1).build());
+    let last_id = lock.messages.max_message_id(body.chat_id.id());
+    let message = lock.messages.add_message(put_in_thread(
+        message.id(last_id + 1).build(),
+        thread.as_ref(),
+    ));
 
-    FILES.lock().unwrap().push(teloxide::types::File {
-        meta: message.photo().unwrap()[0].file.clone(),
-        path: body.file_name.to_owned(),
-    });
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock.sent_messages.push(message.clone());
-    responses_lock.sent_messages_photo.push(SentMessagePhoto {
+    lock.files.add_file(
+        message.photo().unwrap()[0].file.clone(),
+        &body.file_name,
+        Some(body.file_data.clone()),
+    );
+    lock.responses.sent_messages.push(message.clone());
+    lock.responses.sent_messages_photo.push(SentMessagePhoto {
         message: message.clone(),
         bot_request: body,
     });
@@ -69,7 +95,7 @@ pub async fn send_photo(mut payload: Multipart, me: web::Data<Me>) -> impl Respo
 pub struct SendMessagePhotoBody {
     pub chat_id: BodyChatId,
     pub file_name: String,
-    pub file_data: String,
+    pub file_data: Vec<u8>,
     pub caption: Option<String>,
     pub message_thread_id: Option<i64>,
     pub parse_mode: Option<ParseMode>,
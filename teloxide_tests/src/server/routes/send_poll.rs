@@ -1,14 +1,21 @@
-use crate::server::{SentMessagePoll, MESSAGES, RESPONSES};
+use std::sync::Mutex;
+
+use crate::server::{SentMessagePoll, State};
 use crate::MockMessagePoll;
-use actix_web::error::ErrorBadRequest;
 use actix_web::{web, Responder};
 use chrono::DateTime;
 use serde::Deserialize;
-use teloxide::types::{Me, MessageEntity, ParseMode, PollOption, PollType, ReplyMarkup, ReplyParameters, Seconds};
+use teloxide::types::{
+    MessageEntity, ParseMode, PollOption, PollType, ReplyMarkup, ReplyParameters, Seconds,
+};
+use teloxide::ApiError;
 
 use crate::server::routes::check_if_message_exists;
 
-use super::{make_telegram_result, BodyChatId};
+use super::{
+    check_limits, check_message_thread, check_reply_markup, make_telegram_error,
+    make_telegram_result, put_in_thread, text_length, BodyChatId,
+};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct SendMessagePollBody {
@@ -36,13 +43,45 @@ pub struct SendMessagePollBody {
     pub reply_parameters: Option<ReplyParameters>,
 }
 
-pub async fn send_poll(body: web::Json<SendMessagePollBody>, me: web::Data<Me>) -> impl Responder {
+pub async fn send_poll(
+    body: web::Json<SendMessagePollBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    check_limits!(check_reply_markup(
+        &lock.api_limits,
+        body.reply_markup.as_ref()
+    ));
+    let thread = check_message_thread!(lock, body.chat_id.id(), body.message_thread_id);
+    let limits = lock.api_limits;
+    if body.question.trim().is_empty() {
+        return make_telegram_error(ApiError::PollQuestionMustBeNonEmpty);
+    }
+    if text_length(&body.question) > limits.poll_question {
+        return make_telegram_error(ApiError::PollQuestionLengthTooLong);
+    }
+    if body.options.len() < 2 {
+        return make_telegram_error(ApiError::PollMustHaveMoreOptions);
+    }
+    if body.options.len() > limits.poll_options {
+        return make_telegram_error(ApiError::PollCantHaveMoreOptions);
+    }
+    for option in body.options.iter() {
+        if option.trim().is_empty() {
+            return make_telegram_error(ApiError::PollOptionsMustBeNonEmpty);
+        }
+        if text_length(option) > limits.poll_option {
+            return make_telegram_error(ApiError::PollOptionsLengthTooLong);
+        }
+    }
     let chat = body.chat_id.chat();
     let mut message = // Creates the message, which will be mutated to fit the needed shape
         MockMessagePoll::new().chat(chat);
-    message.from = Some(me.user.clone());
+    message.from = Some(lock.me.user.clone());
     message.has_protected_content = body.protect_content.unwrap_or(false);
 
+    // Every poll has its own id, so the votes can find it
+    message.poll_id = rand::random::<u64>().to_string();
     message.question = body.question.clone();
     let mut options = vec![];
     for option in body.options.iter() {
@@ -52,6 +91,8 @@ pub async fn send_poll(body: web::Json<SendMessagePollBody>, me: web::Data<Me>)
         });
     }
     message.options = options;
+    message.is_closed = body.is_closed.unwrap_or(false);
+    message.total_voter_count = 0;
     message.is_anonymous = body.is_anonymous.unwrap_or(false);
     message.poll_type = body.r#type.clone().unwrap_or(PollType::Regular);
     message.allows_multiple_answers = body.allows_multiple_answers.unwrap_or(false);
@@ -62,20 +103,30 @@ pub async fn send_poll(body: web::Json<SendMessagePollBody>, me: web::Data<Me>)
     message.close_date = DateTime::from_timestamp(body.close_date.unwrap_or(0) as i64, 0);
 
     if let Some(reply_parameters) = &body.reply_parameters {
-        check_if_message_exists!(reply_parameters.message_id.0);
-        let reply_to_message = MESSAGES.get_message(reply_parameters.message_id.0).unwrap();
+        check_if_message_exists!(
+            lock,
+            body.chat_id.id(),
+            reply_parameters.message_id.0,
+            ApiError::MessageToReplyNotFound
+        );
+        let reply_to_message = lock
+            .messages
+            .get_message(body.chat_id.id(), reply_parameters.message_id.0)
+            .unwrap();
         message.reply_to_message = Some(Box::new(reply_to_message.clone()));
     }
     if let Some(ReplyMarkup::InlineKeyboard(markup)) = body.reply_markup.clone() {
         message.reply_markup = Some(markup);
     }
 
-    let last_id = MESSAGES.max_message_id();
-    let message = MESSAGES.add_message(message.id(last_id + 1).build());
+    let last_id = lock.messages.max_message_id(body.chat_id.id());
+    let message = lock.messages.add_message(put_in_thread(
+        message.id(last_id + 1).build(),
+        thread.as_ref(),
+    ));
 
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock.sent_messages.push(message.clone());
-    responses_lock.sent_messages_poll.push(SentMessagePoll {
+    lock.responses.sent_messages.push(message.clone());
+    lock.responses.sent_messages_poll.push(SentMessagePoll {
         message: message.clone(),
         bot_request: body.into_inner(),
     });
@@ -3,27 +3,40 @@ use crate::server::routes::{FileType, SerializeRawFields};
 use crate::server::SentMessageSticker;
 use crate::MockMessageSticker;
 use std::collections::HashMap;
+use std::sync::Mutex;
 
 use crate::proc_macros::SerializeRawFields;
 use actix_multipart::Multipart;
-use actix_web::error::ErrorBadRequest;
 use actix_web::{web, Responder};
 use serde::Deserialize;
-use teloxide::types::{Me, ReplyMarkup, ReplyParameters};
+use teloxide::types::{ReplyMarkup, ReplyParameters};
+use teloxide::ApiError;
 
-use crate::server::{routes::check_if_message_exists, FILES, MESSAGES, RESPONSES};
+use crate::server::{routes::check_if_message_exists, State};
 
-use super::{get_raw_multipart_fields, make_telegram_result, BodyChatId};
+use super::{
+    check_limits, check_message_thread, check_reply_markup, get_raw_multipart_fields,
+    make_telegram_result, put_in_thread, BodyChatId,
+};
 
-pub async fn send_sticker(mut payload: Multipart, me: web::Data<Me>) -> impl Responder {
+pub async fn send_sticker(
+    mut payload: Multipart,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
     let (fields, attachments) = get_raw_multipart_fields(&mut payload).await;
     let body =
         SendMessageStickerBody::serialize_raw_fields(&fields, &attachments, FileType::Sticker)
             .unwrap();
+    let mut lock = state.lock().unwrap();
+    check_limits!(check_reply_markup(
+        &lock.api_limits,
+        body.reply_markup.as_ref()
+    ));
+    let thread = check_message_thread!(lock, body.chat_id.id(), body.message_thread_id);
     let chat = body.chat_id.chat();
 
     let mut message = MockMessageSticker::new().chat(chat);
-    message.from = Some(me.user.clone());
+    message.from = Some(lock.me.user.clone());
     message.has_protected_content = body.protect_content.unwrap_or(false);
     message.emoji = body.emoji.clone();
 
@@ -31,24 +44,35 @@ pub async fn send_sticker(mut payload: Multipart, me: web::Data<Me>) -> impl Res
     // ain't nobody testing that
 
     if let Some(reply_parameters) = &body.reply_parameters {
-        check_if_message_exists!(reply_parameters.message_id.0);
-        let reply_to_message = MESSAGES.get_message(reply_parameters.message_id.0).unwrap();
+        check_if_message_exists!(
+            lock,
+            body.chat_id.id(),
+            reply_parameters.message_id.0,
+            ApiError::MessageToReplyNotFound
+        );
+        let reply_to_message = lock
+            .messages
+            .get_message(body.chat_id.id(), reply_parameters.message_id.0)
+            .unwrap();
         message.reply_to_message = Some(Box::new(reply_to_message.clone()));
     }
     if let Some(ReplyMarkup::InlineKeyboard(markup)) = body.reply_markup.clone() {
         message.reply_markup = Some(markup);
     }
 
-    let last_id = MESSAGES.max_message_id();
-    let message = MESSAGES.add_message(message.id(last_id + 1).build());
+    let last_id = lock.messages.max_message_id(body.chat_id.id());
+    let message = lock.messages.add_message(put_in_thread(
+        message.id(last_id + 1).build(),
+        thread.as_ref(),
+    ));
 
-    FILES.lock().unwrap().push(teloxide::types::File {
-        meta: message.sticker().unwrap().file.clone(),
-        path: body.file_name.to_owned(),
-    });
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock.sent_messages.push(message.clone());
-    responses_lock
+    lock.files.add_file(
+        message.sticker().unwrap().file.clone(),
+        &body.file_name,
+        Some(body.file_data.clone()),
+    );
+    lock.responses.sent_messages.push(message.clone());
+    lock.responses
         .sent_messages_sticker
         .push(SentMessageSticker {
             message: message.clone(),
@@ -62,7 +86,7 @@ pub async fn send_sticker(mut payload: Multipart, me: web::Data<Me>) -> impl Res
 pub struct SendMessageStickerBody {
     pub chat_id: BodyChatId,
     pub file_name: String,
-    pub file_data: String,
+    pub file_data: Vec<u8>,
     pub message_thread_id: Option<i64>,
     pub emoji: Option<String>,
     pub disable_notification: Option<bool>,
@@ -7,33 +7,55 @@ use crate::{
     MockMessageVideoNote,
 };
 use std::collections::HashMap;
+use std::sync::Mutex;
 
 use crate::proc_macros::SerializeRawFields;
 use actix_multipart::Multipart;
+use actix_web::web;
 use actix_web::Responder;
-use actix_web::{error::ErrorBadRequest, web};
 use rand::distributions::{Alphanumeric, DistString};
 use serde::Deserialize;
-use teloxide::types::{Me, ReplyMarkup, ReplyParameters, Seconds};
+use teloxide::types::{ReplyMarkup, ReplyParameters, Seconds};
+use teloxide::ApiError;
 
-use crate::server::{routes::check_if_message_exists, FILES, MESSAGES, RESPONSES};
+use crate::server::{routes::check_if_message_exists, State};
 
-use super::{get_raw_multipart_fields, make_telegram_result, BodyChatId};
+use super::{
+    check_limits, check_message_thread, check_reply_markup, get_raw_multipart_fields,
+    make_telegram_result, put_in_thread, BodyChatId,
+};
 
-pub async fn send_video_note(mut payload: Multipart, me: web::Data<Me>) -> impl Responder {
+pub async fn send_video_note(
+    mut payload: Multipart,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
     let (fields, attachments) = get_raw_multipart_fields(&mut payload).await;
     let body =
         SendMessageVideoNoteBody::serialize_raw_fields(&fields, &attachments, FileType::Voice)
             .unwrap();
+    let mut lock = state.lock().unwrap();
+    check_limits!(check_reply_markup(
+        &lock.api_limits,
+        body.reply_markup.as_ref()
+    ));
+    let thread = check_message_thread!(lock, body.chat_id.id(), body.message_thread_id);
     let chat = body.chat_id.chat();
 
     let mut message = MockMessageVideoNote::new().chat(chat.clone());
-    message.from = Some(me.user.clone());
+    message.from = Some(lock.me.user.clone());
     message.has_protected_content = body.protect_content.unwrap_or(false);
 
     if let Some(reply_parameters) = &body.reply_parameters {
-        check_if_message_exists!(reply_parameters.message_id.0);
-        let reply_to_message = MESSAGES.get_message(reply_parameters.message_id.0).unwrap();
+        check_if_message_exists!(
+            lock,
+            body.chat_id.id(),
+            reply_parameters.message_id.0,
+            ApiError::MessageToReplyNotFound
+        );
+        let reply_to_message = lock
+            .messages
+            .get_message(body.chat_id.id(), reply_parameters.message_id.0)
+            .unwrap();
         message.reply_to_message = Some(Box::new(reply_to_message.clone()));
     }
     if let Some(ReplyMarkup::InlineKeyboard(markup)) = body.reply_markup.clone() {
@@ -47,18 +69,21 @@ pub async fn send_video_note(mut payload: Multipart, me: web::Data<Me>) -> impl
     message.file_unique_id = file_unique_id.clone();
     message.duration = body.duration.unwrap_or(Seconds::from_seconds(0));
     message.length = body.length.unwrap_or(100);
-    message.file_size = body.file_data.bytes().len() as u32;
+    message.file_size = body.file_data.len() as u32;
 
-    let last_id = MESSAGES.max_message_id();
-    let message = MESSAGES.add_message(message.id(last_id + 1).build());
+    let last_id = lock.messages.max_message_id(body.chat_id.id());
+    let message = lock.messages.add_message(put_in_thread(
+        message.id(last_id + 1).build(),
+        thread.as_ref(),
+    ));
 
-    FILES.lock().unwrap().push(teloxide::types::File {
-        meta: message.video_note().unwrap().file.clone(),
-        path: body.file_name.to_owned(),
-    });
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock.sent_messages.push(message.clone());
-    responses_lock
+    lock.files.add_file(
+        message.video_note().unwrap().file.clone(),
+        &body.file_name,
+        Some(body.file_data.clone()),
+    );
+    lock.responses.sent_messages.push(message.clone());
+    lock.responses
         .sent_messages_video_note
         .push(SentMessageVideoNote {
             message: message.clone(),
@@ -73,7 +98,7 @@ pub struct SendMessageVideoNoteBody {
     pub chat_id: BodyChatId,
     pub message_thread_id: Option<i64>,
     pub file_name: String,
-    pub file_data: String,
+    pub file_data: Vec<u8>,
     pub duration: Option<Seconds>,
     pub length: Option<u32>,
     pub disable_notification: Option<bool>,
@@ -0,0 +1,61 @@
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
+use serde::Deserialize;
+use teloxide::types::{ChatMember, ChatMemberKind};
+use teloxide::ApiError;
+
+use crate::server::State;
+
+use super::{check_limits, check_my_right, make_telegram_error, make_telegram_result, BodyChatId};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SetChatAdministratorCustomTitleBody {
+    pub chat_id: BodyChatId,
+    pub user_id: u64,
+    pub custom_title: String,
+}
+
+pub async fn set_chat_administrator_custom_title(
+    body: web::Json<SetChatAdministratorCustomTitleBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    let chat_id = body.chat_id.id();
+    check_limits!(check_my_right(
+        &lock,
+        chat_id,
+        |rights| rights.can_promote_members,
+        ApiError::Unknown("Bad Request: not enough rights".to_string())
+    ));
+    let Some(ChatMember {
+        user,
+        kind: ChatMemberKind::Administrator(mut administrator),
+    }) = lock.chat_members.get_member(chat_id, body.user_id)
+    else {
+        return make_telegram_error(ApiError::Unknown(
+            "Bad Request: user is not an administrator".to_string(),
+        ));
+    };
+    // Only the administrators that the bot promoted can be changed by it
+    if !administrator.can_be_edited {
+        return make_telegram_error(ApiError::Unknown(
+            "Bad Request: not enough rights to change custom title of the user".to_string(),
+        ));
+    }
+
+    // An empty title removes it
+    administrator.custom_title = Some(body.custom_title.clone()).filter(|title| !title.is_empty());
+    lock.chat_members.set_member(
+        chat_id,
+        ChatMember {
+            user,
+            kind: ChatMemberKind::Administrator(administrator),
+        },
+    );
+    lock.responses
+        .set_chat_administrator_custom_titles
+        .push(body.into_inner());
+
+    make_telegram_result(true)
+}
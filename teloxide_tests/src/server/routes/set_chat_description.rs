@@ -0,0 +1,51 @@
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
+use serde::Deserialize;
+use teloxide::types::ChatKind;
+use teloxide::ApiError;
+
+use crate::server::State;
+
+use super::{
+    check_if_chat_exists, check_limits, check_my_right, make_telegram_error, make_telegram_result,
+    public_chat_mut, BodyChatId,
+};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SetChatDescriptionBody {
+    pub chat_id: BodyChatId,
+    pub description: Option<String>,
+}
+
+pub async fn set_chat_description(
+    body: web::Json<SetChatDescriptionBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    let chat_id = body.chat_id.id();
+    check_if_chat_exists!(lock, chat_id);
+    check_limits!(check_my_right(
+        &lock,
+        chat_id,
+        |rights| rights.can_change_info,
+        ApiError::Unknown("Bad Request: CHAT_ADMIN_REQUIRED".to_string())
+    ));
+
+    let chat = match public_chat_mut(&mut lock, chat_id) {
+        Ok(chat) => chat,
+        Err(error) => return make_telegram_error(error),
+    };
+    let ChatKind::Public(ref mut public_chat) = chat.kind else {
+        unreachable!("The chat is public");
+    };
+    // No description or an empty one removes it
+    let description = body.description.clone().filter(|d| !d.is_empty());
+    if public_chat.description == description {
+        return make_telegram_error(ApiError::ChatDescriptionIsNotModified);
+    }
+    public_chat.description = description;
+    lock.responses.set_chat_descriptions.push(body.into_inner());
+
+    make_telegram_result(true)
+}
@@ -0,0 +1,55 @@
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
+use serde::Deserialize;
+use teloxide::types::{ChatKind, ChatPermissions, PublicChatKind};
+use teloxide::ApiError;
+
+use crate::server::State;
+
+use super::{
+    check_if_chat_exists, check_limits, check_my_right, make_telegram_error, make_telegram_result,
+    BodyChatId,
+};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SetChatPermissionsBody {
+    pub chat_id: BodyChatId,
+    pub permissions: ChatPermissions,
+    pub use_independent_chat_permissions: Option<bool>,
+}
+
+pub async fn set_chat_permissions(
+    body: web::Json<SetChatPermissionsBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    let chat_id = body.chat_id.id();
+    check_if_chat_exists!(lock, chat_id);
+    check_limits!(check_my_right(
+        &lock,
+        chat_id,
+        |rights| rights.can_restrict_members,
+        ApiError::NotEnoughRightsToChangeChatPermissions
+    ));
+
+    let chat = lock.chats.get_chat_mut(chat_id).unwrap();
+    // Only the groups and supergroups have permissions
+    let permissions = match &mut chat.kind {
+        ChatKind::Public(public_chat) => match &mut public_chat.kind {
+            PublicChatKind::Group(group) => Some(&mut group.permissions),
+            PublicChatKind::Supergroup(supergroup) => Some(&mut supergroup.permissions),
+            PublicChatKind::Channel(_) => None,
+        },
+        ChatKind::Private(_) => None,
+    };
+    let Some(permissions) = permissions else {
+        return make_telegram_error(ApiError::Unknown(
+            "Bad Request: method is available for supergroup and group chats only".to_string(),
+        ));
+    };
+    *permissions = Some(body.permissions);
+    lock.responses.set_chat_permissions.push(body.into_inner());
+
+    make_telegram_result(true)
+}
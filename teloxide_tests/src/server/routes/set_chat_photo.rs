@@ -0,0 +1,74 @@
+use crate::server::routes::{FileType, SerializeRawFields};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::proc_macros::SerializeRawFields;
+use actix_multipart::Multipart;
+use actix_web::{web, Responder};
+use rand::distributions::{Alphanumeric, DistString};
+use serde::Deserialize;
+use teloxide::types::{ChatPhoto, FileMeta};
+use teloxide::ApiError;
+
+use crate::server::{SetChatPhoto, State};
+
+use super::{
+    check_if_chat_exists, check_limits, check_my_right, get_raw_multipart_fields,
+    make_telegram_error, make_telegram_result, public_chat_mut, Attachment, BodyChatId,
+};
+
+pub async fn set_chat_photo(
+    mut payload: Multipart,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let (fields, attachments) = get_raw_multipart_fields(&mut payload).await;
+    let body =
+        SetChatPhotoBody::serialize_raw_fields(&fields, &attachments, FileType::Photo).unwrap();
+    let mut lock = state.lock().unwrap();
+    let chat_id = body.chat_id.id();
+    check_if_chat_exists!(lock, chat_id);
+    check_limits!(check_my_right(
+        &lock,
+        chat_id,
+        |rights| rights.can_change_info,
+        ApiError::Unknown("Bad Request: CHAT_ADMIN_REQUIRED".to_string())
+    ));
+    if let Err(error) = public_chat_mut(&mut lock, chat_id) {
+        return make_telegram_error(error);
+    }
+
+    // Telegram makes a small and a big version of the photo, here they are the same file
+    let mut new_file = || {
+        let meta = FileMeta {
+            id: Alphanumeric.sample_string(&mut rand::thread_rng(), 16),
+            unique_id: Alphanumeric.sample_string(&mut rand::thread_rng(), 8),
+            size: body.file_data.len() as u32,
+        };
+        lock.files
+            .add_file(meta, &body.file_name, Some(body.file_data.clone()))
+            .meta
+    };
+    let small = new_file();
+    let big = new_file();
+    let photo = ChatPhoto {
+        small_file_id: small.id,
+        small_file_unique_id: small.unique_id,
+        big_file_id: big.id,
+        big_file_unique_id: big.unique_id,
+    };
+
+    lock.chats.get_chat_mut(chat_id).unwrap().photo = Some(photo.clone());
+    lock.responses.set_chat_photos.push(SetChatPhoto {
+        photo,
+        bot_request: body,
+    });
+
+    make_telegram_result(true)
+}
+
+#[derive(Debug, Clone, Deserialize, SerializeRawFields)]
+pub struct SetChatPhotoBody {
+    pub chat_id: BodyChatId,
+    pub file_name: String,
+    pub file_data: Vec<u8>,
+}
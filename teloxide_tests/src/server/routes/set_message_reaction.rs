@@ -1,12 +1,12 @@
+use std::sync::Mutex;
+
 use crate::server::SetMessageReaction;
-use actix_web::error::ErrorBadRequest;
 use actix_web::{web, Responder};
 use serde::Deserialize;
-use teloxide::types::
-    ReactionType
-;
+use teloxide::types::ReactionType;
+use teloxide::ApiError;
 
-use crate::server::{routes::check_if_message_exists, MESSAGES, RESPONSES};
+use crate::server::{routes::check_if_message_exists, State};
 
 use super::{make_telegram_result, BodyChatId};
 
@@ -20,14 +20,21 @@ pub struct SetMessageReactionBody {
 
 pub async fn set_message_reaction(
     body: web::Json<SetMessageReactionBody>,
+    state: web::Data<Mutex<State>>,
 ) -> impl Responder {
-    check_if_message_exists!(body.message_id);
-    
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock.set_message_reaction.push(SetMessageReaction {
-        bot_request: body.into_inner(),
-    });
+    let mut lock = state.lock().unwrap();
+    check_if_message_exists!(
+        lock,
+        body.chat_id.id(),
+        body.message_id,
+        ApiError::Unknown("Bad Request: message to react not found".to_string())
+    );
+
+    lock.responses
+        .set_message_reaction
+        .push(SetMessageReaction {
+            bot_request: body.into_inner(),
+        });
 
     make_telegram_result(true)
 }
-
@@ -0,0 +1,61 @@
+use std::sync::Mutex;
+
+use actix_web::{web, Responder};
+use serde::Deserialize;
+use teloxide::types::ReplyMarkup;
+use teloxide::ApiError;
+
+use crate::server::{State, StoppedPoll};
+
+use super::{
+    check_if_message_exists, check_limits, check_reply_markup, make_telegram_error,
+    make_telegram_result, BodyChatId,
+};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct StopPollBody {
+    pub chat_id: BodyChatId,
+    pub message_id: i32,
+    pub business_connection_id: Option<String>,
+    #[serde(default, with = "crate::server::routes::reply_markup_deserialize")]
+    pub reply_markup: Option<ReplyMarkup>,
+}
+
+pub async fn stop_poll(
+    body: web::Json<StopPollBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    check_limits!(check_reply_markup(
+        &lock.api_limits,
+        body.reply_markup.as_ref()
+    ));
+    let chat_id = body.chat_id.id();
+    check_if_message_exists!(
+        lock,
+        chat_id,
+        body.message_id,
+        ApiError::MessageWithPollNotFound
+    );
+    let message = lock.messages.get_message(chat_id, body.message_id).unwrap();
+    let Some(poll) = message.poll() else {
+        return make_telegram_error(ApiError::MessageIsNotAPoll);
+    };
+    if poll.is_closed {
+        return make_telegram_error(ApiError::PollHasAlreadyClosed);
+    }
+
+    let mut poll = poll.clone();
+    poll.is_closed = true;
+    lock.messages
+        .edit_message(chat_id, body.message_id, "poll", &poll);
+    lock.messages
+        .edit_message_reply_markup(chat_id, body.message_id, body.reply_markup.clone());
+
+    lock.responses.stopped_polls.push(StoppedPoll {
+        poll: poll.clone(),
+        bot_request: body.into_inner(),
+    });
+
+    make_telegram_result(poll)
+}
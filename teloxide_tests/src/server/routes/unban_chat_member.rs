@@ -1,10 +1,14 @@
+use std::sync::Mutex;
+
 use actix_web::{web, Responder};
 use serde::Deserialize;
+use teloxide::types::{ChatMember, ChatMemberKind};
+use teloxide::ApiError;
 
 use crate::server::routes::make_telegram_result;
-use crate::server::RESPONSES;
+use crate::server::State;
 
-use super::BodyChatId;
+use super::{check_limits, check_my_right, BodyChatId};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct UnbanChatMemberBody {
@@ -13,10 +17,38 @@ pub struct UnbanChatMemberBody {
     pub only_if_banned: Option<bool>,
 }
 
-pub async fn unban_chat_member(body: web::Json<UnbanChatMemberBody>) -> impl Responder {
-    // Idk what to verify here
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock.unbanned_chat_members.push(body.into_inner());
+pub async fn unban_chat_member(
+    body: web::Json<UnbanChatMemberBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    let chat_id = body.chat_id.id();
+    check_limits!(check_my_right(
+        &lock,
+        chat_id,
+        |rights| rights.can_restrict_members,
+        ApiError::NotEnoughRightsToRestrict
+    ));
+    if let Some(member) = lock.chat_members.get_member(chat_id, body.user_id) {
+        // Unbanning a user that is still in the chat removes them from it, unless the
+        // bot asked to only unban the banned users
+        let is_removed = match member.kind {
+            ChatMemberKind::Banned(_) => true,
+            ChatMemberKind::Owner(_) | ChatMemberKind::Administrator(_) => false,
+            _ => !body.only_if_banned.unwrap_or(false),
+        };
+        if is_removed {
+            let user = member.user.clone();
+            lock.chat_members.set_member(
+                chat_id,
+                ChatMember {
+                    user,
+                    kind: ChatMemberKind::Left,
+                },
+            );
+        }
+    }
+    lock.responses.unbanned_chat_members.push(body.into_inner());
 
     make_telegram_result(true)
 }
@@ -0,0 +1,30 @@
+use std::sync::Mutex;
+
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+
+use crate::{mock_bot::UnsupportedMethods, server::State};
+
+// Every method that doesn't have its own route ends up here
+pub async fn unknown_method(
+    path: web::Path<(String, String)>,
+    state: web::Data<Mutex<State>>,
+) -> HttpResponse {
+    let mut lock = state.lock().unwrap();
+    let method = path.1.clone();
+    lock.responses.unsupported_methods.push(method.clone());
+
+    match &lock.unsupported_methods {
+        UnsupportedMethods::Stub(result) => HttpResponse::Ok().json(json!({
+            "ok": true,
+            "result": result,
+        })),
+        _ => HttpResponse::NotFound().json(json!({
+            "ok": false,
+            "error_code": 404,
+            "description": format!(
+                "Not Found: method {method} is not supported by teloxide_tests"
+            ),
+        })),
+    }
+}
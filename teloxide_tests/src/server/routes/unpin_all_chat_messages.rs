@@ -1,19 +1,31 @@
+use std::sync::Mutex;
+
 use actix_web::{web, Responder};
 use serde::Deserialize;
+use teloxide::ApiError;
 
 use crate::server::routes::make_telegram_result;
-use crate::server::RESPONSES;
+use crate::server::State;
 
-use super::BodyChatId;
+use super::{check_limits, check_my_right, BodyChatId};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct UnpinAllChatMessagesBody {
     pub chat_id: BodyChatId,
 }
 
-pub async fn unpin_all_chat_messages(body: web::Json<UnpinAllChatMessagesBody>) -> impl Responder {
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock
+pub async fn unpin_all_chat_messages(
+    body: web::Json<UnpinAllChatMessagesBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    check_limits!(check_my_right(
+        &lock,
+        body.chat_id.id(),
+        |rights| rights.can_pin_messages.unwrap_or(false),
+        ApiError::NotEnoughRightsToManagePins
+    ));
+    lock.responses
         .unpinned_all_chat_messages
         .push(body.into_inner());
 
@@ -1,11 +1,13 @@
-use actix_web::error::ErrorBadRequest;
+use std::sync::Mutex;
+
 use actix_web::{web, Responder};
 use serde::Deserialize;
+use teloxide::ApiError;
 
 use crate::server::routes::make_telegram_result;
-use crate::server::{MESSAGES, RESPONSES};
+use crate::server::State;
 
-use super::{check_if_message_exists, BodyChatId};
+use super::{check_if_message_exists, check_limits, check_my_right, BodyChatId};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct UnpinChatMessageBody {
@@ -13,12 +15,26 @@ pub struct UnpinChatMessageBody {
     pub message_id: Option<i32>,
 }
 
-pub async fn unpin_chat_message(body: web::Json<UnpinChatMessageBody>) -> impl Responder {
+pub async fn unpin_chat_message(
+    body: web::Json<UnpinChatMessageBody>,
+    state: web::Data<Mutex<State>>,
+) -> impl Responder {
+    let mut lock = state.lock().unwrap();
+    check_limits!(check_my_right(
+        &lock,
+        body.chat_id.id(),
+        |rights| rights.can_pin_messages.unwrap_or(false),
+        ApiError::NotEnoughRightsToManagePins
+    ));
     if let Some(message_id) = body.message_id {
-        check_if_message_exists!(message_id);
+        check_if_message_exists!(
+            lock,
+            body.chat_id.id(),
+            message_id,
+            ApiError::Unknown("Bad Request: message to unpin not found".to_string())
+        );
     }
-    let mut responses_lock = RESPONSES.lock().unwrap();
-    responses_lock
+    lock.responses
         .unpinned_chat_messages
         .push(body.into_inner());
 
@@ -1,18 +1,25 @@
 use super::*;
 use crate::dataset::*;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use teloxide::dispatching::{HandlerExt, UpdateHandler};
 use teloxide::dptree::case;
 use teloxide::net::Download;
 use teloxide::payloads::{
-    BanChatMemberSetters, CopyMessageSetters, SendPhotoSetters, SendPollSetters,
+    AnswerInlineQuerySetters, BanChatMemberSetters, CopyMessageSetters, EditForumTopicSetters,
+    PromoteChatMemberSetters, SendMessageSetters, SendPhotoSetters, SendPollSetters,
+    SetChatDescriptionSetters,
 };
 use teloxide::requests::Requester;
 use teloxide::types::{
-    ChatAction, ChatPermissions, DiceEmoji, InlineKeyboardButton, InlineKeyboardMarkup, InputFile,
-    InputMedia, InputMediaAudio, InputMediaDocument, InputMediaPhoto, InputMediaVideo,
-    LinkPreviewOptions, Message, MessageEntity, PollOption, PollType, ReactionType,
-    ReplyParameters, Seconds, Update,
+    BotCommand, ChatAction, ChatPermissions, ChosenInlineResult, DiceEmoji, InlineKeyboardButton,
+    InlineKeyboardMarkup, InlineQuery, InlineQueryResult, InlineQueryResultArticle,
+    InlineQueryResultsButton, InlineQueryResultsButtonKind, InputFile, InputMedia, InputMediaAudio,
+    InputMediaDocument, InputMediaPhoto, InputMediaVideo, InputMessageContent,
+    InputMessageContentText, LinkPreviewOptions, Message, MessageEntity, MessageId, ParseMode,
+    PollOption, PollType, ReactionType, ReplyParameters, Seconds, ThreadId, Update, UpdateKind,
 };
 use teloxide::{
     dispatching::{
@@ -22,6 +29,7 @@ use teloxide::{
     dptree::deps,
     macros::BotCommands,
     prelude::*,
+    ApiError, RequestError,
 };
 
 //
@@ -253,11 +261,11 @@ async fn handler(
                 gotten_document.meta.unique_id
                     == document_message.document().unwrap().file.unique_id
             );
-            let mut dest = tokio::fs::File::create("test.txt").await?;
+            let mut content = Vec::new();
 
-            bot.download_file(&gotten_document.path, &mut dest).await?;
-            assert!(tokio::fs::read_to_string("test.txt").await.is_ok());
-            tokio::fs::remove_file("test.txt").await?;
+            bot.download_file(&gotten_document.path, &mut content)
+                .await?;
+            assert_eq!(content, b"somedata");
         }
         AllCommands::Animation => {
             let animation = InputFile::memory("somedata".to_string()).file_name("animation.mp4");
@@ -396,6 +404,51 @@ async fn callback_handler(
     bot.answer_callback_query(call.id)
         .text(call.data.unwrap())
         .await?;
+    if let Some(inline_message_id) = call.inline_message_id {
+        bot.edit_message_reply_markup_inline(inline_message_id)
+            .reply_markup(InlineKeyboardMarkup::new(vec![vec![
+                InlineKeyboardButton::callback("edited", "edited"),
+            ]]))
+            .await?;
+    }
+    Ok(())
+}
+
+async fn inline_query_handler(
+    bot: Bot,
+    query: InlineQuery,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let result = InlineQueryResultArticle::new(
+        "1",
+        query.query.clone(),
+        InputMessageContent::Text(InputMessageContentText::new(query.query)),
+    );
+    bot.answer_inline_query(query.id, vec![result.into()])
+        .cache_time(10)
+        .is_personal(true)
+        .next_offset("next")
+        .button(InlineQueryResultsButton {
+            text: "Start".to_string(),
+            kind: InlineQueryResultsButtonKind::StartParameter("start".to_string()),
+        })
+        .await?;
+    Ok(())
+}
+
+async fn chosen_inline_result_handler(
+    bot: Bot,
+    result: ChosenInlineResult,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    match result.inline_message_id {
+        Some(inline_message_id) => {
+            bot.edit_message_text_inline(inline_message_id, "edited")
+                .await?;
+        }
+        None => {
+            bot.send_message(result.from.id, format!("Chosen {}", result.result_id))
+                .await?;
+        }
+    }
     Ok(())
 }
 
@@ -408,6 +461,8 @@ fn get_schema() -> UpdateHandler<Box<dyn std::error::Error + Send + Sync + 'stat
         )
         .branch(Update::filter_message().endpoint(handler))
         .branch(Update::filter_callback_query().endpoint(callback_handler))
+        .branch(Update::filter_inline_query().endpoint(inline_query_handler))
+        .branch(Update::filter_chosen_inline_result().endpoint(chosen_inline_result_handler))
 }
 
 #[tokio::test]
@@ -421,6 +476,183 @@ async fn test_echo() {
     assert_eq!(last_response.text(), Some("/echo echo"));
 }
 
+#[tokio::test]
+async fn test_bots_in_parallel() {
+    let first_bot = MockBot::new(MockMessageText::new().text("/echo first"), get_schema());
+    let second_bot = MockBot::new(MockMessageText::new().text("/echo second"), get_schema());
+
+    assert_ne!(first_bot.port, second_bot.port);
+
+    tokio::join!(first_bot.dispatch(), second_bot.dispatch());
+
+    let first_responses = first_bot.get_responses();
+    let second_responses = second_bot.get_responses();
+
+    assert_eq!(first_responses.sent_messages.len(), 1);
+    assert_eq!(second_responses.sent_messages.len(), 1);
+    assert_eq!(first_responses.sent_messages[0].text(), Some("/echo first"));
+    assert_eq!(
+        second_responses.sent_messages[0].text(),
+        Some("/echo second")
+    );
+}
+
+#[tokio::test]
+async fn test_multiple_dispatches() {
+    let bot = MockBot::new(MockMessageText::new().text("/echo first"), get_schema());
+
+    bot.dispatch().await;
+    assert_eq!(bot.get_responses().sent_messages.len(), 1);
+
+    bot.update(MockMessageText::new().text("/echo second"));
+    bot.me(MockMe::new().first_name("Second"));
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    // Responses are erased between dispatches
+    assert_eq!(responses.sent_messages.len(), 1);
+    let last_response = responses.sent_messages.last().unwrap();
+    assert_eq!(last_response.text(), Some("/echo second"));
+    assert_eq!(last_response.from.as_ref().unwrap().first_name, "Second");
+}
+
+#[tokio::test]
+async fn test_dispatcher() {
+    let bot = MockBot::new(
+        vec![
+            MockMessageText::new().text("/echo 1"),
+            MockMessageText::new().text("/echo 2"),
+            MockMessageText::new().text("/echo 3"),
+        ],
+        get_schema(),
+    );
+    bot.dispatcher(|builder| builder);
+
+    bot.dispatch().await;
+
+    // The updates from one chat are handled one after another, like in production
+    let texts: Vec<_> = bot
+        .get_responses()
+        .sent_messages
+        .iter()
+        .map(|message| message.text().unwrap().to_string())
+        .collect();
+    assert_eq!(texts, vec!["/echo 1", "/echo 2", "/echo 3"]);
+    // The dispatcher gets `me` when it starts, but that isn't a request of the bot
+    let methods: Vec<_> = bot
+        .get_responses()
+        .requests
+        .iter()
+        .map(|request| request.method.clone())
+        .collect();
+    assert_eq!(methods, vec!["SendMessage"; 3]);
+}
+
+#[tokio::test]
+async fn test_dispatcher_get_me() {
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(|bot: Bot, message: Message| async move {
+            let text = match bot.get_me().await {
+                Ok(me) => me.username().to_string(),
+                Err(error) => format!("{:?}", error),
+            };
+            bot.send_message(message.chat.id, text).await?;
+            Ok::<(), Box<dyn std::error::Error + Send + Sync + 'static>>(())
+        }),
+    );
+    bot.dispatcher(|builder| builder);
+    // The startup call of the dispatcher doesn't use up the error
+    bot.fail_next("getMe", ApiError::BotBlocked);
+
+    bot.dispatch_and_check_last_text("Api(BotBlocked)").await;
+    assert_eq!(bot.get_responses().requests_by_method("getMe").len(), 1);
+}
+
+#[tokio::test]
+async fn test_dispatcher_default_handler() {
+    let handler_tree =
+        dptree::entry().branch(Update::filter_callback_query().endpoint(callback_handler));
+    let bot = MockBot::new(MockMessageText::new().text("unhandled"), handler_tree);
+    bot.unhandled_updates(FailurePolicy::Ignore);
+    let unhandled = Arc::new(AtomicBool::new(false));
+    let unhandled_clone = unhandled.clone();
+    bot.dispatcher(move |builder| {
+        let unhandled = unhandled_clone.clone();
+        builder.default_handler(move |_update| {
+            let unhandled = unhandled.clone();
+            async move { unhandled.store(true, Ordering::SeqCst) }
+        })
+    });
+
+    bot.dispatch().await;
+
+    assert!(unhandled.load(Ordering::SeqCst));
+    assert!(bot.get_responses().sent_messages.is_empty());
+}
+
+#[tokio::test]
+#[should_panic]
+async fn test_unhandled_update_panics() {
+    let handler_tree =
+        dptree::entry().branch(Update::filter_callback_query().endpoint(callback_handler));
+    let bot = MockBot::new(MockMessageText::new().text("unhandled"), handler_tree);
+
+    bot.dispatch().await;
+}
+
+#[tokio::test]
+async fn test_collect_unhandled_updates() {
+    let handler_tree =
+        dptree::entry().branch(Update::filter_callback_query().endpoint(callback_handler));
+    let bot = MockBot::new(MockMessageText::new().text("unhandled"), handler_tree);
+    bot.unhandled_updates(FailurePolicy::Collect);
+
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    assert_eq!(responses.unhandled_updates.len(), 1);
+    let UpdateKind::Message(message) = &responses.unhandled_updates[0].kind else {
+        panic!("The unhandled update is not a message!");
+    };
+    assert_eq!(message.text(), Some("unhandled"));
+}
+
+fn get_failing_schema() -> UpdateHandler<Box<dyn std::error::Error + Send + Sync + 'static>> {
+    dptree::entry().branch(
+        Update::filter_message().endpoint(|msg: Message| async move {
+            Err(format!("Can't handle {}", msg.text().unwrap()).into())
+        }),
+    )
+}
+
+#[tokio::test]
+async fn test_collect_handler_errors() {
+    let bot = MockBot::new(MockMessageText::new().text("this"), get_failing_schema());
+    bot.handler_errors(FailurePolicy::Collect);
+
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    assert_eq!(responses.handler_errors.len(), 1);
+    assert_eq!(responses.handler_errors[0].error, "\"Can't handle this\"");
+    assert_eq!(
+        responses.handler_errors[0].update.from().unwrap().id,
+        UserId(MockUser::ID)
+    );
+}
+
+#[tokio::test]
+async fn test_ignore_handler_errors() {
+    let bot = MockBot::new(MockMessageText::new().text("this"), get_failing_schema());
+    bot.handler_errors(FailurePolicy::Ignore);
+    bot.dispatcher(|builder| builder);
+
+    bot.dispatch().await;
+
+    assert!(bot.get_responses().handler_errors.is_empty());
+}
+
 #[tokio::test]
 #[should_panic]
 async fn test_panic() {
@@ -452,7 +684,7 @@ async fn test_send_photo() {
     );
     assert_eq!(last_sent_message.caption_entities().unwrap().len(), 1);
     assert_eq!(last_sent_photo.bot_request.file_name, "test.jpg");
-    assert_eq!(last_sent_photo.bot_request.file_data, "somedata");
+    assert_eq!(last_sent_photo.bot_request.file_data, b"somedata");
 }
 
 #[tokio::test]
@@ -470,7 +702,7 @@ async fn test_send_video() {
     );
     assert_eq!(last_sent_message.caption_entities().unwrap().len(), 1);
     assert_eq!(last_sent_video.bot_request.file_name, "test.mp4");
-    assert_eq!(last_sent_video.bot_request.file_data, "somedata");
+    assert_eq!(last_sent_video.bot_request.file_data, b"somedata");
 }
 
 #[tokio::test]
@@ -488,7 +720,7 @@ async fn test_send_audio() {
     );
     assert_eq!(last_sent_message.caption_entities().unwrap().len(), 1);
     assert_eq!(last_sent_audio.bot_request.file_name, "test.mp3");
-    assert_eq!(last_sent_audio.bot_request.file_data, "somedata");
+    assert_eq!(last_sent_audio.bot_request.file_data, b"somedata");
 }
 
 #[tokio::test]
@@ -506,7 +738,7 @@ async fn test_send_voice() {
     );
     assert_eq!(last_sent_message.caption_entities().unwrap().len(), 1);
     assert_eq!(last_sent_voice.bot_request.file_name, "test.mp3");
-    assert_eq!(last_sent_voice.bot_request.file_data, "somedata");
+    assert_eq!(last_sent_voice.bot_request.file_data, b"somedata");
 }
 
 #[tokio::test]
@@ -522,7 +754,7 @@ async fn test_send_video_note() {
         Some("/videonote")
     );
     assert_eq!(last_sent_video_note.bot_request.file_name, "test.mp4");
-    assert_eq!(last_sent_video_note.bot_request.file_data, "somedata");
+    assert_eq!(last_sent_video_note.bot_request.file_data, b"somedata");
 }
 
 #[tokio::test]
@@ -875,6 +1107,138 @@ async fn test_answer_callback_query() {
     assert_eq!(answered_callback.text, Some("test".to_string()));
 }
 
+#[tokio::test]
+async fn test_answer_inline_query() {
+    let bot = MockBot::new(MockInlineQuery::new().query("some query"), get_schema());
+
+    bot.dispatch().await;
+
+    let answered_query = bot.get_responses().answered_inline_queries.pop().unwrap();
+
+    assert_eq!(answered_query.inline_query_id, MockInlineQuery::ID);
+    assert_eq!(answered_query.cache_time, Some(10));
+    assert_eq!(answered_query.is_personal, Some(true));
+    assert_eq!(answered_query.next_offset, Some("next".to_string()));
+    assert_eq!(
+        answered_query.button.unwrap().kind,
+        InlineQueryResultsButtonKind::StartParameter("start".to_string())
+    );
+    let InlineQueryResult::Article(article) = &answered_query.results[0] else {
+        panic!("The result is not an article!");
+    };
+    assert_eq!(article.title, "some query");
+}
+
+#[tokio::test]
+async fn test_chosen_inline_result() {
+    let bot = MockBot::new(
+        MockChosenInlineResult::new().result_id("result"),
+        get_schema(),
+    );
+
+    bot.dispatch_and_check_last_text("Chosen result").await;
+}
+
+#[tokio::test]
+async fn test_edit_inline_message_text() {
+    let bot = MockBot::new(MockInlineQuery::new().query("some query"), get_schema());
+    bot.dispatch().await;
+
+    bot.update(
+        MockChosenInlineResult::new()
+            .result_id("1")
+            .inline_message_id("inline_id"),
+    );
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    let edited_message = responses.edited_messages_text.last().unwrap();
+    assert_eq!(
+        edited_message.bot_request.inline_message_id,
+        Some("inline_id".to_string())
+    );
+    assert_eq!(edited_message.message.text(), Some("edited"));
+    assert_eq!(
+        edited_message.message.via_bot.as_ref().unwrap().id,
+        UserId(MockMe::ID)
+    );
+}
+
+#[tokio::test]
+async fn test_edit_inline_message_reply_markup() {
+    let bot = MockBot::new(
+        MockCallbackQuery::new()
+            .data("test")
+            .inline_message_id("inline_id")
+            .message(MockMessageText::new().text("inline message").build()),
+        get_schema(),
+    );
+
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    let edited_message = responses.edited_messages_reply_markup.last().unwrap();
+    assert_eq!(edited_message.message.text(), Some("inline message"));
+    assert_eq!(
+        edited_message
+            .message
+            .reply_markup()
+            .unwrap()
+            .inline_keyboard[0][0]
+            .text,
+        "edited"
+    );
+    // The inline message is not a chat message
+    assert!(responses.edited_messages_text.is_empty());
+}
+
+#[tokio::test]
+async fn test_edit_message_text_removes_keyboard() {
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(|bot: Bot, message: Message| async move {
+            let keyboard =
+                InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback("a", "a")]]);
+            let sent = bot
+                .send_message(message.chat.id, "text")
+                .reply_markup(keyboard)
+                .await?;
+            // The same text without a keyboard only removes the keyboard
+            bot.edit_message_text(message.chat.id, sent.id, "text")
+                .await?;
+            Ok::<(), Box<dyn std::error::Error + Send + Sync + 'static>>(())
+        }),
+    );
+
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    assert!(responses.handler_errors.is_empty());
+    let edited = &responses.edited_messages_text[0].message;
+    assert_eq!(edited.text(), Some("text"));
+    assert!(edited.reply_markup().is_none());
+}
+
+#[tokio::test]
+async fn test_edit_unknown_inline_message() {
+    let bot = MockBot::new(
+        MockMessageText::new().text("edit"),
+        Update::filter_message().endpoint(|bot: Bot, message: Message| async move {
+            let error = bot
+                .edit_message_text_inline("unknown", "edited")
+                .await
+                .unwrap_err();
+            bot.send_message(message.chat.id, format!("{:?}", error))
+                .await?;
+            Ok::<(), Box<dyn std::error::Error + Send + Sync + 'static>>(())
+        }),
+    );
+
+    // The bot gets a telegram error, not an invalid response
+    bot.dispatch_and_check_last_text("Api(MessageIdInvalid)")
+        .await;
+}
+
 #[tokio::test]
 async fn test_pin_message() {
     let bot = MockBot::new(MockMessageText::new().text("/pinmessage"), get_schema());
@@ -907,6 +1271,59 @@ async fn test_forward_message() {
     );
 }
 
+#[tokio::test]
+async fn test_forward_protected_message() {
+    let bot = MockBot::new(
+        MockMessageText::new().has_protected_content(true),
+        Update::filter_message().endpoint(|bot: Bot, message: Message| async move {
+            let error = bot
+                .forward_message(message.chat.id, message.chat.id, message.id)
+                .await
+                .unwrap_err();
+            bot.send_message(message.chat.id, format!("{:?}", error))
+                .await?;
+            Ok::<(), Box<dyn std::error::Error + Send + Sync + 'static>>(())
+        }),
+    );
+
+    bot.dispatch_and_check_last_text("Api(Unknown(\"Bad Request: message can't be forwarded\"))")
+        .await;
+}
+
+#[tokio::test]
+async fn test_copy_protected_message() {
+    let bot = MockBot::new(
+        MockMessageText::new().has_protected_content(true),
+        Update::filter_message().endpoint(|bot: Bot, message: Message| async move {
+            let error = bot
+                .copy_message(message.chat.id, message.chat.id, message.id)
+                .await
+                .unwrap_err();
+            bot.send_message(message.chat.id, format!("{:?}", error))
+                .await?;
+            Ok::<(), Box<dyn std::error::Error + Send + Sync + 'static>>(())
+        }),
+    );
+
+    bot.dispatch_and_check_last_text("Api(Unknown(\"Bad Request: message can't be copied\"))")
+        .await;
+}
+
+#[tokio::test]
+async fn test_get_unknown_file() {
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(|bot: Bot, message: Message| async move {
+            let error = bot.get_file("unknown").await.unwrap_err();
+            bot.send_message(message.chat.id, format!("{:?}", error))
+                .await?;
+            Ok::<(), Box<dyn std::error::Error + Send + Sync + 'static>>(())
+        }),
+    );
+
+    bot.dispatch_and_check_last_text("Api(FileIdInvalid)").await;
+}
+
 #[tokio::test]
 async fn test_copy_message() {
     let bot = MockBot::new(MockMessageText::new().text("/copymessage"), get_schema());
@@ -984,3 +1401,1873 @@ async fn test_set_message_reaction() {
         }
     );
 }
+
+const BINARY_DATA: &[u8] = &[0x89, 0x50, 0x4e, 0x47, 0x00, 0xff, 0xfe, 0x80, 0x0d, 0x0a];
+
+async fn send_binary_files(
+    bot: Bot,
+    msg: Message,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let document = InputFile::memory(BINARY_DATA).file_name("binary.bin");
+    let document_message = bot.send_document(msg.chat.id, document).await?;
+    let file = bot
+        .get_file(document_message.document().unwrap().file.id.clone())
+        .await?;
+    let mut content = Vec::new();
+    bot.download_file(&file.path, &mut content).await?;
+    assert_eq!(content, BINARY_DATA);
+    let photos = vec![
+        InputMedia::Photo(InputMediaPhoto::new(
+            InputFile::memory(BINARY_DATA).file_name("photo1.png"),
+        )),
+        InputMedia::Photo(InputMediaPhoto::new(
+            InputFile::memory(&BINARY_DATA[..4]).file_name("photo2.png"),
+        )),
+    ];
+    bot.send_media_group(msg.chat.id, photos).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_send_binary_files() {
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(send_binary_files),
+    );
+
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    let sent_document = &responses.sent_messages_document[0];
+    assert_eq!(sent_document.bot_request.file_data, BINARY_DATA);
+    assert_eq!(
+        sent_document.message.document().unwrap().file.size,
+        BINARY_DATA.len() as u32
+    );
+
+    let media_group = &responses.sent_media_group[0];
+    let sizes: Vec<u32> = media_group
+        .messages
+        .iter()
+        .map(|message| message.photo().unwrap()[0].file.size)
+        .collect();
+    assert_eq!(sizes, vec![BINARY_DATA.len() as u32, 4]);
+    let server::routes::MediaGroupInputMedia::InputMediaPhoto(photo) =
+        &media_group.bot_request.media[1]
+    else {
+        panic!("The media is not a photo!");
+    };
+    assert_eq!(photo.file_data, &BINARY_DATA[..4]);
+}
+
+async fn download_and_echo(
+    bot: Bot,
+    msg: Message,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let file = bot
+        .get_file(msg.document().unwrap().file.id.clone())
+        .await?;
+    let mut content = Vec::new();
+    bot.download_file(&file.path, &mut content).await?;
+    bot.send_message(msg.chat.id, String::from_utf8(content)?)
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_download_seeded_file_content() {
+    let bot = MockBot::new(
+        MockMessageDocument::new().file_content("first document"),
+        Update::filter_message().endpoint(download_and_echo),
+    );
+
+    bot.dispatch_and_check_last_text("first document").await;
+
+    bot.update(
+        MockMessageDocument::new()
+            .file_id("other_file")
+            .file_content("second document"),
+    );
+    bot.dispatch_and_check_last_text("second document").await;
+}
+
+async fn send_photo_with_action(
+    bot: Bot,
+    msg: Message,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    bot.send_chat_action(msg.chat.id, ChatAction::UploadPhoto)
+        .await?;
+    let photo = InputFile::memory("somedata").file_name("photo.jpg");
+    let sent_photo = bot.send_photo(msg.chat.id, photo).await?;
+    bot.pin_chat_message(msg.chat.id, sent_photo.id).await?;
+    bot.edit_message_caption(msg.chat.id, sent_photo.id)
+        .caption("edited")
+        .await?;
+    bot.send_message(ChatId(msg.chat.id.0 + 1), "other chat")
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_requests_log() {
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(send_photo_with_action),
+    );
+
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    let methods: Vec<&str> = responses
+        .requests
+        .iter()
+        .map(|request| request.method.as_str())
+        .collect();
+    assert_eq!(
+        methods,
+        vec![
+            "SendChatAction",
+            "SendPhoto",
+            "PinChatMessage",
+            "EditMessageCaption",
+            "SendMessage"
+        ]
+    );
+    assert!(responses
+        .requests
+        .windows(2)
+        .all(|requests| requests[0].timestamp <= requests[1].timestamp));
+
+    let sent_photo = responses.requests_by_method("sendPhoto")[0];
+    let server::RequestBody::Multipart { attachments, .. } = &sent_photo.body else {
+        panic!("The photo was not sent with multipart!");
+    };
+    assert_eq!(attachments["photo.jpg"].file_data, b"somedata");
+    assert_eq!(sent_photo.response["ok"], true);
+    assert_eq!(
+        sent_photo.response["result"]["photo"][0]["file_size"],
+        "somedata".len()
+    );
+
+    let sent_action = responses.requests_by_method("SendChatAction")[0];
+    let server::RequestBody::Json(body) = &sent_action.body else {
+        panic!("The chat action was not sent with json!");
+    };
+    assert_eq!(body["action"], "upload_photo");
+
+    let chat_id = MockMessageText::new().build().chat.id.0;
+    assert_eq!(responses.requests_by_chat(chat_id).len(), 4);
+    assert_eq!(responses.requests_by_chat(chat_id + 1).len(), 1);
+}
+
+async fn set_commands(
+    bot: Bot,
+    msg: Message,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    bot.set_my_commands(vec![BotCommand::new("start", "Starts the bot")])
+        .await?;
+    bot.send_message(msg.chat.id, "Commands are set").await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unsupported_method_error() {
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(set_commands),
+    );
+    bot.handler_errors(FailurePolicy::Collect);
+
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    assert_eq!(responses.unsupported_methods, vec!["SetMyCommands"]);
+    assert!(responses.handler_errors[0]
+        .error
+        .contains("method SetMyCommands is not supported"));
+    let request = responses.requests_by_method("SetMyCommands")[0];
+    assert_eq!(request.response["ok"], false);
+    assert_eq!(request.response["error_code"], 404);
+    assert!(responses.sent_messages.is_empty());
+}
+
+#[tokio::test]
+async fn test_unsupported_method_stub() {
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(set_commands),
+    );
+    bot.unsupported_methods(UnsupportedMethods::Stub(serde_json::json!(true)));
+
+    bot.dispatch_and_check_last_text("Commands are set").await;
+
+    assert_eq!(
+        bot.get_responses().unsupported_methods,
+        vec!["SetMyCommands"]
+    );
+}
+
+#[tokio::test]
+#[should_panic]
+async fn test_unsupported_method_panics() {
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(set_commands),
+    );
+    bot.unsupported_methods(UnsupportedMethods::Panic);
+    bot.handler_errors(FailurePolicy::Ignore);
+
+    bot.dispatch().await;
+}
+
+async fn send_to_two_chats(
+    bot: Bot,
+    msg: Message,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    for chat_id in [msg.chat.id, ChatId(msg.chat.id.0 + 1)] {
+        match bot.send_message(chat_id, "hello").await {
+            Err(RequestError::Api(ApiError::BotBlocked)) => {
+                bot.send_message(msg.chat.id, format!("{} blocked the bot", chat_id.0))
+                    .await?;
+            }
+            result => {
+                result?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fail_next() {
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(send_to_two_chats),
+    );
+    bot.handler_errors(FailurePolicy::Collect);
+    bot.fail_next("sendMessage", ApiError::ChatNotFound);
+
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    assert_eq!(responses.handler_errors[0].error, "Api(ChatNotFound)");
+    assert!(responses.sent_messages.is_empty());
+    let request = &responses.requests[0];
+    assert_eq!(request.response["error_code"], 400);
+    assert_eq!(
+        request.response["description"],
+        "Bad Request: chat not found"
+    );
+
+    // The error was used up
+    bot.dispatch().await;
+    assert!(bot.get_responses().handler_errors.is_empty());
+    assert_eq!(bot.get_responses().sent_messages.len(), 2);
+}
+
+#[tokio::test]
+async fn test_fail_when_chat_id() {
+    let chat_id = MockMessageText::new().build().chat.id.0;
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(send_to_two_chats),
+    );
+    bot.fail_when(
+        move |call| call.is_method("SendMessage") && call.chat_id() == Some(chat_id + 1),
+        ApiError::BotBlocked,
+    );
+
+    bot.dispatch().await;
+
+    let texts: Vec<_> = bot
+        .get_responses()
+        .sent_messages
+        .iter()
+        .map(|message| message.text().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        texts,
+        vec![
+            "hello".to_string(),
+            format!("{} blocked the bot", chat_id + 1)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_fail_when_call_number() {
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(send_to_two_chats),
+    );
+    bot.handler_errors(FailurePolicy::Collect);
+    bot.fail_when(|call| call.call_number == 2, ApiError::ChatNotFound);
+
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    assert_eq!(responses.sent_messages.len(), 1);
+    assert_eq!(responses.handler_errors[0].error, "Api(ChatNotFound)");
+}
+
+async fn send_twice_with_backoff(
+    bot: Bot,
+    msg: Message,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    for text in ["first", "second"] {
+        loop {
+            match bot.send_message(msg.chat.id, text).await {
+                Err(RequestError::RetryAfter(seconds)) => {
+                    tokio::time::sleep(seconds.duration()).await
+                }
+                result => {
+                    result?;
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_per_chat_rate_limit() {
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(send_to_two_chats),
+    );
+    bot.rate_limits(RateLimits {
+        per_chat: Some(RateLimit::new(1, Duration::from_secs(60))),
+        global: None,
+    });
+
+    // Different chats are not limited together
+    bot.dispatch().await;
+    assert_eq!(bot.get_responses().sent_messages.len(), 2);
+
+    bot.handler_errors(FailurePolicy::Collect);
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    assert!(responses.sent_messages.is_empty());
+    assert_eq!(responses.handler_errors[0].error, "RetryAfter(Seconds(60))");
+    let request = &responses.requests[0];
+    assert_eq!(request.response["error_code"], 429);
+    assert_eq!(request.response["parameters"]["retry_after"], 60);
+}
+
+#[tokio::test]
+async fn test_global_rate_limit() {
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(send_to_two_chats),
+    );
+    bot.handler_errors(FailurePolicy::Collect);
+    bot.rate_limits(RateLimits {
+        per_chat: None,
+        global: Some(RateLimit::new(1, Duration::from_secs(10))),
+    });
+
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    assert_eq!(responses.sent_messages.len(), 1);
+    assert_eq!(responses.handler_errors[0].error, "RetryAfter(Seconds(10))");
+}
+
+#[tokio::test]
+async fn test_rate_limit_backoff() {
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(send_twice_with_backoff),
+    );
+    bot.rate_limits(RateLimits::telegram());
+
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    let texts: Vec<_> = responses
+        .sent_messages
+        .iter()
+        .map(|message| message.text().unwrap())
+        .collect();
+    assert_eq!(texts, vec!["first", "second"]);
+    // The second message had to wait
+    let response_codes: Vec<_> = responses
+        .requests
+        .iter()
+        .map(|request| request.response["error_code"].as_u64())
+        .collect();
+    assert_eq!(response_codes, vec![None, Some(429), None]);
+}
+
+#[tokio::test]
+async fn test_message_ids_are_per_chat() {
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(send_to_two_chats),
+    );
+
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    let ids: Vec<_> = responses
+        .sent_messages
+        .iter()
+        .map(|message| (message.chat.id.0, message.id.0))
+        .collect();
+    let chat_id = MockMessageText::new().build().chat.id.0;
+    // The mocked message is the first one in its chat, the other chat starts from scratch
+    assert_eq!(ids, vec![(chat_id, 2), (chat_id + 1, 1)]);
+}
+
+async fn touch_message_in_other_chat(
+    bot: Bot,
+    msg: Message,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let other_chat = ChatId(msg.chat.id.0 + 1);
+    let errors = vec![
+        bot.edit_message_text(other_chat, msg.id, "edited")
+            .await
+            .unwrap_err(),
+        bot.delete_message(other_chat, msg.id).await.unwrap_err(),
+        bot.forward_message(msg.chat.id, other_chat, msg.id)
+            .await
+            .unwrap_err(),
+        bot.copy_message(msg.chat.id, other_chat, msg.id)
+            .await
+            .unwrap_err(),
+        bot.send_message(other_chat, "reply")
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await
+            .unwrap_err(),
+    ];
+    for error in errors {
+        bot.send_message(msg.chat.id, format!("{:?}", error))
+            .await?;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_message_not_found_in_other_chat() {
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(touch_message_in_other_chat),
+    );
+
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    let texts: Vec<_> = responses
+        .sent_messages
+        .iter()
+        .map(|message| message.text().unwrap())
+        .collect();
+    assert_eq!(
+        texts,
+        vec![
+            "Api(MessageToEditNotFound)",
+            "Api(MessageToDeleteNotFound)",
+            "Api(MessageToForwardNotFound)",
+            "Api(MessageToCopyNotFound)",
+            "Api(MessageToReplyNotFound)",
+        ]
+    );
+    // The message in its own chat is untouched
+    assert!(responses.edited_messages_text.is_empty());
+    assert!(responses.deleted_messages.is_empty());
+}
+
+async fn edit_without_changes(
+    bot: Bot,
+    msg: Message,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let markup =
+        InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback("button", "data")]]);
+    let sent_message = bot
+        .send_message(msg.chat.id, "text")
+        .reply_markup(markup.clone())
+        .await?;
+    let errors = vec![
+        // Without the markup the keyboard would be removed, which is a change
+        bot.edit_message_text(msg.chat.id, sent_message.id, "text")
+            .reply_markup(markup.clone())
+            .await
+            .unwrap_err(),
+        bot.edit_message_reply_markup(msg.chat.id, sent_message.id)
+            .reply_markup(markup)
+            .await
+            .unwrap_err(),
+    ];
+    for error in errors {
+        bot.send_message(msg.chat.id, format!("{:?}", error))
+            .await?;
+    }
+    // Any real change is fine
+    bot.edit_message_text(msg.chat.id, sent_message.id, "new text")
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_edit_message_not_modified() {
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(edit_without_changes),
+    );
+
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    let texts: Vec<_> = responses
+        .sent_messages
+        .iter()
+        .skip(1)
+        .map(|message| message.text().unwrap())
+        .collect();
+    assert_eq!(
+        texts,
+        vec!["Api(MessageNotModified)", "Api(MessageNotModified)"]
+    );
+    assert_eq!(responses.edited_messages_text.len(), 1);
+    assert!(responses.edited_messages_reply_markup.is_empty());
+    let request = responses.requests_by_method("editMessageText")[0];
+    assert_eq!(
+        request.response["description"],
+        "Bad Request: message is not modified: specified new message content and reply markup are exactly the same as a current content and reply markup of the message"
+    );
+}
+
+async fn send_with_markup(
+    bot: Bot,
+    msg: Message,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    bot.send_message(msg.chat.id, "<b>Hello</b>, &lt;user&gt;!")
+        .parse_mode(ParseMode::Html)
+        .await?;
+    // The dot is not escaped
+    bot.send_message(msg.chat.id, "*Hello*, user.")
+        .parse_mode(ParseMode::MarkdownV2)
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_parse_mode() {
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(send_with_markup),
+    );
+    bot.handler_errors(FailurePolicy::Collect);
+
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    let sent_message = &responses.sent_messages[0];
+    assert_eq!(sent_message.text(), Some("Hello, <user>!"));
+    assert_eq!(
+        sent_message.entities(),
+        Some(&[MessageEntity::bold(0, 5)][..])
+    );
+    // The raw text is still in the request
+    assert_eq!(
+        responses.sent_messages_text[0].bot_request.text,
+        "<b>Hello</b>, &lt;user&gt;!"
+    );
+
+    assert_eq!(responses.sent_messages.len(), 1);
+    assert_eq!(
+        responses.handler_errors[0].error,
+        "Api(CantParseEntities(\"Bad Request: can't parse entities: Character '.' is reserved and must be escaped with the preceding '\\\\'\"))"
+    );
+}
+
+async fn send_over_the_limits(
+    bot: Bot,
+    msg: Message,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let long_data = InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(
+        "button",
+        "a".repeat(65),
+    )]]);
+    let errors = vec![
+        bot.send_message(msg.chat.id, "a".repeat(4097)).await.err(),
+        bot.send_message(msg.chat.id, "text")
+            .reply_markup(long_data)
+            .await
+            .err(),
+        bot.send_poll(msg.chat.id, "question", (0..11).map(|i| i.to_string()))
+            .await
+            .err(),
+    ];
+    for error in errors {
+        bot.send_message(msg.chat.id, format!("{:?}", error))
+            .await?;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_api_limits() {
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(send_over_the_limits),
+    );
+
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    let texts: Vec<_> = responses
+        .sent_messages
+        .iter()
+        .map(|message| message.text().unwrap())
+        .collect();
+    assert_eq!(
+        texts,
+        vec![
+            "Some(Api(MessageIsTooLong))",
+            "Some(Api(ButtonDataInvalid))",
+            "Some(Api(PollCantHaveMoreOptions))",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_api_limits_unlimited() {
+    let bot = MockBot::new(
+        MockMessageText::new(),
+        Update::filter_message().endpoint(send_over_the_limits),
+    );
+    bot.api_limits(ApiLimits::unlimited());
+
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    assert_eq!(
+        responses.sent_messages_text[0]
+            .message
+            .text()
+            .unwrap()
+            .len(),
+        4097
+    );
+    assert_eq!(responses.sent_messages_poll.len(), 1);
+    let texts: Vec<_> = responses
+        .sent_messages
+        .iter()
+        .skip(3)
+        .map(|message| message.text().unwrap())
+        .collect();
+    assert_eq!(texts, vec!["None", "None", "None"]);
+}
+
+async fn chat_member_handler(
+    bot: Bot,
+    update: teloxide::types::ChatMemberUpdated,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let old = &update.old_chat_member;
+    let new = &update.new_chat_member;
+    let text = if !old.is_present() && new.is_present() {
+        format!("Welcome, {}!", new.user.first_name)
+    } else if new.is_banned() {
+        format!("{} was banned", new.user.first_name)
+    } else if !new.is_present() {
+        format!("Goodbye, {}!", new.user.first_name)
+    } else {
+        format!("{} is now {:?}", new.user.first_name, new.status())
+    };
+    bot.send_message(update.chat.id, text).await?;
+    Ok(())
+}
+
+async fn my_chat_member_handler(
+    bot: Bot,
+    update: teloxide::types::ChatMemberUpdated,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    if update.new_chat_member.is_administrator() {
+        bot.send_message(update.chat.id, "Thanks for the promotion!")
+            .await?;
+    } else if update.new_chat_member.is_present() {
+        bot.send_message(update.chat.id, "Hello, group!").await?;
+    }
+    Ok(())
+}
+
+fn get_chat_member_schema() -> UpdateHandler<Box<dyn std::error::Error + Send + Sync + 'static>> {
+    dptree::entry()
+        .branch(Update::filter_chat_member().endpoint(chat_member_handler))
+        .branch(Update::filter_my_chat_member().endpoint(my_chat_member_handler))
+}
+
+#[tokio::test]
+async fn test_chat_member_updated() {
+    let bot = MockBot::new(MockChatMemberUpdated::new(), get_chat_member_schema());
+
+    bot.dispatch().await;
+    let responses = bot.get_responses();
+    let message = responses.sent_messages.last().unwrap();
+    assert_eq!(message.chat.id.0, MockGroupChat::ID);
+    assert_eq!(message.text(), Some("Welcome, First!"));
+
+    bot.update(MockChatMemberUpdated::new().left());
+    bot.dispatch().await;
+    let responses = bot.get_responses();
+    assert_eq!(
+        responses.sent_messages.last().unwrap().text(),
+        Some("Goodbye, First!")
+    );
+
+    bot.update(MockChatMemberUpdated::new().banned());
+    bot.dispatch().await;
+    let responses = bot.get_responses();
+    assert_eq!(
+        responses.sent_messages.last().unwrap().text(),
+        Some("First was banned")
+    );
+
+    bot.update(MockChatMemberUpdated::new().promoted());
+    bot.dispatch().await;
+    let responses = bot.get_responses();
+    assert_eq!(
+        responses.sent_messages.last().unwrap().text(),
+        Some("First is now Administrator")
+    );
+}
+
+#[tokio::test]
+async fn test_my_chat_member_updated() {
+    let bot = MockBot::new(
+        vec![
+            MockMyChatMemberUpdated::new(),
+            MockMyChatMemberUpdated::new().promoted(),
+        ],
+        get_chat_member_schema(),
+    );
+
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    assert_eq!(responses.sent_messages.len(), 2);
+    assert_eq!(responses.sent_messages[0].text(), Some("Hello, group!"));
+    assert_eq!(
+        responses.sent_messages[1].text(),
+        Some("Thanks for the promotion!")
+    );
+}
+
+async fn edited_message_handler(
+    bot: Bot,
+    message: Message,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    // Forwards the stored message, to check that the edit replaced it
+    bot.forward_message(message.chat.id, message.chat.id, message.id)
+        .await?;
+    Ok(())
+}
+
+async fn channel_post_handler(
+    bot: Bot,
+    message: Message,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    bot.send_message(
+        message.chat.id,
+        format!("Post: {}", message.text().unwrap()),
+    )
+    .await?;
+    Ok(())
+}
+
+fn get_edited_schema() -> UpdateHandler<Box<dyn std::error::Error + Send + Sync + 'static>> {
+    dptree::entry()
+        .branch(Update::filter_message().endpoint(|| async { Ok(()) }))
+        .branch(Update::filter_edited_message().endpoint(edited_message_handler))
+        .branch(Update::filter_channel_post().endpoint(channel_post_handler))
+        .branch(Update::filter_edited_channel_post().endpoint(edited_message_handler))
+}
+
+#[tokio::test]
+async fn test_edited_message() {
+    let bot = MockBot::new(MockMessageText::new().text("original"), get_edited_schema());
+    bot.dispatch().await;
+
+    bot.update(MockMessageText::new().text("edited").as_edited());
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    let forwarded = responses.forwarded_messages.last().unwrap();
+    assert_eq!(forwarded.message.text(), Some("edited"));
+    assert_eq!(forwarded.bot_request.message_id, MockMessageText::ID);
+}
+
+#[tokio::test]
+async fn test_channel_post() {
+    let channel = MockChannelChat::new().build();
+    let bot = MockBot::new(
+        MockMessageText::new()
+            .text("post")
+            .chat(channel.clone())
+            .as_channel_post(),
+        get_edited_schema(),
+    );
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    let message = responses.sent_messages.last().unwrap();
+    assert_eq!(message.chat.id, channel.id);
+    assert_eq!(message.text(), Some("Post: post"));
+
+    bot.update(
+        MockMessageText::new()
+            .text("edited post")
+            .chat(channel)
+            .as_edited_channel_post(),
+    );
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    let forwarded = responses.forwarded_messages.last().unwrap();
+    assert_eq!(forwarded.message.text(), Some("edited post"));
+}
+
+async fn poll_message_handler(
+    bot: Bot,
+    message: Message,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let text = message.text().unwrap();
+    if text == "/quiz" {
+        bot.send_poll(
+            message.chat.id,
+            "2 + 2 = ?",
+            ["3".to_string(), "4".to_string()],
+        )
+        .type_(PollType::Quiz)
+        .correct_option_id(1)
+        .is_anonymous(false)
+        .await?;
+    } else if let Some(message_id) = text.strip_prefix("/stop ") {
+        let message_id = MessageId(message_id.parse().unwrap());
+        match bot.stop_poll(message.chat.id, message_id).await {
+            Ok(poll) => {
+                let counts: Vec<u32> = poll.options.iter().map(|o| o.voter_count).collect();
+                bot.send_message(
+                    message.chat.id,
+                    format!("Closed with {} votes: {:?}", poll.total_voter_count, counts),
+                )
+                .await?;
+            }
+            Err(error) => {
+                bot.send_message(message.chat.id, format!("{:?}", error))
+                    .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn poll_answer_handler(
+    bot: Bot,
+    answer: teloxide::types::PollAnswer,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let user = answer.voter.user().unwrap();
+    bot.send_message(user.id, format!("Your answer: {:?}", answer.option_ids))
+        .await?;
+    Ok(())
+}
+
+fn get_poll_schema() -> UpdateHandler<Box<dyn std::error::Error + Send + Sync + 'static>> {
+    dptree::entry()
+        .branch(Update::filter_message().endpoint(poll_message_handler))
+        .branch(Update::filter_poll_answer().endpoint(poll_answer_handler))
+}
+
+#[tokio::test]
+async fn test_poll_answers() {
+    let bot = MockBot::new(MockMessageText::new().text("/quiz"), get_poll_schema());
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    let poll_message = responses.sent_messages_poll.last().unwrap().message.clone();
+    let poll = poll_message.poll().unwrap();
+    assert!(!poll.is_closed);
+    assert_eq!(poll.total_voter_count, 0);
+
+    let other_user = teloxide::types::Voter::User(MockUser::new().id(2).build());
+    bot.update(vec![
+        MockPollAnswer::to(poll).option_ids(vec![0]),
+        MockPollAnswer::to(poll)
+            .voter(other_user)
+            .option_ids(vec![1]),
+    ]);
+    bot.dispatch().await;
+    assert_eq!(
+        bot.get_responses().sent_messages.last().unwrap().text(),
+        Some("Your answer: [1]")
+    );
+
+    // The first user changes their mind
+    bot.update(MockPollAnswer::to(poll).option_ids(vec![1]));
+    bot.dispatch().await;
+
+    bot.update(MockMessageText::new().text(format!("/stop {}", poll_message.id)));
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    assert_eq!(
+        responses.sent_messages.last().unwrap().text(),
+        Some("Closed with 2 votes: [0, 2]")
+    );
+    let stopped_poll = &responses.stopped_polls.last().unwrap().poll;
+    assert!(stopped_poll.is_closed);
+    assert_eq!(stopped_poll.id, poll.id);
+
+    // Closed polls can't be voted in or stopped again
+    bot.update(MockPollAnswer::to(poll).option_ids(vec![0]));
+    bot.dispatch().await;
+    bot.update(MockMessageText::new().text(format!("/stop {}", poll_message.id)));
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    assert_eq!(
+        responses.sent_messages.last().unwrap().text(),
+        Some("Api(PollHasAlreadyClosed)")
+    );
+}
+
+#[tokio::test]
+async fn test_stop_poll_errors() {
+    let bot = MockBot::new(MockMessageText::new().text("/stop 1"), get_poll_schema());
+    bot.dispatch().await;
+    assert_eq!(
+        bot.get_responses().sent_messages.last().unwrap().text(),
+        Some("Api(MessageIsNotAPoll)")
+    );
+
+    bot.update(MockMessageText::new().text("/stop 100"));
+    bot.dispatch().await;
+    assert_eq!(
+        bot.get_responses().sent_messages.last().unwrap().text(),
+        Some("Api(MessageWithPollNotFound)")
+    );
+}
+
+async fn refund_star_payment(
+    bot: &Bot,
+    user_id: UserId,
+    telegram_payment_charge_id: &str,
+) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    // teloxide doesn't have refundStarPayment, so it is called by hand
+    let url = format!(
+        "{}/bot{}/RefundStarPayment",
+        bot.api_url().as_str().trim_end_matches('/'),
+        bot.token()
+    );
+    let body = serde_json::json!({
+        "user_id": user_id.0,
+        "telegram_payment_charge_id": telegram_payment_charge_id,
+    });
+    let response = reqwest::Client::new()
+        .post(url)
+        .header("Content-Type", "application/json")
+        .body(body.to_string())
+        .send()
+        .await?;
+    Ok(serde_json::from_str(&response.text().await?)?)
+}
+
+async fn shop_message_handler(
+    bot: Bot,
+    message: Message,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    if let Some(payment) = message.successful_payment() {
+        let user_id = message.from.as_ref().unwrap().id;
+        let response =
+            refund_star_payment(&bot, user_id, &payment.telegram_payment_charge_id).await?;
+        bot.send_message(message.chat.id, response.to_string())
+            .await?;
+        return Ok(());
+    }
+    // The text is the currency and the provider token, if there is one
+    let text = message.text().unwrap();
+    let (currency, provider_token) = text.split_once(' ').unwrap_or((text, ""));
+    if let Err(error) = bot
+        .send_invoice(
+            message.chat.id,
+            "Item",
+            "Very good item",
+            "item_1",
+            provider_token,
+            currency,
+            [teloxide::types::LabeledPrice {
+                label: "Item".to_string(),
+                amount: 50,
+            }],
+        )
+        .await
+    {
+        bot.send_message(message.chat.id, format!("{:?}", error))
+            .await?;
+    }
+    Ok(())
+}
+
+async fn pre_checkout_query_handler(
+    bot: Bot,
+    query: teloxide::types::PreCheckoutQuery,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let ok = query.invoice_payload == "item_1";
+    let mut answer = bot.answer_pre_checkout_query(query.id, ok);
+    if !ok {
+        answer = answer.error_message("Sold out");
+    }
+    answer.await?;
+    Ok(())
+}
+
+async fn shipping_query_handler(
+    bot: Bot,
+    query: teloxide::types::ShippingQuery,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    bot.answer_shipping_query(query.id, false)
+        .error_message("We don't ship there")
+        .await?;
+    Ok(())
+}
+
+fn get_shop_schema() -> UpdateHandler<Box<dyn std::error::Error + Send + Sync + 'static>> {
+    dptree::entry()
+        .branch(Update::filter_message().endpoint(shop_message_handler))
+        .branch(Update::filter_pre_checkout_query().endpoint(pre_checkout_query_handler))
+        .branch(Update::filter_shipping_query().endpoint(shipping_query_handler))
+}
+
+#[tokio::test]
+async fn test_invoice_reply() {
+    let bot = MockBot::new(
+        MockMessageText::new().text("buy"),
+        Update::filter_message().endpoint(|bot: Bot, message: Message| async move {
+            bot.send_invoice(
+                message.chat.id,
+                "Item",
+                "Very good item",
+                "item_1",
+                "",
+                "XTR",
+                [teloxide::types::LabeledPrice {
+                    label: "Item".to_string(),
+                    amount: 50,
+                }],
+            )
+            .reply_parameters(ReplyParameters::new(message.id))
+            .await?;
+            Ok::<(), Box<dyn std::error::Error + Send + Sync + 'static>>(())
+        }),
+    );
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    let invoice = responses.sent_messages_invoice.last().unwrap();
+    let reply_to_message = invoice.reply_to_message.as_ref().unwrap();
+    assert_eq!(reply_to_message.text(), Some("buy"));
+}
+
+#[tokio::test]
+async fn test_star_payment() {
+    let bot = MockBot::new(MockMessageText::new().text("XTR"), get_shop_schema());
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    let invoice = responses.sent_messages_invoice.last().unwrap();
+    assert_eq!(invoice.message.invoice().unwrap().currency, "XTR");
+    assert_eq!(invoice.message.invoice().unwrap().total_amount, 50);
+    assert_eq!(invoice.bot_request.payload, "item_1");
+
+    bot.update(vec![
+        MockPreCheckoutQuery::new().invoice_payload("item_1"),
+        MockPreCheckoutQuery::new().invoice_payload("item_2"),
+    ]);
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    let answers = &responses.answered_pre_checkout_queries;
+    assert_eq!(answers.len(), 2);
+    assert!(answers.iter().any(|answer| answer.ok));
+    assert!(answers
+        .iter()
+        .any(|answer| !answer.ok && answer.error_message.as_deref() == Some("Sold out")));
+
+    bot.update(MockMessageSuccessfulPayment::new().invoice_payload("item_1"));
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    assert_eq!(responses.refunded_star_payments.len(), 1);
+    assert_eq!(
+        responses.refunded_star_payments[0].telegram_payment_charge_id,
+        MockMessageSuccessfulPayment::TELEGRAM_PAYMENT_CHARGE_ID
+    );
+    assert_eq!(
+        responses.sent_messages.last().unwrap().text(),
+        Some(r#"{"ok":true,"result":true}"#)
+    );
+
+    // The same payment can't be refunded twice
+    bot.dispatch().await;
+    let responses = bot.get_responses();
+    assert!(responses.refunded_star_payments.is_empty());
+    assert!(responses
+        .sent_messages
+        .last()
+        .unwrap()
+        .text()
+        .unwrap()
+        .contains("Bad Request: CHARGE_ALREADY_REFUNDED"));
+}
+
+#[tokio::test]
+async fn test_payment_errors() {
+    let bot = MockBot::new(MockMessageText::new().text("USD"), get_shop_schema());
+    bot.dispatch().await;
+    assert_eq!(
+        bot.get_responses().sent_messages.last().unwrap().text(),
+        Some("Api(Unknown(\"Bad Request: PAYMENT_PROVIDER_INVALID\"))")
+    );
+
+    bot.update(MockMessageText::new().text("USD provider"));
+    bot.dispatch().await;
+    assert_eq!(bot.get_responses().sent_messages_invoice.len(), 1);
+
+    bot.update(MockShippingQuery::new());
+    bot.dispatch().await;
+    let responses = bot.get_responses();
+    let answer = responses.answered_shipping_queries.last().unwrap();
+    assert!(!answer.ok);
+    assert_eq!(answer.error_message.as_deref(), Some("We don't ship there"));
+
+    // The payment was never made, so it can't be refunded
+    let response = refund_star_payment(&bot.bot, UserId(MockUser::ID), "unknown")
+        .await
+        .unwrap();
+    assert_eq!(response["description"], "Bad Request: CHARGE_NOT_FOUND");
+}
+
+async fn support_desk_handler(
+    bot: Bot,
+    message: Message,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let Some(text) = message.text() else {
+        // Service messages, like the forum topic being created
+        return Ok(());
+    };
+    let thread_id = message.thread_id.unwrap_or(ThreadId(MessageId(0)));
+    let result = if let Some(name) = text.strip_prefix("/ticket ") {
+        match bot
+            .create_forum_topic(message.chat.id, name, 0x6FB9F0, "")
+            .await
+        {
+            Ok(topic) => bot
+                .send_message(message.chat.id, "How can we help?")
+                .message_thread_id(topic.thread_id)
+                .await
+                .map(|_| ()),
+            Err(error) => Err(error),
+        }
+    } else if let Some(name) = text.strip_prefix("/rename ") {
+        bot.edit_forum_topic(message.chat.id, thread_id)
+            .name(name)
+            .await
+            .map(|_| ())
+    } else if text == "/close" {
+        bot.close_forum_topic(message.chat.id, thread_id)
+            .await
+            .map(|_| ())
+    } else if text == "/reopen" {
+        bot.reopen_forum_topic(message.chat.id, thread_id)
+            .await
+            .map(|_| ())
+    } else if text == "/delete" {
+        match bot.delete_forum_topic(message.chat.id, thread_id).await {
+            // The topic is gone, so this fails
+            Ok(_) => bot
+                .send_message(message.chat.id, "Deleted")
+                .message_thread_id(thread_id)
+                .await
+                .map(|_| ()),
+            Err(error) => Err(error),
+        }
+    } else if text == "/icons" {
+        let stickers = bot.get_forum_topic_icon_stickers().await?;
+        let custom_emojis = stickers
+            .iter()
+            .filter(|sticker| sticker.custom_emoji_id().is_some())
+            .count();
+        bot.send_message(message.chat.id, format!("{custom_emojis} icons"))
+            .await
+            .map(|_| ())
+    } else {
+        bot.send_message(message.chat.id, "Noted")
+            .message_thread_id(thread_id)
+            .await
+            .map(|_| ())
+    };
+    if let Err(error) = result {
+        bot.send_message(message.chat.id, format!("{:?}", error))
+            .await?;
+    }
+    Ok(())
+}
+
+fn forum_chat() -> teloxide::types::Chat {
+    MockSupergroupChat::new().is_forum(true).build()
+}
+
+fn in_topic(text: &str, thread_id: ThreadId) -> MockMessageText {
+    MockMessageText::new()
+        .text(text)
+        .chat(forum_chat())
+        .thread_id(thread_id)
+        .is_topic_message(true)
+}
+
+#[tokio::test]
+async fn test_forum_topics() {
+    let bot = MockBot::new(
+        MockMessageText::new()
+            .text("/ticket Printer")
+            .chat(forum_chat()),
+        Update::filter_message().endpoint(support_desk_handler),
+    );
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    let topic = responses.created_forum_topics.last().unwrap().topic.clone();
+    assert_eq!(topic.name, "Printer");
+    assert_eq!(topic.icon_color, [0x6F, 0xB9, 0xF0]);
+    assert_eq!(topic.icon_custom_emoji_id, None);
+    let sent_message = responses.sent_messages.last().unwrap();
+    assert_eq!(sent_message.text(), Some("How can we help?"));
+    assert_eq!(sent_message.thread_id, Some(topic.thread_id));
+    assert!(sent_message.is_topic_message);
+
+    bot.update(in_topic("/rename Printer is on fire", topic.thread_id));
+    bot.dispatch().await;
+    let responses = bot.get_responses();
+    assert_eq!(
+        responses
+            .edited_forum_topics
+            .last()
+            .unwrap()
+            .name
+            .as_deref(),
+        Some("Printer is on fire")
+    );
+
+    bot.dispatch().await;
+    assert_eq!(
+        bot.get_responses().sent_messages.last().unwrap().text(),
+        Some("Api(Unknown(\"Bad Request: TOPIC_NOT_MODIFIED\"))")
+    );
+
+    bot.update(in_topic("/close", topic.thread_id));
+    bot.dispatch().await;
+    assert_eq!(bot.get_responses().closed_forum_topics.len(), 1);
+    bot.dispatch().await;
+    assert_eq!(
+        bot.get_responses().sent_messages.last().unwrap().text(),
+        Some("Api(Unknown(\"Bad Request: TOPIC_NOT_MODIFIED\"))")
+    );
+
+    bot.update(in_topic("/reopen", topic.thread_id));
+    bot.dispatch().await;
+    assert_eq!(bot.get_responses().reopened_forum_topics.len(), 1);
+
+    bot.update(in_topic("/delete", topic.thread_id));
+    bot.dispatch().await;
+    let responses = bot.get_responses();
+    assert_eq!(responses.deleted_forum_topics.len(), 1);
+    assert_eq!(
+        responses.sent_messages.last().unwrap().text(),
+        Some("Api(Unknown(\"Bad Request: message thread not found\"))")
+    );
+}
+
+#[tokio::test]
+async fn test_forum_topic_from_update() {
+    // The topic was created before, so the bot knows about it only from the messages
+    let thread_id = ThreadId(MessageId(42));
+    let bot = MockBot::new(
+        MockMessageForumTopicCreated::new()
+            .name("Old ticket")
+            .id(42)
+            .thread_id(thread_id),
+        Update::filter_message().endpoint(support_desk_handler),
+    );
+    bot.dispatch().await;
+    bot.update(MockMessageForumTopicClosed::new().thread_id(thread_id));
+    bot.dispatch().await;
+
+    bot.update(in_topic("Hi", thread_id));
+    bot.dispatch_and_check_last_text("Noted").await;
+    let responses = bot.get_responses();
+    assert_eq!(
+        responses.sent_messages.last().unwrap().thread_id,
+        Some(thread_id)
+    );
+    assert_eq!(responses.created_forum_topics.len(), 0);
+
+    bot.update(in_topic("/close", thread_id));
+    bot.dispatch_and_check_last_text("Api(Unknown(\"Bad Request: TOPIC_NOT_MODIFIED\"))")
+        .await;
+
+    // A topic that the bot didn't see being created is known from its messages
+    bot.update(in_topic("Hi", ThreadId(MessageId(7))));
+    bot.dispatch_and_check_last_text("Noted").await;
+}
+
+#[tokio::test]
+async fn test_message_threads_outside_of_forums() {
+    let bot = MockBot::new(
+        MockMessageText::new()
+            .text("/ticket Printer")
+            .chat(MockGroupChat::new().build()),
+        Update::filter_message().endpoint(support_desk_handler),
+    );
+    bot.dispatch_and_check_last_text("Api(Unknown(\"Bad Request: the chat is not a forum\"))")
+        .await;
+
+    // In other chats the thread is the message that is replied to
+    let message = MockMessageText::new()
+        .text("Hi")
+        .chat(MockGroupChat::new().build());
+    bot.update(message.clone().thread_id(ThreadId(MessageId(1))));
+    bot.dispatch_and_check_last_text("Noted").await;
+    let responses = bot.get_responses();
+    let sent_message = responses.sent_messages.last().unwrap();
+    assert_eq!(sent_message.thread_id, Some(ThreadId(MessageId(1))));
+    assert!(!sent_message.is_topic_message);
+
+    bot.update(message.thread_id(ThreadId(MessageId(100))));
+    bot.dispatch_and_check_last_text("Api(Unknown(\"Bad Request: message thread not found\"))")
+        .await;
+}
+
+#[tokio::test]
+async fn test_get_forum_topic_icon_stickers() {
+    let bot = MockBot::new(
+        MockMessageText::new().text("/icons"),
+        Update::filter_message().endpoint(support_desk_handler),
+    );
+    bot.dispatch_and_check_last_text("4 icons").await;
+}
+
+async fn moderation_handler(
+    bot: Bot,
+    message: Message,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let text = message.text().unwrap();
+    let chat_id = message.chat.id;
+    let (command, user_id) = match text.split_once(' ') {
+        Some((command, user_id)) => (command, UserId(user_id.parse().unwrap())),
+        None => (text, message.from.as_ref().unwrap().id),
+    };
+    let result = match command {
+        "/admins" => {
+            let administrators = bot.get_chat_administrators(chat_id).await;
+            let count = bot.get_chat_member_count(chat_id).await;
+            match (administrators, count) {
+                (Ok(administrators), Ok(count)) => {
+                    bot.send_message(
+                        chat_id,
+                        format!("{} admins, {} members", administrators.len(), count),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+                (Err(error), _) | (_, Err(error)) => Err(error),
+            }
+        }
+        "/ban" => bot.ban_chat_member(chat_id, user_id).await.map(|_| ()),
+        "/unban" => bot.unban_chat_member(chat_id, user_id).await.map(|_| ()),
+        "/mute" => bot
+            .restrict_chat_member(chat_id, user_id, ChatPermissions::empty())
+            .await
+            .map(|_| ()),
+        "/unmute" => bot
+            .restrict_chat_member(chat_id, user_id, ChatPermissions::all())
+            .await
+            .map(|_| ()),
+        "/promote" => bot
+            .promote_chat_member(chat_id, user_id)
+            .can_pin_messages(true)
+            .await
+            .map(|_| ()),
+        "/demote" => bot.promote_chat_member(chat_id, user_id).await.map(|_| ()),
+        "/title" => bot
+            .set_chat_administrator_custom_title(chat_id, user_id, "Moderator")
+            .await
+            .map(|_| ()),
+        "/pin" => bot.pin_chat_message(chat_id, message.id).await.map(|_| ()),
+        "/delete" => bot.delete_message(chat_id, message.id).await.map(|_| ()),
+        _ => Ok(()),
+    };
+    let result = match result {
+        Ok(()) => bot
+            .get_chat_member(chat_id, user_id)
+            .await
+            .map(|member| format!("{:?}", member.kind.status())),
+        Err(error) => Err(error),
+    };
+    let text = match result {
+        Ok(status) => status,
+        Err(error) => format!("{:?}", error),
+    };
+    bot.send_message(chat_id, text).await?;
+    Ok(())
+}
+
+fn group_message(text: &str) -> MockMessageText {
+    MockMessageText::new()
+        .text(text)
+        .chat(MockGroupChat::new().build())
+}
+
+#[tokio::test]
+async fn test_chat_members() {
+    let bot = MockBot::new(
+        group_message("/admins"),
+        Update::filter_message().endpoint(moderation_handler),
+    );
+    bot.chat_members(
+        MockGroupChat::ID,
+        vec![
+            MockChatMember::owner().user(MockUser::new().id(1).build()),
+            MockChatMember::administrator().user(MockUser::new().id(2).build()),
+            MockChatMember::new().user(MockUser::new().id(3).build()),
+            MockChatMember::left().user(MockUser::new().id(4).build()),
+        ],
+    );
+    // The user that sent the message and the bot are members too
+    bot.dispatch_and_check_last_text("3 admins, 5 members")
+        .await;
+
+    bot.update(group_message("/status 4"));
+    bot.dispatch_and_check_last_text("Left").await;
+    bot.update(group_message("/status 5"));
+    bot.dispatch_and_check_last_text("Api(UserNotFound)").await;
+}
+
+async fn me_in_chat_handler(
+    bot: Bot,
+    message: Message,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let me = bot.get_me().await?;
+    let member = bot.get_chat_member(message.chat.id, me.id).await?;
+    let is_in_administrators = bot
+        .get_chat_administrators(message.chat.id)
+        .await?
+        .iter()
+        .any(|administrator| administrator.user.id == me.id);
+    let count = bot.get_chat_member_count(message.chat.id).await?;
+    bot.send_message(
+        message.chat.id,
+        format!(
+            "{:?} {} {}",
+            member.kind.status(),
+            is_in_administrators,
+            count
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_me_in_chat_members() {
+    // The bot isn't in the members, but all of the methods see it as an administrator
+    let bot = MockBot::new(
+        group_message("/me"),
+        Update::filter_message().endpoint(me_in_chat_handler),
+    );
+    bot.me(MockMe::new().administrator_rights(rights_to_restrict()));
+    bot.dispatch_and_check_last_text("Administrator true 2")
+        .await;
+
+    // Or as a member, if it is one
+    bot.chat_members(
+        MockGroupChat::ID,
+        vec![MockChatMember::new().user(MockMe::new().build().user)],
+    );
+    bot.dispatch_and_check_last_text("Member false 2").await;
+}
+
+#[tokio::test]
+async fn test_chat_member_changes() {
+    let bot = MockBot::new(
+        group_message("/ban 3"),
+        Update::filter_message().endpoint(moderation_handler),
+    );
+    bot.chat_members(
+        MockGroupChat::ID,
+        vec![MockChatMember::new().user(MockUser::new().id(3).build())],
+    );
+    bot.dispatch_and_check_last_text("Banned").await;
+    bot.update(group_message("/unban 3"));
+    bot.dispatch_and_check_last_text("Left").await;
+
+    bot.update(group_message("/mute 3"));
+    bot.dispatch_and_check_last_text("Restricted").await;
+    bot.update(group_message("/unmute 3"));
+    // The user left the chat before, so they aren't a member after the restrictions are lifted
+    bot.dispatch_and_check_last_text("Left").await;
+
+    bot.update(group_message("/promote 3"));
+    bot.dispatch_and_check_last_text("Administrator").await;
+    bot.update(group_message("/demote 3"));
+    bot.dispatch_and_check_last_text("Member").await;
+
+    let responses = bot.get_responses();
+    let promoted = responses.promoted_chat_members.last().unwrap();
+    assert_eq!(promoted.user_id, 3);
+    assert_eq!(promoted.can_pin_messages, None);
+}
+
+#[tokio::test]
+async fn test_chat_member_from_updates() {
+    let bot = MockBot::new(
+        MockChatMemberUpdated::new().promoted(),
+        Update::filter_message().endpoint(moderation_handler),
+    );
+    // The bot only sees that the user was promoted
+    bot.unhandled_updates(FailurePolicy::Collect);
+    bot.dispatch().await;
+    bot.update(group_message("/status"));
+    bot.dispatch_and_check_last_text("Administrator").await;
+
+    bot.update(MockMessageText::new().text("/admins"));
+    bot.dispatch_and_check_last_text(
+        "Api(Unknown(\"Bad Request: there are no administrators in the private chat\"))",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_ban_records() {
+    let bot = MockBot::new(
+        group_message("/ban 3"),
+        Update::filter_message().endpoint(moderation_handler),
+    );
+    bot.chat_members(
+        MockGroupChat::ID,
+        vec![
+            MockChatMember::owner().user(MockUser::new().id(1).build()),
+            MockChatMember::new().user(MockUser::new().id(3).build()),
+        ],
+    );
+    bot.dispatch_and_check_last_text("Banned").await;
+    assert!(bot.is_banned(MockGroupChat::ID, 3));
+    assert!(!bot.is_restricted(MockGroupChat::ID, 3));
+
+    bot.update(group_message("/unban 3"));
+    bot.dispatch().await;
+    assert!(!bot.is_banned(MockGroupChat::ID, 3));
+
+    bot.update(group_message("/mute 3"));
+    bot.dispatch().await;
+    assert!(bot.is_restricted(MockGroupChat::ID, 3));
+
+    // The owner and the bot itself can't be banned or restricted
+    bot.update(group_message("/ban 1"));
+    bot.dispatch_and_check_last_text("Api(Unknown(\"Bad Request: can't remove chat owner\"))")
+        .await;
+    bot.update(group_message(&format!("/mute {}", MockMe::ID)));
+    bot.dispatch_and_check_last_text("Api(CantRestrictSelf)")
+        .await;
+}
+
+#[tokio::test]
+async fn test_bot_blocked() {
+    // The user blocked the bot
+    let bot = MockBot::new(
+        MockMyChatMemberUpdated::new()
+            .chat(MockPrivateChat::new().build())
+            .banned(),
+        Update::filter_message().endpoint(moderation_handler),
+    );
+    bot.unhandled_updates(FailurePolicy::Collect);
+    bot.handler_errors(FailurePolicy::Collect);
+    bot.dispatch().await;
+    bot.update(MockMessageText::new().text("/status"));
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    assert!(responses.sent_messages.is_empty());
+    assert!(responses.handler_errors[0].error.contains("BotBlocked"));
+}
+
+#[tokio::test]
+async fn test_bot_kicked() {
+    let bot = MockBot::new(
+        MockMyChatMemberUpdated::new().banned(),
+        Update::filter_message().endpoint(moderation_handler),
+    );
+    bot.unhandled_updates(FailurePolicy::Collect);
+    bot.handler_errors(FailurePolicy::Collect);
+    bot.dispatch().await;
+    bot.update(group_message("/status"));
+    bot.dispatch().await;
+
+    let responses = bot.get_responses();
+    assert!(responses.sent_messages.is_empty());
+    assert!(responses.handler_errors[0].error.contains("BotKicked"));
+}
+
+fn rights_to_restrict() -> teloxide::types::ChatAdministratorRights {
+    teloxide::types::ChatAdministratorRights {
+        is_anonymous: false,
+        can_manage_chat: true,
+        can_delete_messages: false,
+        can_manage_video_chats: false,
+        can_restrict_members: true,
+        can_promote_members: true,
+        can_change_info: false,
+        can_invite_users: false,
+        can_post_messages: None,
+        can_edit_messages: None,
+        can_pin_messages: Some(false),
+        can_post_stories: None,
+        can_edit_stories: None,
+        can_delete_stories: None,
+        can_manage_topics: None,
+    }
+}
+
+#[tokio::test]
+async fn test_administrator_rights() {
+    let bot = MockBot::new(
+        group_message("/pin"),
+        Update::filter_message().endpoint(moderation_handler),
+    );
+    bot.me(MockMe::new().administrator_rights(rights_to_restrict()));
+    bot.dispatch_and_check_last_text("Api(NotEnoughRightsToPinMessage)")
+        .await;
+    bot.update(group_message("/delete"));
+    bot.dispatch_and_check_last_text("Api(MessageCantBeDeleted)")
+        .await;
+    bot.update(group_message("/mute 3"));
+    bot.dispatch_and_check_last_text("Restricted").await;
+
+    // The bot can't give the rights that it doesn't have
+    bot.update(group_message("/promote 3"));
+    bot.dispatch_and_check_last_text("Api(Unknown(\"Bad Request: RIGHT_FORBIDDEN\"))")
+        .await;
+
+    bot.update(group_message(&format!("/status {}", MockMe::ID)));
+    bot.dispatch_and_check_last_text("Administrator").await;
+
+    // In private chats the bot can do anything
+    bot.update(MockMessageText::new().text("/pin"));
+    bot.dispatch().await;
+    assert_eq!(bot.get_responses().pinned_chat_messages.len(), 1);
+}
+
+#[tokio::test]
+async fn test_administrator_rights_in_chat() {
+    let bot = MockBot::new(
+        group_message("/ban 3"),
+        Update::filter_message().endpoint(moderation_handler),
+    );
+    // The bot is just a member of this chat
+    bot.chat_members(
+        MockGroupChat::ID,
+        vec![MockChatMember::new().user(MockMe::new().build().user)],
+    );
+    bot.dispatch_and_check_last_text("Api(NotEnoughRightsToRestrict)")
+        .await;
+    bot.update(group_message(&format!("/status {}", MockMe::ID)));
+    bot.dispatch_and_check_last_text("Member").await;
+}
+
+#[tokio::test]
+async fn test_custom_title() {
+    let bot = MockBot::new(
+        vec![group_message("/promote 3"), group_message("/title 3")],
+        Update::filter_message().endpoint(moderation_handler),
+    );
+    bot.chat_members(
+        MockGroupChat::ID,
+        vec![
+            MockChatMember::owner().user(MockUser::new().id(1).build()),
+            MockChatMember::administrator().user(MockUser::new().id(2).build()),
+            MockChatMember::new().user(MockUser::new().id(3).build()),
+            MockChatMember::new().user(MockUser::new().id(4).build()),
+        ],
+    );
+    bot.dispatch_and_check_last_text("Administrator").await;
+    let responses = bot.get_responses();
+    assert_eq!(
+        responses.set_chat_administrator_custom_titles[0].custom_title,
+        "Moderator"
+    );
+
+    bot.update(group_message("/title 4"));
+    bot.dispatch_and_check_last_text("Api(Unknown(\"Bad Request: user is not an administrator\"))")
+        .await;
+    // The bot didn't promote this administrator
+    bot.update(group_message("/title 2"));
+    bot.dispatch_and_check_last_text(
+        "Api(Unknown(\"Bad Request: not enough rights to change custom title of the user\"))",
+    )
+    .await;
+    bot.update(group_message("/promote 1"));
+    bot.dispatch_and_check_last_text("Api(CantDemoteChatCreator)")
+        .await;
+
+    // The bot can't change the titles without the right to promote
+    bot.me(
+        MockMe::new().administrator_rights(teloxide::types::ChatAdministratorRights {
+            can_promote_members: false,
+            ..rights_to_restrict()
+        }),
+    );
+    bot.chat_members(
+        MockGroupChat::ID,
+        vec![MockChatMember::new().user(MockUser::new().id(3).build())],
+    );
+    bot.update(group_message("/title 3"));
+    bot.dispatch_and_check_last_text("Api(Unknown(\"Bad Request: not enough rights\"))")
+        .await;
+}
+
+async fn chat_settings_handler(
+    bot: Bot,
+    message: Message,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let text = message.text().unwrap();
+    let chat_id = message.chat.id;
+    let (command, argument) = text.split_once(' ').unwrap_or((text, ""));
+    let result = match command {
+        "/title" => bot.set_chat_title(chat_id, argument).await.map(|_| ()),
+        "/description" => bot
+            .set_chat_description(chat_id)
+            .description(argument)
+            .await
+            .map(|_| ()),
+        "/photo" => bot
+            .set_chat_photo(chat_id, InputFile::memory("photo").file_name("photo.jpg"))
+            .await
+            .map(|_| ()),
+        "/delete_photo" => bot.delete_chat_photo(chat_id).await.map(|_| ()),
+        "/lock" => bot
+            .set_chat_permissions(chat_id, ChatPermissions::empty())
+            .await
+            .map(|_| ()),
+        _ => Ok(()),
+    };
+    let text = match result {
+        Ok(()) => {
+            let chat = bot.get_chat(chat_id).await?;
+            format!(
+                "{} | {} | {} | {:?}",
+                chat.title().unwrap_or_default(),
+                chat.description().unwrap_or_default(),
+                chat.photo.is_some(),
+                chat.permissions().map(|permissions| permissions.is_empty())
+            )
+        }
+        Err(error) => format!("{:?}", error),
+    };
+    bot.send_message(chat_id, text).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_chat_settings() {
+    let bot = MockBot::new(
+        group_message("/title New title"),
+        Update::filter_message().endpoint(chat_settings_handler),
+    );
+    bot.dispatch_and_check_last_text("New title |  | false | None")
+        .await;
+    assert_eq!(bot.get_responses().set_chat_titles[0].title, "New title");
+
+    // The changes stay between the dispatches
+    bot.update(group_message("/description About the chat"));
+    bot.dispatch_and_check_last_text("New title | About the chat | false | None")
+        .await;
+
+    bot.update(group_message("/photo"));
+    bot.dispatch_and_check_last_text("New title | About the chat | true | None")
+        .await;
+    let photo = bot.get_responses().set_chat_photos[0].photo.clone();
+    assert_ne!(photo.small_file_id, photo.big_file_id);
+
+    bot.update(group_message("/lock"));
+    bot.dispatch_and_check_last_text("New title | About the chat | true | Some(true)")
+        .await;
+    assert!(bot.get_responses().set_chat_permissions[0]
+        .permissions
+        .is_empty());
+
+    bot.update(group_message("/delete_photo"));
+    bot.dispatch_and_check_last_text("New title | About the chat | false | Some(true)")
+        .await;
+    assert_eq!(bot.get_responses().deleted_chat_photos.len(), 1);
+
+    bot.update(group_message("/description"));
+    bot.dispatch_and_check_last_text("New title |  | false | Some(true)")
+        .await;
+}
+
+#[tokio::test]
+async fn test_chat_settings_errors() {
+    let bot = MockBot::new(
+        group_message("/title Title"),
+        Update::filter_message().endpoint(chat_settings_handler),
+    );
+    bot.chat(MockGroupChat::new().title("Title").build());
+    bot.dispatch_and_check_last_text("Api(Unknown(\"Bad Request: chat title is not modified\"))")
+        .await;
+    bot.update(group_message("/delete_photo"));
+    bot.dispatch_and_check_last_text("Api(Unknown(\"Bad Request: CHAT_NOT_MODIFIED\"))")
+        .await;
+    bot.update(group_message("/description"));
+    bot.dispatch_and_check_last_text("Api(ChatDescriptionIsNotModified)")
+        .await;
+
+    bot.update(MockMessageText::new().text("/title Title"));
+    bot.dispatch_and_check_last_text(
+        "Api(Unknown(\"Bad Request: method is not available for private chats\"))",
+    )
+    .await;
+    bot.update(
+        MockMessageText::new()
+            .text("/lock")
+            .chat(MockChannelChat::new().id(-100).build()),
+    );
+    bot.dispatch_and_check_last_text(
+        "Api(Unknown(\"Bad Request: method is available for supergroup and group chats only\"))",
+    )
+    .await;
+
+    // The bot can't change the info without the right to
+    bot.me(MockMe::new().administrator_rights(rights_to_restrict()));
+    bot.update(group_message("/title Another title"));
+    bot.dispatch_and_check_last_text("Api(Unknown(\"Bad Request: CHAT_ADMIN_REQUIRED\"))")
+        .await;
+}
+
+#[tokio::test]
+async fn test_chat_from_mock_bot() {
+    let bot = MockBot::new(
+        group_message("/show"),
+        Update::filter_message().endpoint(chat_settings_handler),
+    );
+    // The chats of the updates don't replace the known ones
+    bot.chat(
+        MockGroupChat::new()
+            .title("Known chat")
+            .description("Description")
+            .build(),
+    );
+    bot.dispatch_and_check_last_text("Known chat | Description | false | None")
+        .await;
+}
@@ -233,23 +233,27 @@ pub fn serialize_raw_fields_derive(input: TokenStream) -> TokenStream {
                 let (file_name, file_data) = match attachment {
                     Some(attachment) => {
                         let attach = attachments.get_key_value(attachment)?;
-                        (attach.1.file_name.clone(), &attach.1.file_data)
+                        (attach.1.file_name.clone(), attach.1.file_data.clone())
                     },
-                    None => match file_type {
-                        FileType::Photo => ("no_name.jpg".to_string(), fields.get("photo")?),
-                        FileType::Video => ("no_name.mp4".to_string(), fields.get("video")?),
-                        FileType::Audio => ("no_name.mp3".to_string(), fields.get("audio")?),
-                        FileType::Document => ("no_name.txt".to_string(), fields.get("document")?),
-                        FileType::Sticker => ("no_name.png".to_string(), fields.get("sticker")?),
-                        FileType::Voice => ("no_name.mp3".to_string(), fields.get("voice")?),
-                        FileType::VideoNote => ("no_name.mp4".to_string(), fields.get("video_note")?),
-                        FileType::Animation => ("no_name.gif".to_string(), fields.get("animation")?),
+                    None => {
+                        // No attachment means that a file_id or a url was sent, so it is the "data"
+                        let (file_name, field) = match file_type {
+                            FileType::Photo => ("no_name.jpg", "photo"),
+                            FileType::Video => ("no_name.mp4", "video"),
+                            FileType::Audio => ("no_name.mp3", "audio"),
+                            FileType::Document => ("no_name.txt", "document"),
+                            FileType::Sticker => ("no_name.png", "sticker"),
+                            FileType::Voice => ("no_name.mp3", "voice"),
+                            FileType::VideoNote => ("no_name.mp4", "video_note"),
+                            FileType::Animation => ("no_name.gif", "animation"),
+                        };
+                        (file_name.to_string(), fields.get(field)?.as_bytes().to_vec())
                     },
                 };
 
                 Some(#name {
-                    file_name: file_name.to_string(),
-                    file_data: file_data.to_string(),
+                    file_name,
+                    file_data,
                     #(#field_serializers)*
                 })
             }